@@ -0,0 +1,74 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::{collections::HashMap, hint::black_box, thread};
+
+use context::SharedContext;
+use test::Bencher;
+
+const READER_COUNT: usize = 4;
+
+fn config() -> SharedContext<HashMap<String, String>> {
+    let config: HashMap<String, String> = (0..64)
+        .map(|index| (format!("key_{index}"), format!("value_{index}")))
+        .collect();
+
+    SharedContext::from(config)
+}
+
+#[bench]
+fn bench_load_epoch(b: &mut Bencher) {
+    let context = config();
+
+    b.iter(|| {
+        let current = context.load();
+        black_box(current.as_ref().get("key_0").cloned())
+    });
+}
+
+#[bench]
+fn bench_load_arc(b: &mut Bencher) {
+    let context = config();
+
+    b.iter(|| {
+        let current = context.load_arc();
+        black_box(current.get("key_0").cloned())
+    });
+}
+
+#[bench]
+fn bench_load_epoch_contended(b: &mut Bencher) {
+    let context = config();
+
+    b.iter(|| {
+        thread::scope(|scope| {
+            for _ in 0..READER_COUNT {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        let current = context.load();
+                        black_box(current.as_ref().get("key_0"));
+                    }
+                });
+            }
+        })
+    });
+}
+
+#[bench]
+fn bench_load_arc_contended(b: &mut Bencher) {
+    let context = config();
+
+    b.iter(|| {
+        thread::scope(|scope| {
+            for _ in 0..READER_COUNT {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        let current = context.load_arc();
+                        black_box(current.get("key_0"));
+                    }
+                });
+            }
+        })
+    });
+}
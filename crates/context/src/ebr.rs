@@ -1,7 +1,15 @@
-use crossbeam_epoch::{Atomic, Guard, Owned};
 use std::{
     marker::PhantomData,
-    sync::{atomic::Ordering, Arc},
+    sync::{atomic::Ordering, Arc, Mutex},
+};
+
+use crossbeam_epoch::{Atomic, Guard, Owned};
+use crossbeam_utils::Backoff;
+use tokio::sync::watch;
+
+use crate::{
+    history::{History, Version},
+    subscriber::Subscriber,
 };
 
 /// Application-wide state management using epoch-based memory reclamation.
@@ -9,8 +17,13 @@ use std::{
 /// helps reduce the read overhead of Mutex when multiple threads access the
 /// same object that is written rarely (where `T` is more than 90% read and less
 /// than 10% write).
+///
+/// The context is kept behind an [`Arc`] so that [`SharedContext::load_arc()`]
+/// can hand out snapshots which outlive the epoch guard.
 pub struct SharedContext<T> {
-    ptr: Arc<Atomic<T>>,
+    ptr: Arc<Atomic<Arc<T>>>,
+    version: Arc<watch::Sender<u64>>,
+    history: Option<Arc<Mutex<History<T>>>>,
 }
 
 unsafe impl<T> Send for SharedContext<T> {}
@@ -21,44 +34,185 @@ impl<T> Clone for SharedContext<T> {
     fn clone(&self) -> Self {
         Self {
             ptr: self.ptr.clone(),
+            version: self.version.clone(),
+            history: self.history.clone(),
         }
     }
 }
 
 impl<T> From<T> for SharedContext<T> {
     fn from(value: T) -> Self {
-        Self {
-            ptr: Arc::new(Atomic::new(value)),
-        }
+        Self::from_arc(Arc::new(value))
     }
 }
 
 impl<T> SharedContext<T> {
-    fn as_ptr(&self) -> Arc<Atomic<T>> {
+    fn as_ptr(&self) -> Arc<Atomic<Arc<T>>> {
         self.ptr.clone()
     }
 
+    pub(crate) fn from_arc(context: Arc<T>) -> Self {
+        let (version, _) = watch::channel(0);
+
+        Self {
+            ptr: Arc::new(Atomic::new(context)),
+            version: Arc::new(version),
+            history: None,
+        }
+    }
+
+    /// Whether both are clones of the same context.
+    pub(crate) fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.ptr, &other.ptr)
+    }
+
+    /// Create the context that keeps the last `capacity` versions including the
+    /// current one so that it can be rolled back with
+    /// [`SharedContext::rollback_to()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context::SharedContext;
+    ///
+    /// let context = SharedContext::with_history(u8::from(1), 8);
+    /// context.store(2);
+    /// context.store(3);
+    ///
+    /// for version in context.history() {
+    ///     println!("{}: {:?}", version.version(), version.as_ref()); // Prints '0: 1', '1: 2', '2: 3'
+    /// }
+    ///
+    /// context.rollback_to(0).unwrap();
+    ///
+    /// let current = context.load();
+    /// let count = current.as_ref();
+    /// println!("{:?}", count); // Prints '1'
+    /// ```
+    pub fn with_history(value: T, capacity: usize) -> Self {
+        let value = Arc::new(value);
+
+        let mut history = History::new(capacity);
+        history.push(0, value.clone());
+
+        let (version, _) = watch::channel(0);
+
+        Self {
+            ptr: Arc::new(Atomic::new(value)),
+            version: Arc::new(version),
+            history: Some(Arc::new(Mutex::new(history))),
+        }
+    }
+
+    /// Replace the context by calling `operation` while holding the version
+    /// lock so that the versions and the history follow the order of writes.
+    /// `operation` returns `false` if the context has not been replaced, in
+    /// which case the subscribers are not notified.
+    fn publish<F>(&self, context: &Arc<T>, operation: F) -> bool
+    where
+        F: FnOnce() -> bool,
+    {
+        self.version.send_if_modified(|version| {
+            if !operation() {
+                return false;
+            }
+
+            *version += 1;
+            if let Some(history) = &self.history {
+                history.lock().unwrap().push(*version, context.clone());
+            }
+
+            true
+        })
+    }
+
+    /// The number of times the context has been replaced. The version starts
+    /// at `0` and increases by one on every successful
+    /// [`SharedContext::store()`], [`SharedContext::update()`] and
+    /// [`SharedContext::fetch_update()`].
+    pub fn version(&self) -> u64 {
+        *self.version.borrow()
+    }
+
+    /// Subscribe to the changes of the context. The returned [`Subscriber`]
+    /// only wakes up on versions newer than the current one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context::SharedContext;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let context = SharedContext::from(u8::from(1));
+    /// let mut subscriber = context.subscribe();
+    ///
+    /// context.store(2);
+    ///
+    /// let version = subscriber.changed().await;
+    /// println!("{:?}", version); // Prints '1'
+    ///
+    /// let current = subscriber.load();
+    /// let count = current.as_ref();
+    /// println!("{:?}", count); // Prints '2'
+    /// # }
+    /// ```
+    pub fn subscribe(&self) -> Subscriber<T> {
+        Subscriber::new(self.clone(), self.version.subscribe())
+    }
+
     /// Thread-safe getter for the current context.
     ///
     /// # Examples
     ///
     /// ```
+    /// use context::SharedContext;
+    ///
     /// let context = SharedContext::from(u8::from(1));
     ///
     /// let current = context.load();
-    /// let count = local.as_ref();
-    /// println!("{:?}", user); // Prints '1'
+    /// let count = current.as_ref();
+    /// println!("{:?}", count); // Prints '1'
     /// ```
     pub fn load(&self) -> Context<T> {
         Context::new(self.clone())
     }
 
+    /// Thread-safe getter for an owned snapshot of the current context. Unlike
+    /// [`Context<T>`], the snapshot is [`Send`] and can be held across `.await`
+    /// points. It stays the same even if the context is replaced afterwards.
+    ///
+    /// Prefer [`SharedContext::load()`] for short-lived reads because the
+    /// snapshot requires updating the reference count shared by all threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context::SharedContext;
+    ///
+    /// let context = SharedContext::from(u8::from(1));
+    ///
+    /// let snapshot = context.load_arc();
+    /// context.store(2);
+    ///
+    /// println!("{:?}", snapshot); // Prints '1'
+    /// ```
+    pub fn load_arc(&self) -> Arc<T> {
+        let guard = crossbeam_epoch::pin();
+        let current_context = self.ptr.load(Ordering::SeqCst, &guard);
+
+        unsafe { current_context.deref() }.clone()
+    }
+
     /// Thread-safe setter to save the new context `T`. After calling the
-    /// function, the previous context becomes unreachable.
+    /// function, the previous context becomes unreachable unless it is kept in
+    /// the history (see [`SharedContext::with_history()`]).
     ///
     /// # Examples
     ///
     /// ```
+    /// use context::SharedContext;
+    ///
     /// let context = SharedContext::from(u8::from(1));
     ///
     /// let current = context.load();
@@ -74,10 +228,19 @@ impl<T> SharedContext<T> {
     /// println!("{:?}", count); // Prints '2'
     /// ```
     pub fn store(&self, context: T) {
+        self.store_arc(Arc::new(context));
+    }
+
+    pub(crate) fn store_arc(&self, context: Arc<T>) {
         let guard = crossbeam_epoch::pin();
-        let previous_context = self.ptr.swap(Owned::new(context), Ordering::SeqCst, &guard);
+        self.publish(&context, || {
+            let previous_context =
+                self.ptr
+                    .swap(Owned::new(context.clone()), Ordering::SeqCst, &guard);
+            unsafe { guard.defer_destroy(previous_context) }
 
-        unsafe { guard.defer_destroy(previous_context) }
+            true
+        });
         guard.flush();
     }
 
@@ -87,8 +250,9 @@ impl<T> SharedContext<T> {
     ///
     /// The difference between [`SharedContext::update()`] and
     /// [`SharedContext::store()`] is that the former may fail when there
-    /// are two or more threads updating the current context. Use this function
-    /// if the new state depends on the current state.
+    /// are two or more threads updating the current context. Use
+    /// [`SharedContext::fetch_update()`] if the new state has to be computed
+    /// from the current state.
     ///
     /// # CAVEAT
     /// Although it is okay to have more than one thread updating the current
@@ -98,6 +262,8 @@ impl<T> SharedContext<T> {
     /// # Examples
     ///
     /// ```
+    /// use context::SharedContext;
+    ///
     /// let context = SharedContext::from(u8::from(1));
     ///
     /// let current = context.load();
@@ -113,20 +279,188 @@ impl<T> SharedContext<T> {
     /// println!("{:?}", count);
     /// ```
     pub fn update(&self, context: T) -> Result<(), ContextError> {
+        let context = Arc::new(context);
+
         let guard = crossbeam_epoch::pin();
         let current_context = self.ptr.load(Ordering::SeqCst, &guard);
-        self.ptr
-            .compare_exchange(
+        let is_updated = self.publish(&context, || {
+            match self.ptr.compare_exchange(
                 current_context,
-                Owned::new(context),
+                Owned::new(context.clone()),
                 Ordering::SeqCst,
                 Ordering::SeqCst,
                 &guard,
-            )
-            .map_err(|_| ContextError::Update)?;
+            ) {
+                Ok(_) => {
+                    unsafe { guard.defer_destroy(current_context) }
+
+                    true
+                }
+                Err(_) => false,
+            }
+        });
+        if !is_updated {
+            return Err(ContextError::Update);
+        }
+        guard.flush();
+
+        Ok(())
+    }
+
+    /// Read-copy-update the current context. `function` receives the current
+    /// context and returns the new one, which replaces the current context
+    /// only if no other thread has changed it in the meantime. Otherwise,
+    /// `function` is called again with the newer context according to
+    /// `retry`. Candidates that lose the race are dropped right away because
+    /// they have never been visible to other threads.
+    ///
+    /// Note that `function` may be called more than once, so it should not
+    /// have side effects.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use context::{Retry, SharedContext};
+    ///
+    /// let context = SharedContext::from(u8::from(1));
+    ///
+    /// context
+    ///     .fetch_update(Retry::Bounded(8), |count| count + 1)
+    ///     .unwrap();
+    ///
+    /// let current = context.load();
+    /// let count = current.as_ref();
+    /// println!("{:?}", count); // Prints '2'
+    /// ```
+    pub fn fetch_update<F>(&self, retry: Retry, mut function: F) -> Result<(), ContextError>
+    where
+        F: FnMut(&T) -> T,
+    {
+        let backoff = Backoff::new();
+        let mut attempt: usize = 0;
+
+        let guard = crossbeam_epoch::pin();
+        let mut current_context = self.ptr.load(Ordering::SeqCst, &guard);
+        loop {
+            let new_context = Arc::new(unsafe { function(current_context.deref().as_ref()) });
+            let is_updated = self.publish(&new_context, || {
+                match self.ptr.compare_exchange(
+                    current_context,
+                    Owned::new(new_context.clone()),
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                    &guard,
+                ) {
+                    Ok(_) => {
+                        unsafe { guard.defer_destroy(current_context) }
+
+                        true
+                    }
+                    Err(error) => {
+                        current_context = error.current;
+
+                        false
+                    }
+                }
+            });
+            if is_updated {
+                guard.flush();
+
+                return Ok(());
+            }
+
+            // The losing candidate has never been shared, so it is freed
+            // immediately instead of being deferred.
+            drop(new_context);
+
+            attempt += 1;
+            if !retry.is_allowed(attempt) {
+                return Err(ContextError::Update);
+            }
+            if retry.is_backoff() {
+                backoff.snooze();
+            }
+        }
+    }
+
+    /// The recorded versions from the oldest to the newest. Empty unless the
+    /// context was created with [`SharedContext::with_history()`].
+    pub fn history(&self) -> Vec<Version<T>> {
+        match &self.history {
+            Some(history) => history.lock().unwrap().to_vec(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the recorded context for `version`.
+    pub fn get_version(&self, version: u64) -> Result<Version<T>, ContextError> {
+        self.history
+            .as_ref()
+            .and_then(|history| history.lock().unwrap().get(version))
+            .ok_or(ContextError::VersionNotFound(version))
+    }
+
+    /// Store the context recorded for `version` as the new context. Rolling
+    /// back creates a new version instead of discarding the newer ones, so
+    /// the rollback itself can be rolled back.
+    pub fn rollback_to(&self, version: u64) -> Result<(), ContextError> {
+        let version = self.get_version(version)?;
+        self.store_arc(version.context());
 
         Ok(())
     }
+
+    /// Compare the contexts recorded for `from` and `to` with `function`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::BTreeSet;
+    ///
+    /// use context::SharedContext;
+    ///
+    /// let context = SharedContext::with_history(BTreeSet::from([1, 2]), 8);
+    /// context.store(BTreeSet::from([2, 3]));
+    ///
+    /// let added: Vec<i32> = context
+    ///     .diff(0, 1, |from, to| to.difference(from).cloned().collect())
+    ///     .unwrap();
+    /// println!("{:?}", added); // Prints '[3]'
+    /// ```
+    pub fn diff<F, D>(&self, from: u64, to: u64, function: F) -> Result<D, ContextError>
+    where
+        F: FnOnce(&T, &T) -> D,
+    {
+        let from = self.get_version(from)?;
+        let to = self.get_version(to)?;
+
+        Ok(function(from.as_ref(), to.as_ref()))
+    }
+}
+
+/// Retry policy for [`SharedContext::fetch_update()`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Retry {
+    /// Retry until the update succeeds.
+    Unbounded,
+    /// Retry immediately up to the given number of times.
+    Bounded(usize),
+    /// Retry up to the given number of times, backing off exponentially
+    /// between the attempts.
+    Backoff(usize),
+}
+
+impl Retry {
+    fn is_allowed(&self, attempt: usize) -> bool {
+        match self {
+            Self::Unbounded => true,
+            Self::Bounded(max_retries) | Self::Backoff(max_retries) => attempt <= *max_retries,
+        }
+    }
+
+    fn is_backoff(&self) -> bool {
+        matches!(self, Self::Backoff(_))
+    }
 }
 
 pub struct Context<T> {
@@ -143,6 +477,7 @@ impl<T> AsRef<T> for Context<T> {
                 .load(Ordering::SeqCst, &self.guard)
                 .as_ref()
                 .unwrap()
+                .as_ref()
         }
     }
 }
@@ -162,6 +497,8 @@ struct NotSend(*const ());
 
 pub enum ContextError {
     Update,
+    VersionNotFound(u64),
+    KeyNotFound(String),
 }
 
 impl std::fmt::Debug for ContextError {
@@ -170,6 +507,8 @@ impl std::fmt::Debug for ContextError {
             // If you are seeing this error too often, check if there's more than one thread/task
             // updating the context concurrently.
             Self::Update => write!(f, "Context changed while getting updated"),
+            Self::VersionNotFound(version) => write!(f, "Version {version} is not in the history"),
+            Self::KeyNotFound(key) => write!(f, "Key {key} does not exist"),
         }
     }
 }
@@ -181,3 +520,130 @@ impl std::fmt::Display for ContextError {
 }
 
 impl std::error::Error for ContextError {}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_fetch_update_concurrent() {
+        let context = SharedContext::from(0_usize);
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let context = context.clone();
+                thread::spawn(move || {
+                    for _ in 0..1000 {
+                        context
+                            .fetch_update(Retry::Unbounded, |count| count + 1)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(*context.load().as_ref(), 8000);
+    }
+
+    #[test]
+    fn test_fetch_update_retry() {
+        let context = SharedContext::from(0_usize);
+
+        // Simulate a concurrent writer on the first attempt.
+        let mut raced = false;
+        let result = context.fetch_update(Retry::Bounded(0), |count| {
+            if !raced {
+                raced = true;
+                context.store(10);
+            }
+            count + 1
+        });
+        assert!(result.is_err());
+        assert_eq!(*context.load().as_ref(), 10);
+
+        let mut raced = false;
+        context
+            .fetch_update(Retry::Backoff(1), |count| {
+                if !raced {
+                    raced = true;
+                    context.store(20);
+                }
+                count + 1
+            })
+            .unwrap();
+        assert_eq!(*context.load().as_ref(), 21);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let context = SharedContext::from(0_usize);
+        let mut subscriber = context.subscribe();
+        assert_eq!(subscriber.version(), 0);
+
+        let writer = context.clone();
+        let handle = tokio::spawn(async move {
+            writer.store(1);
+            writer.update(2).unwrap();
+            writer
+                .fetch_update(Retry::Unbounded, |count| count + 1)
+                .unwrap();
+        });
+
+        let mut version = 0;
+        while version < 3 {
+            let changed = subscriber.changed().await;
+            assert!(changed > version);
+            version = changed;
+        }
+        handle.await.unwrap();
+
+        assert_eq!(context.version(), 3);
+        assert_eq!(*subscriber.load().as_ref(), 3);
+    }
+
+    #[test]
+    fn test_history() {
+        let context = SharedContext::with_history(0_usize, 3);
+        for count in 1..=4 {
+            context.store(count);
+        }
+
+        let versions: Vec<(u64, usize)> = context
+            .history()
+            .iter()
+            .map(|version| (version.version(), *version.as_ref()))
+            .collect();
+        assert_eq!(versions, vec![(2, 2), (3, 3), (4, 4)]);
+        assert!(context.rollback_to(1).is_err());
+
+        context.rollback_to(2).unwrap();
+        assert_eq!(*context.load().as_ref(), 2);
+        assert_eq!(context.version(), 5);
+
+        let delta = context.diff(3, 4, |from, to| to - from).unwrap();
+        assert_eq!(delta, 1);
+
+        assert!(SharedContext::from(0_usize).history().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_load_arc() {
+        let context = SharedContext::from(String::from("first"));
+
+        let snapshot = context.load_arc();
+        let handle = tokio::spawn(async move {
+            tokio::task::yield_now().await;
+
+            snapshot
+        });
+        context.store(String::from("second"));
+
+        assert_eq!(handle.await.unwrap().as_str(), "first");
+        assert_eq!(context.load_arc().as_str(), "second");
+    }
+}
@@ -0,0 +1,93 @@
+use std::{collections::VecDeque, sync::Arc, time::SystemTime};
+
+/// A past or current context recorded by [`crate::SharedContext`] when it is
+/// created with [`crate::SharedContext::with_history()`].
+pub struct Version<T> {
+    version: u64,
+    timestamp: SystemTime,
+    context: Arc<T>,
+}
+
+impl<T> AsRef<T> for Version<T> {
+    fn as_ref(&self) -> &T {
+        &self.context
+    }
+}
+
+impl<T> Clone for Version<T> {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version,
+            timestamp: self.timestamp,
+            context: self.context.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Version<T>
+where
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Version")
+            .field("version", &self.version)
+            .field("timestamp", &self.timestamp)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+impl<T> Version<T> {
+    /// Equivalent to [`crate::SharedContext::version()`] right after the
+    /// context was stored.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// The time at which the context was stored.
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+
+    pub fn context(&self) -> Arc<T> {
+        self.context.clone()
+    }
+}
+
+/// Bounded queue of the latest versions, from the oldest to the newest.
+pub(crate) struct History<T> {
+    capacity: usize,
+    versions: VecDeque<Version<T>>,
+}
+
+impl<T> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            versions: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, version: u64, context: Arc<T>) {
+        self.versions.push_back(Version {
+            version,
+            timestamp: SystemTime::now(),
+            context,
+        });
+
+        while self.versions.len() > self.capacity {
+            self.versions.pop_front();
+        }
+    }
+
+    pub fn get(&self, version: u64) -> Option<Version<T>> {
+        self.versions
+            .iter()
+            .find(|entry| entry.version == version)
+            .cloned()
+    }
+
+    pub fn to_vec(&self) -> Vec<Version<T>> {
+        self.versions.iter().cloned().collect()
+    }
+}
@@ -1,2 +1,12 @@
 mod ebr;
-pub use ebr::{Context, ContextError, SharedContext};
+mod history;
+mod map;
+#[cfg(feature = "kvstore")]
+mod persistent;
+mod subscriber;
+pub use ebr::{Context, ContextError, Retry, SharedContext};
+pub use history::Version;
+pub use map::SharedMap;
+#[cfg(feature = "kvstore")]
+pub use persistent::PersistentContext;
+pub use subscriber::Subscriber;
@@ -0,0 +1,267 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+};
+
+use crate::{Context, ContextError, Retry, SharedContext};
+
+/// Keyed version of [`SharedContext`] where each entry is a [`SharedContext`]
+/// of its own. Reading an entry and writing to an existing key are lock-free
+/// and replace only that entry. Adding and removing keys are serialized with
+/// each other, and copy the index of entries but never the values.
+///
+/// # Examples
+///
+/// ```
+/// use context::SharedMap;
+///
+/// let rollups = SharedMap::<String, Vec<String>>::default();
+/// rollups.store("rollup_id".to_owned(), vec!["executor".to_owned()]);
+///
+/// let executors = rollups.load("rollup_id").unwrap();
+/// println!("{:?}", executors.as_ref()); // Prints '["executor"]'
+/// ```
+pub struct SharedMap<K, V> {
+    entries: SharedContext<HashMap<K, SharedContext<V>>>,
+    lock: Arc<Mutex<()>>,
+}
+
+impl<K, V> Clone for SharedMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+            lock: self.lock.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for SharedMap<K, V> {
+    fn default() -> Self {
+        Self {
+            entries: SharedContext::from(HashMap::new()),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for SharedMap<K, V>
+where
+    K: Eq + Hash,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let entries: HashMap<K, SharedContext<V>> = iter
+            .into_iter()
+            .map(|(key, value)| (key, SharedContext::from(value)))
+            .collect();
+
+        Self {
+            entries: SharedContext::from(entries),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+}
+
+impl<K, V> SharedMap<K, V>
+where
+    K: Clone + Eq + Hash,
+{
+    /// Get the [`SharedContext`] of the entry, which can be used to subscribe
+    /// to the changes of a single key.
+    pub fn get<Q>(&self, key: &Q) -> Option<SharedContext<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.load().as_ref().get(key).cloned()
+    }
+
+    /// Equivalent to [`SharedContext::load()`] for the entry.
+    pub fn load<Q>(&self, key: &Q) -> Option<Context<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get(key).map(|entry| entry.load())
+    }
+
+    /// Equivalent to [`SharedContext::load_arc()`] for the entry.
+    pub fn load_arc<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries
+            .load()
+            .as_ref()
+            .get(key)
+            .map(|entry| entry.load_arc())
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.load().as_ref().contains_key(key)
+    }
+
+    pub fn keys(&self) -> Vec<K> {
+        self.entries.load().as_ref().keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.load().as_ref().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.load().as_ref().is_empty()
+    }
+
+    /// Store the value for the key. Only the entry is replaced if the key
+    /// already exists, without blocking the writes to the other keys.
+    pub fn store(&self, key: K, value: V) {
+        let value = match self.get(&key) {
+            Some(entry) => {
+                entry.store(value);
+                if self.is_entry(&key, &entry) {
+                    return;
+                }
+
+                // The entry has been removed meanwhile, so the value is stored
+                // again in a new entry rather than lost.
+                entry.load_arc()
+            }
+            None => Arc::new(value),
+        };
+
+        let _lock = self.lock.lock().unwrap();
+        let mut entries = self.entries.load().as_ref().clone();
+        match entries.get(&key) {
+            Some(entry) => entry.store_arc(value),
+            None => {
+                entries.insert(key, SharedContext::from_arc(value));
+                self.entries.store(entries);
+            }
+        }
+    }
+
+    /// Equivalent to [`SharedContext::fetch_update()`] for the entry, which
+    /// runs `function` without blocking the other writes so that it can
+    /// write to the map itself. Returns [`ContextError::KeyNotFound`] if the
+    /// key does not exist or is removed while getting updated.
+    pub fn fetch_update<Q, F>(&self, key: &Q, retry: Retry, function: F) -> Result<(), ContextError>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + std::fmt::Debug + ?Sized,
+        F: FnMut(&V) -> V,
+    {
+        let key_not_found = || ContextError::KeyNotFound(format!("{key:?}"));
+        let entry = self.get(key).ok_or_else(key_not_found)?;
+        entry.fetch_update(retry, function)?;
+
+        match self.is_entry(key, &entry) {
+            true => Ok(()),
+            false => Err(key_not_found()),
+        }
+    }
+
+    /// Remove the key and return the last value.
+    pub fn remove<Q>(&self, key: &Q) -> Option<Arc<V>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let _lock = self.lock.lock().unwrap();
+
+        let mut entries = self.entries.load().as_ref().clone();
+        let entry = entries.remove(key)?;
+        self.entries.store(entries);
+
+        Some(entry.load_arc())
+    }
+
+    /// Whether the entry is still the one of the key.
+    fn is_entry<Q>(&self, key: &Q, entry: &SharedContext<V>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get(key).is_some_and(|current| current.ptr_eq(entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_shared_map() {
+        let map = SharedMap::<String, usize>::default();
+        assert!(map.is_empty());
+
+        let handles: Vec<_> = (0..4)
+            .map(|index| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    let key = format!("key_{index}");
+                    map.store(key.clone(), 0);
+                    for _ in 0..100 {
+                        map.fetch_update(&key, Retry::Unbounded, |count| count + 1)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(map.len(), 4);
+        for index in 0..4 {
+            let key = format!("key_{index}");
+            assert_eq!(*map.load(&key).unwrap().as_ref(), 100);
+        }
+
+        let entry = map.get("key_0").unwrap();
+        map.store("key_0".to_owned(), 1);
+        assert_eq!(*entry.load().as_ref(), 1);
+        assert_eq!(map.len(), 4);
+
+        assert_eq!(*map.remove("key_0").unwrap(), 1);
+        assert!(!map.contains_key("key_0"));
+        assert!(map.remove("key_0").is_none());
+        assert!(matches!(
+            map.fetch_update("key_0", Retry::Unbounded, |count| count + 1),
+            Err(ContextError::KeyNotFound(key)) if key == "\"key_0\""
+        ));
+    }
+
+    #[test]
+    fn test_shared_map_reentrant_update() {
+        let map = SharedMap::<&str, usize>::from_iter([("a", 0)]);
+
+        // The function can write to the map.
+        map.fetch_update("a", Retry::Unbounded, |count| {
+            map.store("b", *count);
+            map.remove("c");
+            count + 1
+        })
+        .unwrap();
+        assert_eq!(*map.load("a").unwrap().as_ref(), 1);
+        assert_eq!(*map.load("b").unwrap().as_ref(), 0);
+
+        // The key removed while getting updated is not found.
+        assert!(matches!(
+            map.fetch_update("a", Retry::Unbounded, |count| {
+                map.remove("a");
+                count + 1
+            }),
+            Err(ContextError::KeyNotFound(_))
+        ));
+        assert!(!map.contains_key("a"));
+    }
+}
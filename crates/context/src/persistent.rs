@@ -0,0 +1,316 @@
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use kvstore::{KvStore, KvStoreError};
+use serde::{de::DeserializeOwned, ser::Serialize};
+use tokio::task::AbortHandle;
+
+use crate::{Context, SharedContext, Subscriber};
+
+/// [`SharedContext`] bound to a key of [`KvStore`]. The context is loaded from
+/// the database on creation and every write goes through to the database, so
+/// the context survives restarts while reads stay as cheap as
+/// [`SharedContext::load()`].
+///
+/// [`kvstore::Model`] types are stored under `(Model::ID, key, ..)`, so the
+/// same tuple binds the context to the record of the model.
+///
+/// If the writes are debounced, dropping the last clone writes the pending
+/// changes but discards the error. Call [`PersistentContext::flush()`] before
+/// dropping it to handle the error.
+///
+/// # Examples
+///
+/// ```
+/// use context::PersistentContext;
+/// use kvstore::KvStore;
+///
+/// let database = KvStore::open(std::env::temp_dir().join("persistent_context_example")).unwrap();
+///
+/// let context = PersistentContext::load_or_default(database, "count").unwrap();
+/// context.fetch_update(|count: &u64| count + 1).unwrap();
+///
+/// // Prints the number of times the example has run.
+/// println!("{:?}", context.load().as_ref());
+/// ```
+pub struct PersistentContext<K, T> {
+    shared_context: SharedContext<T>,
+    inner: Arc<Inner<K>>,
+}
+
+struct Inner<K> {
+    kvstore: KvStore,
+    key: Arc<K>,
+    lock: Mutex<()>,
+    debounce: Mutex<Option<Debounce>>,
+}
+
+type Write = Box<dyn Fn() -> Result<(), KvStoreError> + Send + Sync>;
+
+struct Debounce {
+    abort_handle: AbortHandle,
+    error: Option<KvStoreError>,
+    /// Write the current context, which `Drop` calls without knowing `T`.
+    write: Write,
+}
+
+impl<K> Drop for Inner<K> {
+    fn drop(&mut self) {
+        if let Some(debounce) = self.debounce.get_mut().unwrap().take() {
+            debounce.abort_handle.abort();
+            // No task is left to write the pending changes.
+            let _ = (debounce.write)();
+        }
+    }
+}
+
+impl<K, T> Clone for PersistentContext<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared_context: self.shared_context.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, T> PersistentContext<K, T>
+where
+    K: Debug + Serialize + Send + Sync + 'static,
+    T: Debug + DeserializeOwned + Serialize + Send + Sync + 'static,
+{
+    /// Load the context from `key`, or put the value returned by `function`
+    /// if the key does not exist.
+    pub fn load_or<F>(kvstore: KvStore, key: K, function: F) -> Result<Self, KvStoreError>
+    where
+        F: FnOnce() -> T,
+    {
+        let context = match kvstore.get(&key) {
+            Ok(context) => context,
+            Err(KvStoreError::NoneType) => {
+                let context = function();
+                kvstore.put(&key, &context)?;
+
+                context
+            }
+            Err(error) => return Err(error),
+        };
+
+        Ok(Self {
+            shared_context: SharedContext::from(context),
+            inner: Arc::new(Inner {
+                kvstore,
+                key: Arc::new(key),
+                lock: Mutex::new(()),
+                debounce: Mutex::new(None),
+            }),
+        })
+    }
+
+    /// Load the context from `key`, or put `T::default()` if the key does not
+    /// exist.
+    pub fn load_or_default(kvstore: KvStore, key: K) -> Result<Self, KvStoreError>
+    where
+        T: Default,
+    {
+        Self::load_or(kvstore, key, T::default)
+    }
+
+    /// Defer the writes to the database until `interval` has passed since the
+    /// first unsaved change, so that a burst of changes is written once.
+    /// The context in memory is still replaced immediately.
+    ///
+    /// An error from a deferred write is returned by the next
+    /// [`PersistentContext::store()`], [`PersistentContext::fetch_update()`]
+    /// or [`PersistentContext::flush()`].
+    ///
+    /// # Panics
+    ///
+    /// Must be called inside a `tokio` runtime.
+    pub fn debounce(self, interval: Duration) -> Self {
+        let mut subscriber = self.shared_context.subscribe();
+        let inner = Arc::downgrade(&self.inner);
+        let shared_context = self.shared_context.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                subscriber.changed().await;
+                tokio::time::sleep(interval).await;
+
+                let Some(inner) = Weak::upgrade(&inner) else {
+                    break;
+                };
+                let shared_context = shared_context.clone();
+                // Keep the runtime worker free while RocksDB writes.
+                let _ = tokio::task::spawn_blocking(move || {
+                    let _lock = inner.lock.lock().unwrap();
+                    if let Err(error) = inner.write(&shared_context) {
+                        if let Some(debounce) = inner.debounce.lock().unwrap().as_mut() {
+                            debounce.error = Some(error);
+                        }
+                    }
+                })
+                .await;
+            }
+        });
+
+        let kvstore = self.inner.kvstore.clone();
+        let key = self.inner.key.clone();
+        let shared_context = self.shared_context.clone();
+        let previous = self.inner.debounce.lock().unwrap().replace(Debounce {
+            abort_handle: handle.abort_handle(),
+            error: None,
+            write: Box::new(move || kvstore.put(key.as_ref(), shared_context.load_arc().as_ref())),
+        });
+        if let Some(previous) = previous {
+            previous.abort_handle.abort();
+        }
+
+        self
+    }
+
+    pub fn key(&self) -> &K {
+        &self.inner.key
+    }
+
+    /// Equivalent to [`SharedContext::load()`].
+    pub fn load(&self) -> Context<T> {
+        self.shared_context.load()
+    }
+
+    /// Equivalent to [`SharedContext::load_arc()`].
+    pub fn load_arc(&self) -> Arc<T> {
+        self.shared_context.load_arc()
+    }
+
+    /// Equivalent to [`SharedContext::subscribe()`].
+    pub fn subscribe(&self) -> Subscriber<T> {
+        self.shared_context.subscribe()
+    }
+
+    /// Put the context to the database and replace the context in memory.
+    /// The context in memory is left unchanged if the database write fails.
+    /// If the writes are debounced, only the context in memory is replaced
+    /// and the database is updated later.
+    pub fn store(&self, context: T) -> Result<(), KvStoreError> {
+        let _lock = self.inner.lock.lock().unwrap();
+
+        if !self.inner.is_debounced()? {
+            self.inner.kvstore.put(self.key(), &context)?;
+        }
+        self.shared_context.store(context);
+
+        Ok(())
+    }
+
+    /// Compute the new context from the current one and store it like
+    /// [`PersistentContext::store()`]. Writers of the same
+    /// [`PersistentContext`] are serialized, so `function` is called exactly
+    /// once.
+    pub fn fetch_update<F>(&self, function: F) -> Result<(), KvStoreError>
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let _lock = self.inner.lock.lock().unwrap();
+
+        let context = function(self.shared_context.load().as_ref());
+        if !self.inner.is_debounced()? {
+            self.inner.kvstore.put(self.key(), &context)?;
+        }
+        self.shared_context.store(context);
+
+        Ok(())
+    }
+
+    /// Write the current context to the database immediately.
+    pub fn flush(&self) -> Result<(), KvStoreError> {
+        let _lock = self.inner.lock.lock().unwrap();
+
+        self.inner.is_debounced()?;
+        self.inner.write(&self.shared_context)
+    }
+}
+
+impl<K> Inner<K>
+where
+    K: Debug + Serialize,
+{
+    fn write<T>(&self, shared_context: &SharedContext<T>) -> Result<(), KvStoreError>
+    where
+        T: Debug + DeserializeOwned + Serialize,
+    {
+        self.kvstore
+            .put(self.key.as_ref(), shared_context.load_arc().as_ref())
+    }
+
+    /// Whether the writes are deferred by [`PersistentContext::debounce()`].
+    /// Returns the error of the last deferred write if it has failed.
+    fn is_debounced(&self) -> Result<bool, KvStoreError> {
+        match self.debounce.lock().unwrap().as_mut() {
+            Some(debounce) => match debounce.error.take() {
+                Some(error) => Err(error),
+                None => Ok(true),
+            },
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn database_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("context_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+
+        path
+    }
+
+    #[test]
+    fn test_write_through() {
+        let path = database_path("write_through");
+        let key = ("Config", 0_u64);
+
+        {
+            let kvstore = KvStore::open(&path).unwrap();
+            let context = PersistentContext::load_or(kvstore, key, || vec![1_u64]).unwrap();
+            context.store(vec![1, 2]).unwrap();
+            context
+                .fetch_update(|list| list.iter().map(|item| item * 10).collect())
+                .unwrap();
+        }
+
+        let kvstore = KvStore::open(&path).unwrap();
+        let context = PersistentContext::<_, Vec<u64>>::load_or_default(kvstore, key).unwrap();
+        assert_eq!(context.load().as_ref(), &vec![10, 20]);
+    }
+
+    #[tokio::test]
+    async fn test_debounce() {
+        let kvstore = KvStore::open(database_path("debounce")).unwrap();
+
+        let context = PersistentContext::load_or_default(kvstore.clone(), "count")
+            .unwrap()
+            .debounce(Duration::from_millis(50));
+        for count in 1..=10_u64 {
+            context.store(count).unwrap();
+        }
+        assert_eq!(kvstore.get::<_, u64>(&"count").unwrap(), 0);
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(kvstore.get::<_, u64>(&"count").unwrap(), 10);
+
+        context.store(11).unwrap();
+        context.flush().unwrap();
+        assert_eq!(kvstore.get::<_, u64>(&"count").unwrap(), 11);
+
+        context.store(12).unwrap();
+        drop(context);
+        assert_eq!(kvstore.get::<_, u64>(&"count").unwrap(), 12);
+    }
+}
@@ -0,0 +1,74 @@
+use tokio::sync::watch;
+
+use crate::{Context, SharedContext};
+
+/// Receives the version of [`SharedContext`] whenever the context is replaced.
+/// Created by [`SharedContext::subscribe()`].
+///
+/// # Examples
+///
+/// ```
+/// use context::SharedContext;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let context = SharedContext::from(u8::from(1));
+/// let mut subscriber = context.subscribe();
+///
+/// tokio::spawn(async move {
+///     loop {
+///         subscriber.changed().await;
+///
+///         let count = *subscriber.load().as_ref();
+///         println!("{:?}", count);
+///     }
+/// });
+///
+/// context.store(2);
+/// # }
+/// ```
+pub struct Subscriber<T> {
+    shared_context: SharedContext<T>,
+    receiver: watch::Receiver<u64>,
+}
+
+impl<T> Subscriber<T> {
+    pub(crate) fn new(shared_context: SharedContext<T>, receiver: watch::Receiver<u64>) -> Self {
+        Self {
+            shared_context,
+            receiver,
+        }
+    }
+
+    /// Wait until the context is replaced and return the new version. If the
+    /// context has been replaced more than once since the last call, only the
+    /// latest version is returned.
+    pub async fn changed(&mut self) -> u64 {
+        // The sender lives as long as `self.shared_context`, so the channel is
+        // never closed while waiting.
+        let _ = self.receiver.changed().await;
+
+        *self.receiver.borrow_and_update()
+    }
+
+    /// The latest version of the context, which may be newer than the one
+    /// last returned by [`Subscriber::changed()`]. It does not mark the
+    /// version as seen.
+    pub fn version(&self) -> u64 {
+        *self.receiver.borrow()
+    }
+
+    /// Equivalent to [`SharedContext::load()`].
+    pub fn load(&self) -> Context<T> {
+        self.shared_context.load()
+    }
+}
+
+impl<T> Clone for Subscriber<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared_context: self.shared_context.clone(),
+            receiver: self.receiver.clone(),
+        }
+    }
+}
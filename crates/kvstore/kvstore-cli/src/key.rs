@@ -0,0 +1,336 @@
+//! The keys of `kvstore` in the [`KeyEncoding`] of the database. The
+//! order-preserving encoding is not self-describing, so the components are
+//! decoded with the types given with `--key-types`, or as strings as long as
+//! they look like ones, and the bytes which cannot be decoded are kept as
+//! `{"raw": "0x..."}` so that every key is encoded back to the same bytes.
+//! The keys serialized with the codec are decoded as the codec describes
+//! them, or as a whole as `{"raw": "0x..."}` for the codecs such as bincode
+//! which do not.
+
+use kvstore::{deserialize_key_prefix, serialize_key, Codec, KeyEncoding};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{value, Error};
+
+const RAW: &str = "raw";
+
+/// Type of a key component given with `--key-types`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyType {
+    Str,
+    Bytes,
+    Bool,
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+}
+
+impl std::str::FromStr for KeyType {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "str" => Ok(Self::Str),
+            "bytes" => Ok(Self::Bytes),
+            "bool" => Ok(Self::Bool),
+            "u8" => Ok(Self::U8),
+            "u16" => Ok(Self::U16),
+            "u32" => Ok(Self::U32),
+            "u64" => Ok(Self::U64),
+            "u128" => Ok(Self::U128),
+            "i8" => Ok(Self::I8),
+            "i16" => Ok(Self::I16),
+            "i32" => Ok(Self::I32),
+            "i64" => Ok(Self::I64),
+            "i128" => Ok(Self::I128),
+            others => Err(format!("Unknown key type '{others}'").into()),
+        }
+    }
+}
+
+impl KeyType {
+    /// Parse the component of the type, given as a JSON string for `bytes`
+    /// in hex and for the integers which do not fit in JSON numbers.
+    fn component(self, value: &Value) -> Result<Component, Error> {
+        let invalid = || format!("The key component {value} is not {self:?}");
+        let unsigned = || -> Result<u128, Error> {
+            match value {
+                Value::String(string) => Ok(string.parse().map_err(|_| invalid())?),
+                number => Ok(number.as_u64().ok_or_else(invalid)?.into()),
+            }
+        };
+        let signed = || -> Result<i128, Error> {
+            match value {
+                Value::String(string) => Ok(string.parse().map_err(|_| invalid())?),
+                number => Ok(number.as_i64().ok_or_else(invalid)?.into()),
+            }
+        };
+
+        let component = match self {
+            Self::Str => Component::Str(value.as_str().ok_or_else(invalid)?.to_owned()),
+            Self::Bytes => Component::Bytes(Bytes(const_hex::decode(
+                value.as_str().ok_or_else(invalid)?,
+            )?)),
+            Self::Bool => Component::Bool(value.as_bool().ok_or_else(invalid)?),
+            Self::U8 => Component::U8(unsigned()?.try_into().map_err(|_| invalid())?),
+            Self::U16 => Component::U16(unsigned()?.try_into().map_err(|_| invalid())?),
+            Self::U32 => Component::U32(unsigned()?.try_into().map_err(|_| invalid())?),
+            Self::U64 => Component::U64(unsigned()?.try_into().map_err(|_| invalid())?),
+            Self::U128 => Component::U128(unsigned()?),
+            Self::I8 => Component::I8(signed()?.try_into().map_err(|_| invalid())?),
+            Self::I16 => Component::I16(signed()?.try_into().map_err(|_| invalid())?),
+            Self::I32 => Component::I32(signed()?.try_into().map_err(|_| invalid())?),
+            Self::I64 => Component::I64(signed()?.try_into().map_err(|_| invalid())?),
+            Self::I128 => Component::I128(signed()?),
+        };
+
+        Ok(component)
+    }
+
+    /// Deserialize the component of the type at the start of the key in the
+    /// order-preserving encoding.
+    fn decode(self, key_slice: &[u8]) -> Result<(Value, &[u8]), Error> {
+        fn prefix<T>(key_slice: &[u8], to_value: fn(T) -> Value) -> Result<(Value, &[u8]), Error>
+        where
+            T: std::fmt::Debug + de::DeserializeOwned + Serialize,
+        {
+            let (component, rest) = deserialize_key_prefix(key_slice)?;
+
+            Ok((to_value(component), rest))
+        }
+
+        match self {
+            Self::Str => prefix::<String>(key_slice, Value::from),
+            Self::Bytes => prefix(key_slice, |bytes: Bytes| {
+                Value::from(const_hex::encode_prefixed(bytes.0))
+            }),
+            Self::Bool => prefix::<bool>(key_slice, Value::from),
+            Self::U8 => prefix::<u8>(key_slice, Value::from),
+            Self::U16 => prefix::<u16>(key_slice, Value::from),
+            Self::U32 => prefix::<u32>(key_slice, Value::from),
+            Self::U64 => prefix::<u64>(key_slice, Value::from),
+            Self::U128 => prefix(key_slice, |integer: u128| Value::from(integer.to_string())),
+            Self::I8 => prefix::<i8>(key_slice, Value::from),
+            Self::I16 => prefix::<i16>(key_slice, Value::from),
+            Self::I32 => prefix::<i32>(key_slice, Value::from),
+            Self::I64 => prefix::<i64>(key_slice, Value::from),
+            Self::I128 => prefix(key_slice, |integer: i128| Value::from(integer.to_string())),
+        }
+    }
+}
+
+/// A key component serialized as its type, or as the JSON value if it has
+/// no type.
+#[derive(Debug)]
+enum Component {
+    Str(String),
+    Bytes(Bytes),
+    Bool(bool),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    U128(u128),
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    I128(i128),
+    Json(Value),
+}
+
+impl Serialize for Component {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Str(v) => v.serialize(serializer),
+            Self::Bytes(v) => v.serialize(serializer),
+            Self::Bool(v) => v.serialize(serializer),
+            Self::U8(v) => v.serialize(serializer),
+            Self::U16(v) => v.serialize(serializer),
+            Self::U32(v) => v.serialize(serializer),
+            Self::U64(v) => v.serialize(serializer),
+            Self::U128(v) => v.serialize(serializer),
+            Self::I8(v) => v.serialize(serializer),
+            Self::I16(v) => v.serialize(serializer),
+            Self::I32(v) => v.serialize(serializer),
+            Self::I64(v) => v.serialize(serializer),
+            Self::I128(v) => v.serialize(serializer),
+            Self::Json(v) => v.serialize(serializer),
+        }
+    }
+}
+
+/// The components of a tuple key, serialized as a tuple.
+#[derive(Debug)]
+struct Tuple(Vec<Component>);
+
+impl Serialize for Tuple {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeTuple;
+
+        let mut tuple = serializer.serialize_tuple(self.0.len())?;
+        for component in &self.0 {
+            tuple.serialize_element(component)?;
+        }
+
+        tuple.end()
+    }
+}
+
+/// Byte string serialized with `serialize_bytes()` rather than as a sequence.
+#[derive(Debug)]
+struct Bytes(Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl de::Visitor<'_> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a byte string")
+            }
+
+            fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Bytes, E> {
+                Ok(Bytes(v.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Bytes, E> {
+                Ok(Bytes(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+/// Decode the key into a JSON array of its components, or into the key
+/// itself if it is not a tuple and the codec describes it.
+pub fn decode(
+    key_encoding: KeyEncoding,
+    codec: &dyn Codec,
+    mut key_slice: &[u8],
+    key_types: &[KeyType],
+) -> Result<Value, Error> {
+    if key_encoding == KeyEncoding::Codec {
+        return Ok(value::transcode(codec, key_slice).unwrap_or_else(|| raw(key_slice)));
+    }
+
+    let mut components = Vec::new();
+    for key_type in key_types {
+        if key_slice.is_empty() {
+            break;
+        }
+
+        let (component, rest) = key_type.decode(key_slice)?;
+        components.push(component);
+        key_slice = rest;
+    }
+
+    if key_types.is_empty() {
+        while let Some((string, rest)) = guess_string(key_slice) {
+            components.push(Value::String(string));
+            key_slice = rest;
+        }
+    }
+
+    if !key_slice.is_empty() {
+        components.push(raw(key_slice));
+    }
+
+    Ok(Value::Array(components))
+}
+
+/// Encode the key from a JSON array of its components or from a single
+/// component. The components without type are serialized as their JSON
+/// value, e.g. a string or a `u64`.
+pub fn encode(
+    key_encoding: KeyEncoding,
+    codec: &dyn Codec,
+    key: &Value,
+    key_types: &[KeyType],
+) -> Result<Vec<u8>, Error> {
+    let component = |index: usize, value: &Value| match key_types.get(index) {
+        Some(key_type) => key_type.component(value),
+        None => Ok(Component::Json(value.clone())),
+    };
+
+    if let Some(raw) = as_raw(key) {
+        return raw;
+    }
+
+    match (key_encoding, key) {
+        (KeyEncoding::Codec, Value::Array(components)) => {
+            let components = components
+                .iter()
+                .enumerate()
+                .map(|(index, value)| component(index, value))
+                .collect::<Result<_, _>>()?;
+
+            Ok(codec.serialize(&Tuple(components))?)
+        }
+        (KeyEncoding::Codec, key) => Ok(codec.serialize(&component(0, key)?)?),
+        (KeyEncoding::Ordered, key) => {
+            let components = match key {
+                Value::Array(components) => components.as_slice(),
+                component => std::slice::from_ref(component),
+            };
+
+            // The components of a tuple are concatenated.
+            let mut key_vec = Vec::new();
+            for (index, value) in components.iter().enumerate() {
+                match as_raw(value) {
+                    Some(raw) => key_vec.extend_from_slice(&raw?),
+                    None => key_vec.extend_from_slice(&serialize_key(&component(index, value)?)?),
+                }
+            }
+
+            Ok(key_vec)
+        }
+    }
+}
+
+/// Decode a non-empty string of printable characters.
+fn guess_string(key_slice: &[u8]) -> Option<(String, &[u8])> {
+    let (string, rest) = deserialize_key_prefix::<String>(key_slice).ok()?;
+
+    match !string.is_empty() && !string.chars().any(char::is_control) {
+        true => Some((string, rest)),
+        false => None,
+    }
+}
+
+fn raw(bytes: &[u8]) -> Value {
+    let mut object = Map::new();
+    object.insert(
+        RAW.to_owned(),
+        Value::String(const_hex::encode_prefixed(bytes)),
+    );
+
+    Value::Object(object)
+}
+
+fn as_raw(value: &Value) -> Option<Result<Vec<u8>, Error>> {
+    match value {
+        Value::Object(object) if object.len() == 1 => object
+            .get(RAW)
+            .and_then(Value::as_str)
+            .map(|raw| Ok(const_hex::decode(raw)?)),
+        _others => None,
+    }
+}
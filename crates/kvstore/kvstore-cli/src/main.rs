@@ -0,0 +1,713 @@
+//! `kvstore` inspects and edits a database of `kvstore` offline, e.g. the
+//! database of a stopped sequencer. Run `kvstore --help` for the usage.
+
+mod key;
+mod value;
+
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use key::KeyType;
+use kvstore::{Codec, ColumnFamilyFormat, KeyEncoding, StoredFormat};
+use rocksdb::{properties, IteratorMode, Options, WriteBatch, DB};
+use serde_json::{Map, Value};
+
+type Error = Box<dyn std::error::Error + Send + Sync>;
+
+const USAGE: &str = "\
+Usage: kvstore <PATH> [OPTIONS] <COMMAND>
+
+Commands:
+  keys [PREFIX]     List the keys of the column family starting with PREFIX
+  get <KEY>         Print the value of the key
+  put <KEY> <VALUE> Put the value of the key, which never expires
+  delete <KEY>      Delete the key
+  dump              Print every key-value pair of the database as JSON lines
+  load              Put the key-value pairs of `dump` read from the standard input
+  stats             Print the size and the number of keys of the column families
+
+The keys are JSON arrays of the components of the key tuples, e.g.
+'[\"RollupMetadata\", \"rollup\"]'. The components which cannot be decoded
+are printed as {\"raw\": \"0x...\"}. `keys`, `get`, `dump` and `stats` open
+the database read-only, and the others fail while the database is open.
+
+The codec, the key encoding and the column families with TTL or versioning
+are read from the format which `kvstore` stores in the database. The options
+below describe the databases and the column families without stored format,
+and fail if they contradict it.
+
+Options:
+  --codec <CODEC>          Codec of the values: json (default), bincode or cbor
+  --key-encoding <ENCODING>
+                           Encoding of the keys: codec (default), which serializes
+                           the keys with the codec, or ordered
+  --column-family <NAME>   Column family of keys, get, put and delete [default: default]
+  --key-types <TYPES>      Comma-separated types of the key components: str, bytes,
+                           bool, u8 to u128 and i8 to i128, e.g. str,u64
+  --ttl <NAME>             Column family with TTL, whose values start with the
+                           expiration time. Can be repeated
+  --versioned <NAME>       Versioned column family, whose values start with the
+                           version of the value. Can be repeated
+  --version <VERSION>      Version of the model to put the value with
+  --hex                    Put VALUE as the hex of the serialized value
+  -h, --help               Print the usage";
+
+struct Arguments {
+    path: PathBuf,
+    command: String,
+    operands: Vec<String>,
+    codec: Option<String>,
+    key_encoding: Option<KeyEncoding>,
+    column_family: String,
+    key_types: Vec<KeyType>,
+    ttl_column_families: Vec<String>,
+    versioned_column_families: Vec<String>,
+    version: Option<u32>,
+    is_hex: bool,
+}
+
+impl Arguments {
+    fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Self, Error> {
+        let mut positionals = Vec::new();
+        let mut codec = None;
+        let mut key_encoding = None;
+        let mut column_family = "default".to_owned();
+        let mut key_types = Vec::new();
+        let mut ttl_column_families = Vec::new();
+        let mut versioned_column_families = Vec::new();
+        let mut version = None;
+        let mut is_hex = false;
+
+        let mut arguments = arguments.into_iter();
+        while let Some(argument) = arguments.next() {
+            let mut value = || {
+                arguments
+                    .next()
+                    .ok_or_else(|| format!("{argument} requires a value"))
+            };
+
+            match argument.as_str() {
+                "--codec" => {
+                    let name = value()?;
+                    self::codec(&name)?;
+                    codec = Some(name);
+                }
+                "--key-encoding" => {
+                    key_encoding = match value()?.as_str() {
+                        "ordered" => Some(KeyEncoding::Ordered),
+                        "codec" => Some(KeyEncoding::Codec),
+                        others => return Err(format!("Unknown key encoding '{others}'").into()),
+                    }
+                }
+                "--column-family" => column_family = value()?,
+                "--key-types" => {
+                    key_types = value()?
+                        .split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()?
+                }
+                "--ttl" => ttl_column_families.push(value()?),
+                "--versioned" => versioned_column_families.push(value()?),
+                "--version" => version = Some(value()?.parse()?),
+                "--hex" => is_hex = true,
+                option if option.starts_with("--") => {
+                    return Err(format!("Unknown option '{option}'").into())
+                }
+                _positional => positionals.push(argument),
+            }
+        }
+
+        let mut positionals = positionals.into_iter();
+        let (path, command) = match (positionals.next(), positionals.next()) {
+            (Some(path), Some(command)) => (path, command),
+            _others => return Err("Expected <PATH> and <COMMAND>".into()),
+        };
+
+        Ok(Self {
+            path: path.into(),
+            command,
+            operands: positionals.collect(),
+            codec,
+            key_encoding,
+            column_family,
+            key_types,
+            ttl_column_families,
+            versioned_column_families,
+            version,
+            is_hex,
+        })
+    }
+
+    fn operand(&self, index: usize, name: &str) -> Result<&str, Error> {
+        self.operands
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("`{}` requires <{name}>", self.command).into())
+    }
+
+    fn key(&self, format: &Format, index: usize) -> Result<Vec<u8>, Error> {
+        let key = serde_json::from_str(self.operand(index, "KEY")?)?;
+
+        format.encode_key(&key, &self.key_types)
+    }
+}
+
+/// How the database is encoded, read from its [`StoredFormat`] and from the
+/// options for what the stored format does not describe.
+struct Format {
+    codec: Box<dyn Codec>,
+    key_encoding: KeyEncoding,
+    stored: Option<StoredFormat>,
+    ttl_column_families: Vec<String>,
+    versioned_column_families: Vec<String>,
+}
+
+impl Format {
+    fn read(database: &DB, arguments: &Arguments) -> Result<Self, Error> {
+        let stored = match database.cf_handle(StoredFormat::COLUMN_FAMILY) {
+            Some(handle) => database
+                .get_cf(handle, StoredFormat::KEY)?
+                .map(|value_vec| StoredFormat::from_slice(&value_vec))
+                .transpose()?,
+            None => None,
+        };
+
+        let codec = match (&stored, &arguments.codec) {
+            (Some(stored), Some(name)) if stored.codec != *name => {
+                return Err(format!("The database uses the codec '{}'", stored.codec).into())
+            }
+            (Some(stored), _) => codec(&stored.codec)?,
+            (None, name) => codec(name.as_deref().unwrap_or("json"))?,
+        };
+        let key_encoding = match (&stored, arguments.key_encoding) {
+            (Some(stored), Some(key_encoding)) if stored.key_encoding != key_encoding => {
+                return Err(format!(
+                    "The database uses the key encoding {:?}",
+                    stored.key_encoding
+                )
+                .into())
+            }
+            (Some(stored), _) => stored.key_encoding,
+            (None, key_encoding) => key_encoding.unwrap_or_default(),
+        };
+
+        Ok(Self {
+            codec,
+            key_encoding,
+            stored,
+            ttl_column_families: arguments.ttl_column_families.clone(),
+            versioned_column_families: arguments.versioned_column_families.clone(),
+        })
+    }
+
+    /// The stored format of the column family, or the one given with `--ttl`
+    /// and `--versioned` if the column family has no stored format.
+    fn column_family(&self, name: &str) -> Result<ColumnFamilyFormat, Error> {
+        let given = ColumnFamilyFormat {
+            expiring: self.ttl_column_families.iter().any(|other| other == name),
+            versioned: self
+                .versioned_column_families
+                .iter()
+                .any(|other| other == name),
+        };
+
+        match self
+            .stored
+            .as_ref()
+            .and_then(|stored| stored.column_families.get(name))
+        {
+            Some(stored) if given.expiring && !stored.expiring => {
+                Err(format!("The column family '{name}' has no TTL").into())
+            }
+            Some(stored) if given.versioned && !stored.versioned => {
+                Err(format!("The column family '{name}' is not versioned").into())
+            }
+            Some(stored) => Ok(*stored),
+            None => Ok(given),
+        }
+    }
+
+    fn decode_key(&self, key_slice: &[u8], key_types: &[KeyType]) -> Result<Value, Error> {
+        key::decode(self.key_encoding, self.codec.as_ref(), key_slice, key_types)
+    }
+
+    fn encode_key(&self, key: &Value, key_types: &[KeyType]) -> Result<Vec<u8>, Error> {
+        key::encode(self.key_encoding, self.codec.as_ref(), key, key_types)
+    }
+
+    fn decode_value(&self, name: &str, value_slice: &[u8]) -> Result<Map<String, Value>, Error> {
+        value::decode(self.codec.as_ref(), self.column_family(name)?, value_slice)
+    }
+
+    fn encode_value(&self, name: &str, fields: &Map<String, Value>) -> Result<Vec<u8>, Error> {
+        value::encode(self.codec.as_ref(), self.column_family(name)?, fields)
+    }
+}
+
+/// The codecs with [`Codec::name()`].
+fn codec(name: &str) -> Result<Box<dyn Codec>, Error> {
+    match name {
+        "json" => Ok(Box::new(kvstore::Json)),
+        "bincode" => Ok(Box::new(kvstore::Bincode)),
+        "cbor" => Ok(Box::new(kvstore::Cbor)),
+        others => Err(format!("Unknown codec '{others}'").into()),
+    }
+}
+
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    if arguments.is_empty()
+        || arguments
+            .iter()
+            .any(|argument| argument == "-h" || argument == "--help")
+    {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+
+    match Arguments::parse(arguments).and_then(|arguments| run(&arguments)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("kvstore: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(arguments: &Arguments) -> Result<(), Error> {
+    let mut output = std::io::stdout().lock();
+
+    match arguments.command.as_str() {
+        "keys" => keys(arguments, &mut output),
+        "get" => get(arguments, &mut output),
+        "put" => put(arguments),
+        "delete" => delete(arguments),
+        "dump" => dump(arguments, &mut output),
+        "load" => load(arguments, std::io::stdin().lock()),
+        "stats" => stats(arguments, &mut output),
+        others => Err(format!("Unknown command '{others}'").into()),
+    }
+}
+
+/// Open the database with its format. The column families exclude the one
+/// of the stored format.
+fn open(path: &Path, arguments: &Arguments, read_only: bool) -> Result<Database, Error> {
+    let options = Options::default();
+    let mut column_families = DB::list_cf(&options, path)?;
+
+    let database = match read_only {
+        true => DB::open_cf_for_read_only(&options, path, &column_families, false)?,
+        false => DB::open_cf(&options, path, &column_families)?,
+    };
+    let format = Format::read(&database, arguments)?;
+    column_families.retain(|name| name != StoredFormat::COLUMN_FAMILY);
+
+    Ok((database, column_families, format))
+}
+
+type Database = (DB, Vec<String>, Format);
+
+fn keys(arguments: &Arguments, output: &mut impl Write) -> Result<(), Error> {
+    let (database, _, format) = open(&arguments.path, arguments, true)?;
+    let handle = column_family(&database, &arguments.column_family)?;
+
+    let prefix = match arguments.operands.is_empty() {
+        true => Vec::new(),
+        false if format.key_encoding == KeyEncoding::Codec => {
+            return Err("The keys serialized with the codec have no prefix".into())
+        }
+        false => arguments.key(&format, 0)?,
+    };
+    for item in database.iterator_cf(
+        handle,
+        IteratorMode::From(&prefix, rocksdb::Direction::Forward),
+    ) {
+        let (key, _) = item?;
+        if !key.starts_with(&prefix) {
+            break;
+        }
+
+        writeln!(output, "{}", format.decode_key(&key, &arguments.key_types)?)?;
+    }
+
+    Ok(())
+}
+
+fn get(arguments: &Arguments, output: &mut impl Write) -> Result<(), Error> {
+    let (database, _, format) = open(&arguments.path, arguments, true)?;
+    let handle = column_family(&database, &arguments.column_family)?;
+
+    let value_vec = database
+        .get_cf(handle, arguments.key(&format, 0)?)?
+        .ok_or("The key does not exist")?;
+    let fields = format.decode_value(&arguments.column_family, &value_vec)?;
+    writeln!(output, "{}", Value::Object(fields))?;
+
+    Ok(())
+}
+
+fn put(arguments: &Arguments) -> Result<(), Error> {
+    let (database, _, format) = open(&arguments.path, arguments, false)?;
+    let handle = column_family(&database, &arguments.column_family)?;
+
+    let key_vec = arguments.key(&format, 0)?;
+    let value = arguments.operand(1, "VALUE")?;
+
+    let mut fields = Map::new();
+    match arguments.is_hex {
+        true => fields.insert("value_hex".to_owned(), Value::String(value.to_owned())),
+        false => fields.insert("value".to_owned(), serde_json::from_str(value)?),
+    };
+    if let Some(version) = arguments.version {
+        fields.insert("version".to_owned(), Value::from(version));
+    }
+    let value_vec = format.encode_value(&arguments.column_family, &fields)?;
+
+    database.put_cf(handle, key_vec, value_vec)?;
+
+    Ok(())
+}
+
+fn delete(arguments: &Arguments) -> Result<(), Error> {
+    let (database, _, format) = open(&arguments.path, arguments, false)?;
+    let handle = column_family(&database, &arguments.column_family)?;
+
+    let key_vec = arguments.key(&format, 0)?;
+    database.delete_cf(handle, key_vec)?;
+
+    Ok(())
+}
+
+/// Print a line of `{"column_family": ..., "key": ..., "value": ...}` for
+/// every key-value pair, with the fields of [`value::decode()`].
+fn dump(arguments: &Arguments, output: &mut impl Write) -> Result<(), Error> {
+    let (database, column_families, format) = open(&arguments.path, arguments, true)?;
+
+    for name in &column_families {
+        let handle = column_family(&database, name)?;
+
+        for item in database.iterator_cf(handle, IteratorMode::Start) {
+            let (key, value) = item?;
+
+            let mut record = Map::new();
+            record.insert("column_family".to_owned(), Value::String(name.clone()));
+            record.insert(
+                "key".to_owned(),
+                format.decode_key(&key, &arguments.key_types)?,
+            );
+            record.extend(format.decode_value(name, &value)?);
+            writeln!(output, "{}", Value::Object(record))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Put the lines of [`dump()`], creating the missing column families with
+/// the format given with `--ttl` and `--versioned`, which is added to the
+/// stored format if the database has one.
+fn load(arguments: &Arguments, input: impl BufRead) -> Result<(), Error> {
+    /// The number of the key-value pairs written at once.
+    const BATCH_SIZE: usize = 10_000;
+
+    let (mut database, mut column_families, mut format) = open(&arguments.path, arguments, false)?;
+    let mut write_batch = WriteBatch::default();
+
+    for (index, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: Map<String, Value> =
+            serde_json::from_str(&line).map_err(|error| format!("Line {}: {error}", index + 1))?;
+        let name = match record.get("column_family") {
+            Some(Value::String(name)) => name.as_str(),
+            None => "default",
+            Some(_others) => {
+                return Err(format!("Line {}: invalid column_family", index + 1).into())
+            }
+        };
+        let key_vec = format.encode_key(
+            record
+                .get("key")
+                .ok_or_else(|| format!("Line {}: missing key", index + 1))?,
+            &arguments.key_types,
+        )?;
+        let value_vec = format
+            .encode_value(name, &record)
+            .map_err(|error| format!("Line {}: {error}", index + 1))?;
+
+        if !column_families
+            .iter()
+            .any(|column_family| column_family == name)
+        {
+            database.write(std::mem::take(&mut write_batch))?;
+            database.create_cf(name, &Options::default())?;
+            column_families.push(name.to_owned());
+
+            let column_family_format = format.column_family(name)?;
+            if let Some(stored) = &mut format.stored {
+                stored
+                    .column_families
+                    .insert(name.to_owned(), column_family_format);
+                write_batch.put_cf(
+                    column_family(&database, StoredFormat::COLUMN_FAMILY)?,
+                    StoredFormat::KEY,
+                    stored.to_vec()?,
+                );
+            }
+        }
+        write_batch.put_cf(column_family(&database, name)?, key_vec, value_vec);
+
+        if write_batch.len() >= BATCH_SIZE {
+            database.write(std::mem::take(&mut write_batch))?;
+        }
+    }
+    database.write(write_batch)?;
+
+    Ok(())
+}
+
+/// Print the sizes estimated by RocksDB and the number of keys of every
+/// column family, grouped by the first component of the keys such as the ID
+/// of the models.
+fn stats(arguments: &Arguments, output: &mut impl Write) -> Result<(), Error> {
+    let (database, column_families, format) = open(&arguments.path, arguments, true)?;
+
+    for name in &column_families {
+        let handle = column_family(&database, name)?;
+        let property = |property_name| -> Result<u64, Error> {
+            Ok(database
+                .property_int_value_cf(handle, property_name)?
+                .unwrap_or_default())
+        };
+
+        let mut key_counts = BTreeMap::<String, u64>::new();
+        for item in database.iterator_cf(handle, IteratorMode::Start) {
+            let (key, _) = item?;
+            let first = match format.decode_key(&key, &arguments.key_types)? {
+                Value::Array(components) => components.into_iter().next().unwrap_or_default(),
+                others => others,
+            };
+            *key_counts.entry(first.to_string()).or_default() += 1;
+        }
+
+        writeln!(output, "{name}")?;
+        writeln!(output, "  keys: {}", key_counts.values().sum::<u64>())?;
+        writeln!(
+            output,
+            "  estimated keys: {}",
+            property(properties::ESTIMATE_NUM_KEYS)?
+        )?;
+        writeln!(
+            output,
+            "  live SST bytes: {}",
+            property(properties::LIVE_SST_FILES_SIZE)?
+        )?;
+        writeln!(
+            output,
+            "  memtable bytes: {}",
+            property(properties::CUR_SIZE_ALL_MEM_TABLES)?
+        )?;
+        for (first, count) in key_counts {
+            writeln!(output, "  {first}: {count}")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn column_family<'a>(database: &'a DB, name: &str) -> Result<&'a rocksdb::ColumnFamily, Error> {
+    database
+        .cf_handle(name)
+        .ok_or_else(|| format!("The column family '{name}' does not exist").into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kvstore::{ColumnFamilyOptions, KvStoreBuilder, Version};
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct RollupMetadata {
+        block_height: u64,
+    }
+
+    fn temporary_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("kvstore_cli_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    fn arguments(path: &Path, arguments: &[&str]) -> Arguments {
+        let path = path.to_str().unwrap().to_owned();
+        Arguments::parse(
+            std::iter::once(path).chain(arguments.iter().map(|argument| argument.to_string())),
+        )
+        .unwrap()
+    }
+
+    fn output(path: &Path, command: &[&str]) -> String {
+        let mut output = Vec::new();
+        let arguments = arguments(path, command);
+        match arguments.command.as_str() {
+            "keys" => keys(&arguments, &mut output),
+            "get" => get(&arguments, &mut output),
+            "dump" => dump(&arguments, &mut output),
+            _others => run(&arguments),
+        }
+        .unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_cli() {
+        let path = temporary_path("cli");
+        {
+            let database = KvStoreBuilder::default()
+                .set_key_encoding(KeyEncoding::Ordered)
+                .build(&path)
+                .unwrap();
+            database
+                .put(
+                    &("RollupMetadata", 7_u64),
+                    &RollupMetadata { block_height: 10 },
+                )
+                .unwrap();
+            database.put(&("Height", -1_i32), &1_u8).unwrap();
+        }
+
+        assert_eq!(
+            output(
+                &path,
+                &["keys", "--key-types", "str,u64", "[\"RollupMetadata\"]"]
+            ),
+            "[\"RollupMetadata\",7]\n"
+        );
+        assert_eq!(
+            output(
+                &path,
+                &["get", "--key-types", "str,i32", "[\"Height\", -1]"]
+            ),
+            "{\"value\":1}\n"
+        );
+        output(
+            &path,
+            &["put", "[\"Height\", \"tip\"]", "2", "--version", "3"],
+        );
+        output(
+            &path,
+            &["delete", "--key-types", "str,i32", "[\"Height\", -1]"],
+        );
+
+        let dumped = output(&path, &["dump"]);
+        assert_eq!(
+            dumped
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect::<Vec<Value>>(),
+            [
+                json!({"column_family": "default", "key": ["Height", "tip"], "version": 3, "value": 2}),
+                json!({
+                    "column_family": "default",
+                    "key": ["RollupMetadata", {"raw": "0x0000000000000007"}],
+                    "value": {"block_height": 10},
+                }),
+            ]
+        );
+
+        // The stored format cannot be contradicted.
+        assert!(Arguments::parse(
+            [path.to_str().unwrap(), "dump", "--codec", "bincode"].map(str::to_owned)
+        )
+        .and_then(|arguments| dump(&arguments, &mut Vec::new()))
+        .is_err());
+
+        // Loaded into a database with a column family with TTL, and into a
+        // new versioned column family.
+        let loaded_path = temporary_path("cli_loaded");
+        drop(
+            KvStoreBuilder::default()
+                .set_key_encoding(KeyEncoding::Ordered)
+                .column_family(
+                    "expiring",
+                    ColumnFamilyOptions::default().set_ttl(Duration::from_secs(60)),
+                )
+                .build(&loaded_path)
+                .unwrap(),
+        );
+        let input = format!(
+            "{dumped}{}\n{}\n",
+            json!({"column_family": "expiring", "key": ["a"], "value": null}),
+            json!({"column_family": "versioned", "key": ["b"], "value_version": 5, "value": 1}),
+        );
+        load(
+            &arguments(&loaded_path, &["load", "--versioned", "versioned"]),
+            input.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            output(&loaded_path, &["dump"]),
+            format!(
+                "{dumped}{}\n{}\n",
+                json!({"column_family": "expiring", "key": ["a"], "expiration": u64::MAX, "value": null}),
+                json!({"column_family": "versioned", "key": ["b"], "value_version": 5, "value": 1}),
+            )
+        );
+
+        // The loaded values are read by `kvstore`.
+        let database = KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .column_family(
+                "versioned",
+                ColumnFamilyOptions::default().enable_versioning(),
+            )
+            .build(&loaded_path)
+            .unwrap();
+        assert_eq!(
+            database
+                .column_family("versioned")
+                .unwrap()
+                .get_with_version::<_, u8>(&("b",))
+                .unwrap(),
+            (1, Version::from(5))
+        );
+    }
+
+    #[test]
+    fn test_codec_keys() {
+        let path = temporary_path("codec_keys");
+        {
+            let database = KvStoreBuilder::default().build(&path).unwrap();
+            database.put(&("Height", 7_u64), &1_u8).unwrap();
+        }
+
+        assert_eq!(output(&path, &["keys"]), "[\"Height\",7]\n");
+        // The databases without stored format use the codec by default.
+        {
+            let (mut database, ..) = open(&path, &arguments(&path, &["keys"]), false).unwrap();
+            database.drop_cf(StoredFormat::COLUMN_FAMILY).unwrap();
+        }
+        assert_eq!(output(&path, &["keys"]), "[\"Height\",7]\n");
+        assert_eq!(
+            output(&path, &["get", "--key-types", "str,u8", "[\"Height\", 7]"]),
+            "{\"value\":1}\n"
+        );
+        assert!(Arguments::parse(
+            [path.to_str().unwrap(), "keys", "[\"Height\"]"].map(str::to_owned)
+        )
+        .and_then(|arguments| keys(&arguments, &mut Vec::new()))
+        .is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! The stored values, split and joined with [`ColumnFamilyFormat`]: the
+//! serialized value with the codec, preceded by the version of the model if
+//! it is versioned, by the version of the value if the column family is
+//! versioned and by the expiration time if the column family has TTL.
+
+use kvstore::{erased_serde, Codec, ColumnFamilyFormat, StoredValue, Version};
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+/// Decode the value into the fields `value` or `value_hex` if the codec cannot
+/// decode it without its type, `version` of the model, `value_version` and
+/// `expiration`.
+pub fn decode(
+    codec: &dyn Codec,
+    format: ColumnFamilyFormat,
+    value_slice: &[u8],
+) -> Result<Map<String, Value>, Error> {
+    let stored_value = format.decode(value_slice);
+    let mut fields = Map::new();
+
+    if let Some(expiration) = stored_value.expiration {
+        fields.insert("expiration".to_owned(), Value::from(expiration));
+    }
+    if let Some(version) = stored_value.version {
+        fields.insert("value_version".to_owned(), Value::from(u64::from(version)));
+    }
+    if let Some(schema_version) = stored_value.schema_version {
+        fields.insert("version".to_owned(), Value::from(schema_version));
+    }
+    match transcode(codec, stored_value.payload) {
+        Some(value) => fields.insert("value".to_owned(), value),
+        None => fields.insert(
+            "value_hex".to_owned(),
+            Value::String(const_hex::encode_prefixed(stored_value.payload)),
+        ),
+    };
+
+    Ok(fields)
+}
+
+/// Encode the value from the fields returned by [`decode()`]. The value
+/// never expires if the column family has TTL and `expiration` is missing,
+/// and takes a new version if the column family is versioned and
+/// `value_version` is missing.
+pub fn encode(
+    codec: &dyn Codec,
+    format: ColumnFamilyFormat,
+    fields: &Map<String, Value>,
+) -> Result<Vec<u8>, Error> {
+    let payload = match (fields.get("value"), fields.get("value_hex")) {
+        (Some(value), None) => codec.serialize(value)?,
+        (None, Some(Value::String(value_hex))) => const_hex::decode(value_hex)?,
+        _others => return Err("Expected either 'value' or 'value_hex' as a string".into()),
+    };
+
+    let expiration = fields
+        .get("expiration")
+        .map(|expiration| {
+            expiration
+                .as_u64()
+                .ok_or("Expected 'expiration' in seconds since the Unix epoch")
+        })
+        .transpose()?;
+    let version = fields
+        .get("value_version")
+        .map(|version| version.as_u64().ok_or("Expected 'value_version' as u64"))
+        .transpose()?
+        .map(Version::from);
+    let schema_version = fields
+        .get("version")
+        .map(|version| {
+            version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .ok_or("Expected 'version' as u32")
+        })
+        .transpose()?;
+
+    Ok(format.encode(&StoredValue {
+        expiration,
+        version,
+        schema_version,
+        payload: &payload,
+    }))
+}
+
+/// Deserialize the payload as JSON, which fails for the codecs which are not
+/// self-describing such as bincode.
+pub fn transcode(codec: &dyn Codec, payload: &[u8]) -> Option<Value> {
+    let mut value = None;
+    codec
+        .deserialize(payload, &mut |deserializer| {
+            value = Some(erased_serde::deserialize(deserializer)?);
+
+            Ok(())
+        })
+        .ok()?;
+
+    value
+}
@@ -3,19 +3,29 @@ use quote::{quote, ToTokens};
 use syn::{
     parse::{discouraged::AnyDelimiter, Parse},
     punctuated::{self, Punctuated},
-    DeriveInput, Error, Ident, Meta, Path, Result, Token, Type,
+    DeriveInput, Error, Ident, LitInt, LitStr, Meta, Path, Result, Token, Type,
 };
 
 #[derive(Debug)]
 pub struct KvStoreAttribute {
     path_attribute: PathAttribute,
     key_attribute: Option<KeyAttribute>,
+    database_attribute: Option<DatabaseAttribute>,
+    column_family_attribute: Option<ColumnFamilyAttribute>,
+    ttl_attribute: Option<TtlAttribute>,
+    version_attribute: Option<VersionAttribute>,
+    codec_attribute: Option<CodecAttribute>,
 }
 
 impl KvStoreAttribute {
     pub fn from_ast(ast: &DeriveInput) -> Result<Self> {
         let mut path_attribute: Option<PathAttribute> = None;
         let mut key_attribute: Option<KeyAttribute> = None;
+        let mut database_attribute: Option<DatabaseAttribute> = None;
+        let mut column_family_attribute: Option<ColumnFamilyAttribute> = None;
+        let mut ttl_attribute: Option<TtlAttribute> = None;
+        let mut version_attribute: Option<VersionAttribute> = None;
+        let mut codec_attribute: Option<CodecAttribute> = None;
 
         for attribute in ast.attrs.iter() {
             if attribute.path().is_ident("kvstore") {
@@ -42,6 +52,51 @@ impl KvStoreAttribute {
                                 }
                                 key_attribute = Some(key);
                             }
+                            AttributeType::Database(database) => {
+                                if database_attribute.is_some() {
+                                    return Err(Error::new_spanned(
+                                        meta_list,
+                                        "Attribute database already exists.",
+                                    ));
+                                }
+                                database_attribute = Some(database);
+                            }
+                            AttributeType::ColumnFamily(column_family) => {
+                                if column_family_attribute.is_some() {
+                                    return Err(Error::new_spanned(
+                                        meta_list,
+                                        "Attribute column_family already exists.",
+                                    ));
+                                }
+                                column_family_attribute = Some(column_family);
+                            }
+                            AttributeType::Ttl(ttl) => {
+                                if ttl_attribute.is_some() {
+                                    return Err(Error::new_spanned(
+                                        meta_list,
+                                        "Attribute ttl already exists.",
+                                    ));
+                                }
+                                ttl_attribute = Some(ttl);
+                            }
+                            AttributeType::Version(version) => {
+                                if version_attribute.is_some() {
+                                    return Err(Error::new_spanned(
+                                        meta_list,
+                                        "Attribute version already exists.",
+                                    ));
+                                }
+                                version_attribute = Some(version);
+                            }
+                            AttributeType::Codec(codec) => {
+                                if codec_attribute.is_some() {
+                                    return Err(Error::new_spanned(
+                                        meta_list,
+                                        "Attribute codec already exists.",
+                                    ));
+                                }
+                                codec_attribute = Some(codec);
+                            }
                         }
                     }
                     others => return Err(Error::new_spanned(others, "Expect kvstore(token)")),
@@ -58,6 +113,11 @@ impl KvStoreAttribute {
         Ok(Self {
             path_attribute: path_attribute.unwrap(),
             key_attribute,
+            database_attribute,
+            column_family_attribute,
+            ttl_attribute,
+            version_attribute,
+            codec_attribute,
         })
     }
 
@@ -68,12 +128,160 @@ impl KvStoreAttribute {
     pub fn key_attribute(&self) -> Option<&KeyAttribute> {
         self.key_attribute.as_ref()
     }
+
+    pub fn version_attribute(&self) -> Option<&VersionAttribute> {
+        self.version_attribute.as_ref()
+    }
+
+    /// Expression evaluating to the `KvStore` or the `ColumnFamily` to operate
+    /// on. Errors are returned early with `?`.
+    pub fn kvstore(&self) -> TokenStream {
+        self.register(self.scope(self.database()))
+    }
+
+    /// The expression for the `AsyncKvStore` of the database.
+    pub fn async_kvstore(&self) -> TokenStream {
+        let database = self.database();
+
+        self.register(self.scope(quote!(#database.asynchronous())))
+    }
+
+    fn database(&self) -> TokenStream {
+        let path = self.path();
+
+        match &self.database_attribute {
+            Some(database_attribute) => {
+                let name = database_attribute.name();
+                quote!(#path::kvstore_named(#name)?)
+            }
+            None => quote!(#path::kvstore()?),
+        }
+    }
+
+    /// Scope `kvstore` to the column family if any.
+    fn scope(&self, kvstore: TokenStream) -> TokenStream {
+        match &self.column_family_attribute {
+            Some(column_family_attribute) => {
+                let name = column_family_attribute.name();
+                quote!(#kvstore.column_family(#name)?)
+            }
+            None => kvstore,
+        }
+    }
+
+    /// Register the schema and the codec of the model before evaluating
+    /// `expression` if it has any, in case it is not registered at load time
+    /// by [`KvStoreAttribute::register_at_load()`].
+    fn register(&self, expression: TokenStream) -> TokenStream {
+        match self.registrations(quote!(Self)) {
+            Some(registrations) => quote! {
+                ({
+                    static REGISTER: std::sync::Once = std::sync::Once::new();
+                    REGISTER.call_once(|| {
+                        #registrations
+                    });
+
+                    #expression
+                })
+            },
+            None => expression,
+        }
+    }
+
+    /// Register the schema and the codec of the model when the program is
+    /// loaded, before the values of the model are accessed with `KvStore`
+    /// directly. The function is placed in the section of the constructors
+    /// on the targets which have one.
+    pub fn register_at_load(&self, ident: &Ident) -> TokenStream {
+        let Some(registrations) = self.registrations(quote!(#ident)) else {
+            return TokenStream::new();
+        };
+
+        quote! {
+            const _: () = {
+                #[used]
+                #[cfg_attr(
+                    any(
+                        target_os = "linux",
+                        target_os = "android",
+                        target_os = "freebsd",
+                        target_os = "netbsd",
+                        target_os = "openbsd",
+                        target_os = "dragonfly",
+                        target_os = "illumos",
+                        target_os = "solaris"
+                    ),
+                    link_section = ".init_array"
+                )]
+                #[cfg_attr(target_vendor = "apple", link_section = "__DATA,__mod_init_func")]
+                #[cfg_attr(windows, link_section = ".CRT$XCU")]
+                static REGISTER: extern "C" fn() = {
+                    extern "C" fn register() {
+                        #registrations
+                    }
+
+                    register
+                };
+            };
+        }
+    }
+
+    fn registrations(&self, ty: TokenStream) -> Option<TokenStream> {
+        let path = self.path();
+        let schema = self
+            .version_attribute
+            .as_ref()
+            .map(|_| quote!(#path::register_schema::<#ty>();));
+        let codec = self.codec_attribute.as_ref().map(|codec_attribute| {
+            let codec = codec_attribute.codec();
+            quote!(#path::register_codec::<#ty>(&#codec);)
+        });
+
+        match (&schema, &codec) {
+            (None, None) => None,
+            _others => Some(quote! {
+                #schema
+                #codec
+            }),
+        }
+    }
+
+    /// The expression for `transaction`, scoped to the column family if any.
+    pub fn transaction(&self) -> TokenStream {
+        self.register(self.scope(quote!(transaction)))
+    }
+
+    /// The expression for `batch`, scoped to the column family if any.
+    pub fn write_batch(&self) -> TokenStream {
+        self.register(self.scope(quote!(batch)))
+    }
+
+    /// The method call to put `value` at `key`, with the TTL if any.
+    pub fn put(&self, key: TokenStream, value: TokenStream) -> TokenStream {
+        match &self.ttl_attribute {
+            Some(ttl_attribute) => {
+                let milliseconds = ttl_attribute.milliseconds();
+                quote!(put_with_ttl(#key, #value, std::time::Duration::from_millis(#milliseconds)))
+            }
+            None => quote!(put(#key, #value)),
+        }
+    }
+
+    /// The expression for `snapshot`, scoped to the column family if any.
+    pub fn snapshot(&self) -> TokenStream {
+        self.register(self.scope(quote!(snapshot)))
+    }
 }
 
 #[derive(Debug)]
 pub enum AttributeType {
     Path(PathAttribute),
     Key(KeyAttribute),
+    Database(DatabaseAttribute),
+    ColumnFamily(ColumnFamilyAttribute),
+    Ttl(TtlAttribute),
+    Version(VersionAttribute),
+    Codec(CodecAttribute),
 }
 
 impl Parse for AttributeType {
@@ -93,7 +301,40 @@ impl Parse for AttributeType {
 
                 Ok(Self::Key(key_attribute))
             }
-            _others => Err(Error::new_spanned(ident, "Must be 'path' or 'key'")),
+            "database" => {
+                let _punctuation: Token![=] = input.parse()?;
+                let database_attribute = input.parse::<DatabaseAttribute>()?;
+
+                Ok(Self::Database(database_attribute))
+            }
+            "column_family" => {
+                let _punctuation: Token![=] = input.parse()?;
+                let column_family_attribute = input.parse::<ColumnFamilyAttribute>()?;
+
+                Ok(Self::ColumnFamily(column_family_attribute))
+            }
+            "ttl" => {
+                let _punctuation: Token![=] = input.parse()?;
+                let ttl_attribute = input.parse::<TtlAttribute>()?;
+
+                Ok(Self::Ttl(ttl_attribute))
+            }
+            "version" => {
+                let _punctuation: Token![=] = input.parse()?;
+                let version_attribute = input.parse::<VersionAttribute>()?;
+
+                Ok(Self::Version(version_attribute))
+            }
+            "codec" => {
+                let _punctuation: Token![=] = input.parse()?;
+                let codec_attribute = input.parse::<CodecAttribute>()?;
+
+                Ok(Self::Codec(codec_attribute))
+            }
+            _others => Err(Error::new_spanned(
+                ident,
+                "Must be 'path', 'key', 'database', 'column_family', 'ttl', 'version' or 'codec'",
+            )),
         }
     }
 }
@@ -120,6 +361,171 @@ impl PathAttribute {
     }
 }
 
+#[derive(Debug)]
+pub struct DatabaseAttribute {
+    name: LitStr,
+}
+
+impl Parse for DatabaseAttribute {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        Ok(Self {
+            name: input.parse()?,
+        })
+    }
+}
+
+impl DatabaseAttribute {
+    pub fn name(&self) -> &LitStr {
+        &self.name
+    }
+}
+
+#[derive(Debug)]
+pub struct ColumnFamilyAttribute {
+    name: LitStr,
+}
+
+impl Parse for ColumnFamilyAttribute {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        Ok(Self {
+            name: input.parse()?,
+        })
+    }
+}
+
+impl ColumnFamilyAttribute {
+    pub fn name(&self) -> &LitStr {
+        &self.name
+    }
+}
+
+/// TTL of the values, e.g. `ttl = "1h30m"`. The units are `d`, `h`, `m`,
+/// `s` and `ms`.
+#[derive(Debug)]
+pub struct TtlAttribute {
+    milliseconds: u64,
+}
+
+impl Parse for TtlAttribute {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        let literal: LitStr = input.parse()?;
+        let error = || {
+            Error::new_spanned(
+                &literal,
+                "Must be a duration such as '30s', '10m' or '1h30m'",
+            )
+        };
+
+        let value = literal.value();
+        let mut rest = value.trim();
+        let mut milliseconds: u64 = 0;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|character: char| !character.is_ascii_digit())
+                .unwrap_or(rest.len());
+            let number: u64 = rest[..digits].parse().map_err(|_| error())?;
+            rest = &rest[digits..];
+
+            let units = rest
+                .find(|character: char| !character.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let unit: u64 = match &rest[..units] {
+                "d" => 86_400_000,
+                "h" => 3_600_000,
+                "m" => 60_000,
+                "s" => 1_000,
+                "ms" => 1,
+                _others => return Err(error()),
+            };
+            rest = rest[units..].trim_start();
+
+            milliseconds = number
+                .checked_mul(unit)
+                .and_then(|duration| milliseconds.checked_add(duration))
+                .ok_or_else(error)?;
+        }
+
+        match milliseconds {
+            0 => Err(error()),
+            milliseconds => Ok(Self { milliseconds }),
+        }
+    }
+}
+
+impl TtlAttribute {
+    pub fn milliseconds(&self) -> u64 {
+        self.milliseconds
+    }
+}
+
+/// Version of the values, e.g. `version = 2, migrate_from = ModelV1` where
+/// the model of the previous version converts into this one with `From`.
+#[derive(Debug)]
+pub struct VersionAttribute {
+    version: LitInt,
+    migrate_from: Option<Type>,
+}
+
+impl Parse for VersionAttribute {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        let version: LitInt = input.parse()?;
+        match version.base10_parse::<u32>()? {
+            0 => return Err(Error::new_spanned(&version, "Must be greater than 0")),
+            _others => {}
+        }
+
+        let migrate_from = match input.is_empty() {
+            true => None,
+            false => {
+                let _punctuation: Token![,] = input.parse()?;
+                let ident: Ident = input.parse()?;
+                if ident != "migrate_from" {
+                    return Err(Error::new_spanned(ident, "Must be 'migrate_from'"));
+                }
+                let _punctuation: Token![=] = input.parse()?;
+
+                Some(input.parse()?)
+            }
+        };
+
+        Ok(Self {
+            version,
+            migrate_from,
+        })
+    }
+}
+
+impl VersionAttribute {
+    pub fn version(&self) -> &LitInt {
+        &self.version
+    }
+
+    pub fn migrate_from(&self) -> Option<&Type> {
+        self.migrate_from.as_ref()
+    }
+}
+
+/// Codec of the values overriding the one of the database, e.g.
+/// `codec = kvstore::Cbor`.
+#[derive(Debug)]
+pub struct CodecAttribute {
+    codec: Path,
+}
+
+impl Parse for CodecAttribute {
+    fn parse(input: syn::parse::ParseStream) -> Result<Self> {
+        Ok(Self {
+            codec: input.parse()?,
+        })
+    }
+}
+
+impl CodecAttribute {
+    pub fn codec(&self) -> &Path {
+        &self.codec
+    }
+}
+
 #[derive(Debug)]
 pub struct KeyAttribute {
     key_list: Punctuated<Key, Token![,]>,
@@ -150,6 +556,13 @@ impl KeyAttribute {
             #(#key_ident #key_punctuation #key_reference #key_type,)*
         }
     }
+
+    /// Every key but the last one.
+    pub fn leading(&self) -> impl Iterator<Item = &Key> {
+        self.key_list
+            .iter()
+            .take(self.key_list.len().saturating_sub(1))
+    }
 }
 
 #[derive(Debug)]
@@ -160,6 +573,31 @@ pub struct Key {
     pub key_type: Type,
 }
 
+impl Key {
+    pub fn as_function_parameter(&self) -> TokenStream {
+        let Self {
+            name,
+            punctuation,
+            reference,
+            key_type,
+        } = self;
+
+        quote!(#name #punctuation #reference #key_type)
+    }
+
+    /// Type of the key when it is deserialized, which is the owned type for
+    /// a reference, e.g. `String` for `&str`.
+    pub fn owned_type(&self) -> TokenStream {
+        match &self.key_type {
+            Type::Reference(reference) => {
+                let element = &reference.elem;
+                quote!(<#element as std::borrow::ToOwned>::Owned)
+            }
+            others => quote!(#others),
+        }
+    }
+}
+
 impl Parse for Key {
     fn parse(input: syn::parse::ParseStream) -> Result<Self> {
         Ok(Self {
@@ -15,12 +15,14 @@ pub fn fn_put(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let put = kvstore_attribute.put(quote!(key), quote!(self));
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn put(&self, #parameters) -> std::result::Result<(), #path::KvStoreError> {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.put(key, self)
+                #kvstore.#put
             }
         })
     } else {
@@ -33,12 +35,13 @@ pub fn fn_get(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn get(#parameters) -> std::result::Result<Self, #path::KvStoreError> {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.get(key)
+                #kvstore.get(key)
             }
         })
     } else {
@@ -51,6 +54,7 @@ pub fn fn_get_or(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn get_or<F>(#parameters function: F) -> std::result::Result<Self, #path::KvStoreError>
@@ -59,7 +63,7 @@ pub fn fn_get_or(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
             {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.get_or(key, function)
+                #kvstore.get_or(key, function)
             }
         })
     } else {
@@ -72,12 +76,13 @@ pub fn fn_get_mut(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn get_mut(#parameters) -> std::result::Result<#path::Lock<'static, Self>, #path::KvStoreError> {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.get_mut(key)
+                #kvstore.get_mut(key)
             }
         })
     } else {
@@ -90,6 +95,7 @@ pub fn fn_get_mut_or(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn get_mut_or<F>(#parameters function: F) -> std::result::Result<#path::Lock<'static, Self>, #path::KvStoreError>
@@ -98,7 +104,7 @@ pub fn fn_get_mut_or(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream
             {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.get_mut_or(key, function)
+                #kvstore.get_mut_or(key, function)
             }
         })
     } else {
@@ -111,6 +117,7 @@ pub fn fn_apply(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn apply<F>(#parameters operation: F) -> std::result::Result<(), #path::KvStoreError>
@@ -119,7 +126,7 @@ pub fn fn_apply(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
             {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.apply(key, |value: &mut #path::Lock<'_, Self>| { operation(value) })
+                #kvstore.apply(key, |value: &mut #path::Lock<'_, Self>| { operation(value) })
             }
         })
     } else {
@@ -132,15 +139,457 @@ pub fn fn_delete(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
         let parameters = key_attribute.as_function_parameters();
         let key_names = key_attribute.iter().map(|key| &key.name);
         let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
 
         Some(quote! {
             pub fn delete(#parameters) -> std::result::Result<(), #path::KvStoreError> {
                 let key = &(Self::ID, #(#key_names,)*);
 
-                #path::kvstore()?.delete(key)
+                #kvstore.delete(key)
             }
         })
     } else {
         None
     }
 }
+
+pub fn fn_iter_all(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let key_types = key_attribute.iter().map(|key| key.owned_type());
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
+
+        Some(quote! {
+            pub fn iter_all() -> std::result::Result<#path::Iter<'static, (String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                #kvstore.iter_prefix(&(Self::ID,))
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_iter_by(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    match kvstore_attribute.key_attribute() {
+        Some(key_attribute) if key_attribute.leading().next().is_some() => {
+            let parameters = key_attribute
+                .leading()
+                .map(|key| key.as_function_parameter());
+            let key_names = key_attribute.leading().map(|key| &key.name);
+            let key_types = key_attribute.iter().map(|key| key.owned_type());
+            let path = kvstore_attribute.path();
+            let kvstore = kvstore_attribute.kvstore();
+
+            Some(quote! {
+                pub fn iter_by(#(#parameters,)*) -> std::result::Result<#path::Iter<'static, (String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                    let prefix = &(Self::ID, #(#key_names,)*);
+
+                    #kvstore.iter_prefix(prefix)
+                }
+            })
+        }
+        _others => None,
+    }
+}
+
+pub fn fn_get_at(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let snapshot = kvstore_attribute.snapshot();
+
+        Some(quote! {
+            pub fn get_at(snapshot: &#path::Snapshot, #parameters) -> std::result::Result<Self, #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #snapshot.get(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_iter_all_at(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let key_types = key_attribute.iter().map(|key| key.owned_type());
+        let path = kvstore_attribute.path();
+        let snapshot = kvstore_attribute.snapshot();
+
+        Some(quote! {
+            pub fn iter_all_at<'s>(snapshot: &'s #path::Snapshot) -> std::result::Result<#path::Iter<'s, (String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                #snapshot.iter_prefix(&(Self::ID,))
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_iter_by_at(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    match kvstore_attribute.key_attribute() {
+        Some(key_attribute) if key_attribute.leading().next().is_some() => {
+            let parameters = key_attribute
+                .leading()
+                .map(|key| key.as_function_parameter());
+            let key_names = key_attribute.leading().map(|key| &key.name);
+            let key_types = key_attribute.iter().map(|key| key.owned_type());
+            let path = kvstore_attribute.path();
+            let snapshot = kvstore_attribute.snapshot();
+
+            Some(quote! {
+                pub fn iter_by_at<'s>(snapshot: &'s #path::Snapshot, #(#parameters,)*) -> std::result::Result<#path::Iter<'s, (String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                    let prefix = &(Self::ID, #(#key_names,)*);
+
+                    #snapshot.iter_prefix(prefix)
+                }
+            })
+        }
+        _others => None,
+    }
+}
+
+pub fn fn_put_in(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let put = kvstore_attribute.put(quote!(key), quote!(self));
+        let transaction = kvstore_attribute.transaction();
+
+        Some(quote! {
+            pub fn put_in(&self, transaction: &#path::Transaction, #parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #transaction.#put
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_in(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let transaction = kvstore_attribute.transaction();
+
+        Some(quote! {
+            pub fn get_in(transaction: &#path::Transaction, #parameters) -> std::result::Result<Self, #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #transaction.get(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_for_update_in(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let transaction = kvstore_attribute.transaction();
+
+        Some(quote! {
+            pub fn get_for_update_in(transaction: &#path::Transaction, #parameters) -> std::result::Result<Self, #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #transaction.get_for_update(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_delete_in(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let transaction = kvstore_attribute.transaction();
+
+        Some(quote! {
+            pub fn delete_in(transaction: &#path::Transaction, #parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #transaction.delete(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_put_in_batch(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let put = kvstore_attribute.put(quote!(key), quote!(self));
+        let batch = kvstore_attribute.write_batch();
+
+        Some(quote! {
+            pub fn put_in_batch(&self, batch: &mut #path::WriteBatch, #parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #batch.#put
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_delete_in_batch(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let batch = kvstore_attribute.write_batch();
+
+        Some(quote! {
+            pub fn delete_in_batch(batch: &mut #path::WriteBatch, #parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #batch.delete(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_put_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let put = kvstore_attribute.put(quote!(key), quote!(self));
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn put_async(self, #parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.#put.await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn get_async(#parameters) -> std::result::Result<Self, #path::KvStoreError> {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.get(key).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_or_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn get_or_async<F>(#parameters function: F) -> std::result::Result<Self, #path::KvStoreError>
+            where
+                F: FnOnce() -> Self + Send + 'static,
+            {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.get_or(key, function).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_mut_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn get_mut_async(#parameters) -> std::result::Result<#path::AsyncLock<Self>, #path::KvStoreError> {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.get_mut(key).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_get_mut_or_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn get_mut_or_async<F>(#parameters function: F) -> std::result::Result<#path::AsyncLock<Self>, #path::KvStoreError>
+            where
+                F: FnOnce() -> Self + Send + 'static,
+            {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.get_mut_or(key, function).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_apply_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn apply_async<F>(#parameters operation: F) -> std::result::Result<(), #path::KvStoreError>
+            where
+                F: FnOnce(&mut Self) + Send + 'static,
+            {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.apply(key, move |value: &mut #path::Lock<'_, Self>| { operation(value) }).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_delete_async(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.async_kvstore();
+
+        Some(quote! {
+            pub async fn delete_async(#parameters) -> std::result::Result<(), #path::KvStoreError> {
+                let key = (Self::ID, #(#key_names.to_owned(),)*);
+
+                #kvstore.delete(key).await
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_watch(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let parameters = key_attribute.as_function_parameters();
+        let key_names = key_attribute.iter().map(|key| &key.name);
+        let key_types = key_attribute.iter().map(|key| key.owned_type());
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
+
+        Some(quote! {
+            pub fn watch(#parameters) -> std::result::Result<#path::Watch<(String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                let key = &(Self::ID, #(#key_names,)*);
+
+                #kvstore.watch(key)
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_watch_all(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    if let Some(key_attribute) = kvstore_attribute.key_attribute() {
+        let key_types = key_attribute.iter().map(|key| key.owned_type());
+        let path = kvstore_attribute.path();
+        let kvstore = kvstore_attribute.kvstore();
+
+        Some(quote! {
+            pub fn watch_all() -> std::result::Result<#path::Watch<(String, #(#key_types,)*), Self>, #path::KvStoreError> {
+                #kvstore.watch_prefix(&(Self::ID,))
+            }
+        })
+    } else {
+        None
+    }
+}
+
+pub fn fn_migrate_all(kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    match (
+        kvstore_attribute.key_attribute(),
+        kvstore_attribute.version_attribute(),
+    ) {
+        (Some(key_attribute), Some(_)) => {
+            let key_types = key_attribute.iter().map(|key| key.owned_type());
+            let path = kvstore_attribute.path();
+            let kvstore = kvstore_attribute.kvstore();
+
+            Some(quote! {
+                pub fn migrate_all() -> std::result::Result<usize, #path::KvStoreError> {
+                    #kvstore.migrate_where::<_, (String, #(#key_types,)*), Self>(
+                        &(Self::ID,),
+                        |key| key.0 == Self::ID,
+                    )
+                }
+            })
+        }
+        _others => None,
+    }
+}
+
+pub fn impl_schema(type_name: &Ident, kvstore_attribute: &KvStoreAttribute) -> Option<TokenStream> {
+    let version_attribute = kvstore_attribute.version_attribute()?;
+    let version = version_attribute.version();
+    let path = kvstore_attribute.path();
+
+    match version_attribute.migrate_from() {
+        Some(previous) => Some(quote! {
+            impl #path::Schema for #type_name {
+                const VERSION: u32 = #version;
+                type Previous = #previous;
+            }
+
+            const _: () = assert!(
+                <#previous as #path::Schema>::VERSION < #version,
+                "migrate_from must be an older version",
+            );
+        }),
+        None => Some(quote! {
+            impl #path::Schema for #type_name {
+                const VERSION: u32 = #version;
+                type Previous = Self;
+            }
+        }),
+    }
+}
@@ -19,6 +19,29 @@ pub fn expand_derive_model(input: &mut DeriveInput) -> Result<TokenStream> {
     let get_mut_or = fn_get_mut_or(&kvstore_attribute);
     let apply = fn_apply(&kvstore_attribute);
     let delete = fn_delete(&kvstore_attribute);
+    let iter_all = fn_iter_all(&kvstore_attribute);
+    let iter_by = fn_iter_by(&kvstore_attribute);
+    let get_at = fn_get_at(&kvstore_attribute);
+    let iter_all_at = fn_iter_all_at(&kvstore_attribute);
+    let iter_by_at = fn_iter_by_at(&kvstore_attribute);
+    let put_in = fn_put_in(&kvstore_attribute);
+    let get_in = fn_get_in(&kvstore_attribute);
+    let get_for_update_in = fn_get_for_update_in(&kvstore_attribute);
+    let delete_in = fn_delete_in(&kvstore_attribute);
+    let put_in_batch = fn_put_in_batch(&kvstore_attribute);
+    let delete_in_batch = fn_delete_in_batch(&kvstore_attribute);
+    let put_async = fn_put_async(&kvstore_attribute);
+    let get_async = fn_get_async(&kvstore_attribute);
+    let get_or_async = fn_get_or_async(&kvstore_attribute);
+    let get_mut_async = fn_get_mut_async(&kvstore_attribute);
+    let get_mut_or_async = fn_get_mut_or_async(&kvstore_attribute);
+    let apply_async = fn_apply_async(&kvstore_attribute);
+    let delete_async = fn_delete_async(&kvstore_attribute);
+    let watch = fn_watch(&kvstore_attribute);
+    let watch_all = fn_watch_all(&kvstore_attribute);
+    let migrate_all = fn_migrate_all(&kvstore_attribute);
+    let schema = impl_schema(ident, &kvstore_attribute);
+    let register = kvstore_attribute.register_at_load(ident);
 
     Ok(quote! {
         impl #ident {
@@ -30,6 +53,30 @@ pub fn expand_derive_model(input: &mut DeriveInput) -> Result<TokenStream> {
             #get_mut_or
             #apply
             #delete
+            #iter_all
+            #iter_by
+            #get_at
+            #iter_all_at
+            #iter_by_at
+            #put_in
+            #get_in
+            #get_for_update_in
+            #delete_in
+            #put_in_batch
+            #delete_in_batch
+            #put_async
+            #get_async
+            #get_or_async
+            #get_mut_async
+            #get_mut_or_async
+            #apply_async
+            #delete_async
+            #watch
+            #watch_all
+            #migrate_all
         }
+
+        #schema
+        #register
     })
 }
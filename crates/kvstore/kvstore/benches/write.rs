@@ -0,0 +1,97 @@
+#![feature(test)]
+
+extern crate test;
+
+use std::path::{Path, PathBuf};
+
+use kvstore::{ColumnFamilyOptions, KeyEncoding, KvStore, KvStoreBuilder, SstWriter};
+use test::Bencher;
+
+const ENTRY_COUNT: u64 = 1000;
+
+fn database_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("kvstore_bench_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+
+    path
+}
+
+fn value(index: u64) -> String {
+    format!("transaction {index}")
+}
+
+#[bench]
+fn bench_put(b: &mut Bencher) {
+    let database = KvStore::open(database_path("put")).unwrap();
+
+    b.iter(|| {
+        for index in 0..ENTRY_COUNT {
+            database
+                .put(&("Transaction", index), &value(index))
+                .unwrap();
+        }
+    });
+}
+
+#[bench]
+fn bench_write_batch(b: &mut Bencher) {
+    let database = KvStore::open(database_path("write_batch")).unwrap();
+
+    b.iter(|| {
+        database
+            .write_batch(|batch| {
+                for index in 0..ENTRY_COUNT {
+                    batch.put(&("Transaction", index), &value(index))?;
+                }
+
+                Ok(())
+            })
+            .unwrap();
+    });
+}
+
+fn write_sst(path: &Path, options: &ColumnFamilyOptions) {
+    // The keys must be in ascending order of the serialized keys.
+    let mut writer = SstWriter::create(path, options)
+        .unwrap()
+        .set_key_encoding(KeyEncoding::Ordered);
+    for index in 0..ENTRY_COUNT {
+        writer.put(&("Transaction", index), &value(index)).unwrap();
+    }
+    writer.finish().unwrap();
+}
+
+#[bench]
+fn bench_write_sst(b: &mut Bencher) {
+    let path = database_path("write_sst");
+    let options = ColumnFamilyOptions::default();
+
+    b.iter(|| write_sst(&path.join("transaction.sst"), &options));
+}
+
+#[bench]
+fn bench_open(b: &mut Bencher) {
+    let path = database_path("open");
+    KvStore::open(&path).unwrap();
+
+    b.iter(|| KvStoreBuilder::default().build(&path).unwrap());
+}
+
+// The files are only ingested when the database is opened, so the ingestion
+// alone costs the difference with `bench_open`.
+#[bench]
+fn bench_ingest(b: &mut Bencher) {
+    let path = database_path("ingest");
+    let sst_path = path.join("transaction.sst");
+    write_sst(&sst_path, &ColumnFamilyOptions::default());
+    KvStore::open(path.join("database")).unwrap();
+
+    b.iter(|| {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .ingest("default", [&sst_path])
+            .build(path.join("database"))
+            .unwrap()
+    });
+}
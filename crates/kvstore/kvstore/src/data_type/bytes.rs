@@ -1,41 +1,44 @@
+use bincode::Options;
+
 use super::prelude::*;
 
-pub fn deserialize<T>(data: impl AsRef<[u8]>) -> Result<T, DataTypeError>
-where
-    T: Debug + DeserializeOwned + Serialize,
-{
-    bincode::deserialize(data.as_ref()).map_err(|error| DataTypeError::Deserialize {
-        type_name: any::type_name::<T>(),
-        error,
-    })
-}
+/// bincode, which is compact but not self-describing, so that adding a field
+/// to a model breaks its stored values unless it is versioned.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Bincode;
 
-pub fn serialize<T>(data: &T) -> Result<Vec<u8>, DataTypeError>
-where
-    T: Debug + Serialize,
-{
-    bincode::serialize(data).map_err(|error| DataTypeError::Serialize {
-        type_name: any::type_name::<T>(),
-        error,
-    })
+// The options of `bincode::serialize()`.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
 }
 
-#[derive(Debug)]
-pub enum DataTypeError {
-    Deserialize {
-        type_name: &'static str,
-        error: bincode::Error,
-    },
-    Serialize {
-        type_name: &'static str,
-        error: bincode::Error,
-    },
-}
+impl Codec for Bincode {
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, CodecError> {
+        let mut value_vec = Vec::new();
+        erased_serde::serialize(
+            value,
+            &mut bincode::Serializer::new(&mut value_vec, options()),
+        )?;
 
-impl std::fmt::Display for DataTypeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+        Ok(value_vec)
     }
-}
 
-impl std::error::Error for DataTypeError {}
+    fn deserialize<'de>(
+        &self,
+        slice: &'de [u8],
+        visit: &mut Visit<'_, 'de>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = bincode::Deserializer::from_slice(slice, options());
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "bincode"
+    }
+}
@@ -0,0 +1,37 @@
+use cbor4ii::{
+    core::utils::{BufWriter, SliceReader},
+    serde::{Deserializer, Serializer},
+};
+
+use super::prelude::*;
+
+/// CBOR with `cbor4ii`, which is compact and self-describing as the fields
+/// are serialized with their names.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Cbor;
+
+impl Codec for Cbor {
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, CodecError> {
+        let mut serializer = Serializer::new(BufWriter::new(Vec::new()));
+        erased_serde::serialize(value, &mut serializer)?;
+
+        Ok(serializer.into_inner().into_inner())
+    }
+
+    fn deserialize<'de>(
+        &self,
+        slice: &'de [u8],
+        visit: &mut Visit<'_, 'de>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = Deserializer::new(SliceReader::new(slice));
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+}
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use super::prelude::*;
+
+/// Boxed error of a [`Codec`].
+pub type CodecError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Deserialization of a value from the deserializer of a [`Codec`].
+pub type Visit<'a, 'de> =
+    dyn FnMut(&mut dyn erased_serde::Deserializer<'de>) -> Result<(), erased_serde::Error> + 'a;
+
+/// Format of the serialized values, set for a database with
+/// [`KvStoreBuilder::set_codec()`](crate::KvStoreBuilder::set_codec) or for a
+/// model with `#[kvstore(codec = kvstore::Cbor)]`.
+///
+/// The keys are always serialized in the same order-preserving format
+/// regardless of the codec.
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{erased_serde, Codec, CodecError, Visit};
+///
+/// /// JSON with indentation.
+/// struct PrettyJson;
+///
+/// impl Codec for PrettyJson {
+///     fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, CodecError> {
+///         let mut value_vec = Vec::new();
+///         let mut serializer = serde_json::Serializer::pretty(&mut value_vec);
+///         erased_serde::serialize(value, &mut serializer)?;
+///
+///         Ok(value_vec)
+///     }
+///
+///     fn deserialize<'de>(
+///         &self,
+///         slice: &'de [u8],
+///         visit: &mut Visit<'_, 'de>,
+///     ) -> Result<(), CodecError> {
+///         let mut deserializer = serde_json::Deserializer::from_slice(slice);
+///         visit(&mut <dyn erased_serde::Deserializer>::erase(
+///             &mut deserializer,
+///         ))?;
+///
+///         Ok(deserializer.end()?)
+///     }
+/// }
+/// ```
+pub trait Codec: Send + Sync {
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, CodecError>;
+
+    /// Call `visit` once with the deserializer of `slice`.
+    fn deserialize<'de>(
+        &self,
+        slice: &'de [u8],
+        visit: &mut Visit<'_, 'de>,
+    ) -> Result<(), CodecError>;
+
+    /// The name stored with the format of the database, so that the tools
+    /// such as `kvstore-cli` decode the values with the same codec. The
+    /// default is the name of the type.
+    fn name(&self) -> &'static str {
+        any::type_name::<Self>()
+    }
+}
+
+/// The codec of the databases which do not set one, which is always JSON so
+/// that enabling the `bincode` or `cbor` feature anywhere in the dependency
+/// graph never changes the format of a database. The databases stored with
+/// another codec must set it with
+/// [`KvStoreBuilder::set_codec()`](crate::KvStoreBuilder::set_codec),
+/// including the ones written with the removed `bytes` feature or with only
+/// the `bincode` feature, whose default codec used to be bincode, e.g.
+/// `KvStoreBuilder::default().set_codec(Bincode)`.
+pub fn default_codec() -> Arc<dyn Codec> {
+    Arc::new(super::Json)
+}
+
+pub fn deserialize<T>(codec: &dyn Codec, data: impl AsRef<[u8]>) -> Result<T, DataTypeError>
+where
+    T: Debug + DeserializeOwned,
+{
+    let error = |error| DataTypeError::Deserialize {
+        type_name: any::type_name::<T>(),
+        error,
+    };
+
+    let mut value = None;
+    codec
+        .deserialize(data.as_ref(), &mut |deserializer| {
+            value = Some(erased_serde::deserialize(deserializer)?);
+
+            Ok(())
+        })
+        .map_err(error)?;
+
+    value.ok_or_else(|| error("The codec did not deserialize the value".into()))
+}
+
+pub fn serialize<T>(codec: &dyn Codec, data: &T) -> Result<Vec<u8>, DataTypeError>
+where
+    T: Debug + Serialize,
+{
+    codec
+        .serialize(data)
+        .map_err(|error| DataTypeError::Serialize {
+            type_name: any::type_name::<T>(),
+            error,
+        })
+}
+
+#[derive(Debug)]
+pub enum DataTypeError {
+    Deserialize {
+        type_name: &'static str,
+        error: CodecError,
+    },
+    Serialize {
+        type_name: &'static str,
+        error: CodecError,
+    },
+}
+
+impl std::fmt::Display for DataTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for DataTypeError {}
@@ -1,41 +1,31 @@
 use super::prelude::*;
 
-pub fn deserialize<T>(data: impl AsRef<[u8]>) -> Result<T, DataTypeError>
-where
-    T: Debug + DeserializeOwned + Serialize,
-{
-    serde_json::from_slice(data.as_ref()).map_err(|error| DataTypeError::Deserialize {
-        type_name: any::type_name::<T>(),
-        error,
-    })
-}
+/// JSON with `serde_json`, the default codec.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Json;
 
-pub fn serialize<T>(data: &T) -> Result<Vec<u8>, DataTypeError>
-where
-    T: Debug + Serialize,
-{
-    serde_json::to_vec(data).map_err(|error| DataTypeError::Serialize {
-        type_name: any::type_name::<T>(),
-        error,
-    })
-}
+impl Codec for Json {
+    fn serialize(&self, value: &dyn erased_serde::Serialize) -> Result<Vec<u8>, CodecError> {
+        let mut value_vec = Vec::new();
+        erased_serde::serialize(value, &mut serde_json::Serializer::new(&mut value_vec))?;
 
-#[derive(Debug)]
-pub enum DataTypeError {
-    Deserialize {
-        type_name: &'static str,
-        error: serde_json::Error,
-    },
-    Serialize {
-        type_name: &'static str,
-        error: serde_json::Error,
-    },
-}
+        Ok(value_vec)
+    }
 
-impl std::fmt::Display for DataTypeError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{self:?}")
+    fn deserialize<'de>(
+        &self,
+        slice: &'de [u8],
+        visit: &mut Visit<'_, 'de>,
+    ) -> Result<(), CodecError> {
+        let mut deserializer = serde_json::Deserializer::from_slice(slice);
+        visit(&mut <dyn erased_serde::Deserializer>::erase(
+            &mut deserializer,
+        ))?;
+
+        Ok(deserializer.end()?)
     }
-}
 
-impl std::error::Error for DataTypeError {}
+    fn name(&self) -> &'static str {
+        "json"
+    }
+}
@@ -0,0 +1,826 @@
+//! Order-preserving encoding of the keys.
+//!
+//! The encoded keys compare in the same order as the keys themselves, so that
+//! RocksDB iterates numeric key components in numeric order. The components
+//! of tuples and structs are concatenated, and strings and byte strings are
+//! terminated, so the encoding of a tuple is a prefix of the encoding of every
+//! longer tuple starting with the same components:
+//!
+//! - Integers are big-endian with the sign bit flipped for the signed ones.
+//! - Floats are big-endian with the sign bit flipped for the positive ones and
+//!   every bit flipped for the negative ones.
+//! - Strings and byte strings escape `0x00` as `0x00 0xFF` and end with `0x00
+//!   0x00`.
+//! - `None` is `0x00` and `Some` is `0x01` followed by the value.
+//! - The elements of sequences and maps are preceded by `0x01` and the end is
+//!   marked with `0x00`.
+//! - Enum variants are the variant index as `u32` followed by the content.
+//!
+//! The encoding is not self-describing, so `deserialize_any()` is not
+//! supported.
+
+use serde::{
+    de::{
+        self, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+        Visitor,
+    },
+    ser, Deserialize, Serialize,
+};
+
+use super::prelude::*;
+
+const ESCAPE: u8 = 0x00;
+const ESCAPED: u8 = 0xFF;
+const TERMINATOR: u8 = 0x00;
+const NONE: u8 = 0x00;
+const SOME: u8 = 0x01;
+const ELEMENT: u8 = 0x01;
+const END: u8 = 0x00;
+
+/// Format of the keys of a database, set with
+/// [`KvStoreBuilder::set_key_encoding()`](crate::KvStoreBuilder::set_key_encoding).
+///
+/// # Compatibility
+///
+/// The two encodings are not compatible, and nothing on disk tells them
+/// apart. The databases created before the order-preserving encoding use
+/// [`KeyEncoding::Codec`], which stays the default, and must be rewritten
+/// key by key to switch to [`KeyEncoding::Ordered`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyEncoding {
+    /// Serialize the keys with the codec of the database like the values.
+    /// The serialized keys do not sort like the keys, so the prefix and range
+    /// iterators,
+    /// [`ColumnFamily::watch_prefix()`](crate::ColumnFamily::watch_prefix)
+    /// and [`ColumnFamily::migrate_prefix()`](crate::ColumnFamily::migrate_prefix)
+    /// fail with [`KvStoreError::Unsupported`](crate::KvStoreError::Unsupported).
+    #[default]
+    Codec,
+    /// Serialize the keys with the order-preserving encoding of this module,
+    /// so that a tuple key is iterated by its leading components.
+    Ordered,
+}
+
+pub fn serialize_key<T>(key: &T) -> Result<Vec<u8>, KeyError>
+where
+    T: Debug + Serialize,
+{
+    let mut serializer = KeySerializer { output: Vec::new() };
+    key.serialize(&mut serializer)?;
+
+    Ok(serializer.output)
+}
+
+pub fn deserialize_key<T>(key_slice: impl AsRef<[u8]>) -> Result<T, KeyError>
+where
+    T: Debug + DeserializeOwned + Serialize,
+{
+    let (key, rest) = deserialize_key_prefix(key_slice.as_ref())?;
+
+    match rest.is_empty() {
+        true => Ok(key),
+        false => Err(KeyError::TrailingBytes),
+    }
+}
+
+/// Deserialize the leading components of the key, e.g. the first component
+/// of a tuple, and return the rest of the key.
+pub fn deserialize_key_prefix<T>(key_slice: &[u8]) -> Result<(T, &[u8]), KeyError>
+where
+    T: Debug + DeserializeOwned + Serialize,
+{
+    let mut deserializer = KeyDeserializer { input: key_slice };
+    let key = T::deserialize(&mut deserializer)?;
+
+    Ok((key, deserializer.input))
+}
+
+#[derive(Debug)]
+pub enum KeyError {
+    Message(String),
+    Unsupported(&'static str),
+    UnexpectedEnd,
+    TrailingBytes,
+    InvalidTag(u8),
+    InvalidChar(u32),
+    InvalidString(std::string::FromUtf8Error),
+}
+
+impl std::fmt::Display for KeyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl ser::Error for KeyError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+impl de::Error for KeyError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self::Message(message.to_string())
+    }
+}
+
+struct KeySerializer {
+    output: Vec<u8>,
+}
+
+impl KeySerializer {
+    fn write_escaped(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.output.push(*byte);
+            if *byte == ESCAPE {
+                self.output.push(ESCAPED);
+            }
+        }
+        self.output.extend_from_slice(&[ESCAPE, TERMINATOR]);
+    }
+}
+
+impl ser::Serializer for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), KeyError> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), KeyError> {
+        self.serialize_u8(v as u8 ^ (1 << 7))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), KeyError> {
+        self.serialize_u16(v as u16 ^ (1 << 15))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), KeyError> {
+        self.serialize_u32(v as u32 ^ (1 << 31))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), KeyError> {
+        self.serialize_u64(v as u64 ^ (1 << 63))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), KeyError> {
+        self.serialize_u128(v as u128 ^ (1 << 127))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), KeyError> {
+        self.output.push(v);
+        Ok(())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), KeyError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), KeyError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), KeyError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), KeyError> {
+        self.output.extend_from_slice(&v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), KeyError> {
+        let bits = v.to_bits();
+        match bits >> 31 == 1 {
+            true => self.serialize_u32(!bits),
+            false => self.serialize_u32(bits ^ (1 << 31)),
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), KeyError> {
+        let bits = v.to_bits();
+        match bits >> 63 == 1 {
+            true => self.serialize_u64(!bits),
+            false => self.serialize_u64(bits ^ (1 << 63)),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), KeyError> {
+        self.serialize_u32(v as u32)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), KeyError> {
+        self.write_escaped(v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), KeyError> {
+        self.write_escaped(v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), KeyError> {
+        self.output.push(NONE);
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), KeyError> {
+        self.output.push(SOME);
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), KeyError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<(), KeyError> {
+        self.serialize_u32(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), KeyError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        value: &T,
+    ) -> Result<(), KeyError> {
+        self.serialize_u32(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, KeyError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, KeyError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, KeyError> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, KeyError> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, KeyError> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, KeyError> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self, KeyError> {
+        self.serialize_u32(variant_index)?;
+        Ok(self)
+    }
+}
+
+impl ser::SerializeSeq for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), KeyError> {
+        self.output.push(ELEMENT);
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleStruct for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), KeyError> {
+        self.output.push(ELEMENT);
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        self.output.push(END);
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStructVariant for &mut KeySerializer {
+    type Ok = ();
+    type Error = KeyError;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), KeyError> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+}
+
+struct KeyDeserializer<'de> {
+    input: &'de [u8],
+}
+
+impl KeyDeserializer<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], KeyError> {
+        let (bytes, input) = self
+            .input
+            .split_first_chunk::<N>()
+            .ok_or(KeyError::UnexpectedEnd)?;
+        self.input = input;
+
+        Ok(*bytes)
+    }
+
+    fn take_u8(&mut self) -> Result<u8, KeyError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn take_u32(&mut self) -> Result<u32, KeyError> {
+        Ok(u32::from_be_bytes(self.take()?))
+    }
+
+    fn take_u64(&mut self) -> Result<u64, KeyError> {
+        Ok(u64::from_be_bytes(self.take()?))
+    }
+
+    fn take_escaped(&mut self) -> Result<Vec<u8>, KeyError> {
+        let mut bytes = Vec::new();
+        loop {
+            match self.take_u8()? {
+                ESCAPE => match self.take_u8()? {
+                    ESCAPED => bytes.push(ESCAPE),
+                    TERMINATOR => return Ok(bytes),
+                    others => return Err(KeyError::InvalidTag(others)),
+                },
+                byte => bytes.push(byte),
+            }
+        }
+    }
+
+    /// Whether the next element of a sequence or a map exists.
+    fn take_element(&mut self) -> Result<bool, KeyError> {
+        match self.take_u8()? {
+            ELEMENT => Ok(true),
+            END => Ok(false),
+            others => Err(KeyError::InvalidTag(others)),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut KeyDeserializer<'de> {
+    type Error = KeyError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, KeyError> {
+        Err(KeyError::Unsupported("deserialize_any"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        match self.take_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            others => Err(KeyError::InvalidTag(others)),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_i8((self.take_u8()? ^ (1 << 7)) as i8)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_i16((u16::from_be_bytes(self.take()?) ^ (1 << 15)) as i16)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_i32((self.take_u32()? ^ (1 << 31)) as i32)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_i64((self.take_u64()? ^ (1 << 63)) as i64)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_i128((u128::from_be_bytes(self.take()?) ^ (1 << 127)) as i128)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u8(self.take_u8()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u16(u16::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u32(self.take_u32()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u64(self.take_u64()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u128(u128::from_be_bytes(self.take()?))
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        let bits = self.take_u32()?;
+        match bits >> 31 == 1 {
+            true => visitor.visit_f32(f32::from_bits(bits ^ (1 << 31))),
+            false => visitor.visit_f32(f32::from_bits(!bits)),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        let bits = self.take_u64()?;
+        match bits >> 63 == 1 {
+            true => visitor.visit_f64(f64::from_bits(bits ^ (1 << 63))),
+            false => visitor.visit_f64(f64::from_bits(!bits)),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        let code = self.take_u32()?;
+        visitor.visit_char(char::from_u32(code).ok_or(KeyError::InvalidChar(code))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        let string = String::from_utf8(self.take_escaped()?).map_err(KeyError::InvalidString)?;
+        visitor.visit_string(string)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_byte_buf(self.take_escaped()?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        match self.take_u8()? {
+            NONE => visitor.visit_none(),
+            SOME => visitor.visit_some(self),
+            others => Err(KeyError::InvalidTag(others)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_seq(Elements { deserializer: self })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        visitor.visit_seq(Fields {
+            deserializer: self,
+            remaining: len,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_map(Elements { deserializer: self })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, KeyError> {
+        visitor.visit_u32(self.take_u32()?)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, KeyError> {
+        Err(KeyError::Unsupported("deserialize_ignored_any"))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+/// Elements of a sequence or a map, which are terminated by [`END`].
+struct Elements<'a, 'de> {
+    deserializer: &'a mut KeyDeserializer<'de>,
+}
+
+impl<'de> SeqAccess<'de> for Elements<'_, 'de> {
+    type Error = KeyError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, KeyError> {
+        match self.deserializer.take_element()? {
+            true => seed.deserialize(&mut *self.deserializer).map(Some),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for Elements<'_, 'de> {
+    type Error = KeyError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, KeyError> {
+        match self.deserializer.take_element()? {
+            true => seed.deserialize(&mut *self.deserializer).map(Some),
+            false => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, KeyError> {
+        seed.deserialize(&mut *self.deserializer)
+    }
+}
+
+/// Fields of a tuple or a struct, whose number is known.
+struct Fields<'a, 'de> {
+    deserializer: &'a mut KeyDeserializer<'de>,
+    remaining: usize,
+}
+
+impl<'de> SeqAccess<'de> for Fields<'_, 'de> {
+    type Error = KeyError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, KeyError> {
+        match self.remaining {
+            0 => Ok(None),
+            _others => {
+                self.remaining -= 1;
+                seed.deserialize(&mut *self.deserializer).map(Some)
+            }
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut KeyDeserializer<'de> {
+    type Error = KeyError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self), KeyError> {
+        let variant_index = self.take_u32()?;
+        let variant = seed.deserialize(IntoDeserializer::<KeyError>::into_deserializer(
+            variant_index,
+        ))?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut KeyDeserializer<'de> {
+    type Error = KeyError;
+
+    fn unit_variant(self) -> Result<(), KeyError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, KeyError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, KeyError> {
+        de::Deserializer::deserialize_tuple(self, len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, KeyError> {
+        de::Deserializer::deserialize_tuple(self, fields.len(), visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    fn assert_ordered<T>(keys: &[T])
+    where
+        T: Debug + DeserializeOwned + PartialEq + Serialize,
+    {
+        let encoded: Vec<Vec<u8>> = keys.iter().map(|key| serialize_key(key).unwrap()).collect();
+        for pair in encoded.windows(2) {
+            assert!(pair[0] < pair[1], "{keys:?}");
+        }
+        for (key, encoded) in keys.iter().zip(encoded) {
+            assert_eq!(&deserialize_key::<T>(encoded).unwrap(), key);
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Kind {
+        Block(u64),
+        Rollup { id: String },
+    }
+
+    #[test]
+    fn test_order() {
+        assert_ordered(&[0_u64, 9, 10, 255, 256, u64::MAX]);
+        assert_ordered(&[i64::MIN, -256, -1, 0, 1, i64::MAX]);
+        assert_ordered(&[f64::NEG_INFINITY, -1.5, -0.0, 0.0, 1.5, f64::INFINITY]);
+        assert_ordered(&[
+            String::new(),
+            "a".to_owned(),
+            "a\0".to_owned(),
+            "a\0\0".to_owned(),
+            "a\u{1}".to_owned(),
+            "ab".to_owned(),
+        ]);
+        assert_ordered(&[None, Some(0_u8), Some(1)]);
+        assert_ordered(&[vec![], vec![0_u32], vec![0, 0], vec![1]]);
+        assert_ordered(&[
+            ("Block".to_owned(), 9_u64, true),
+            ("Block".to_owned(), 10, false),
+            ("Blocks".to_owned(), 0, false),
+        ]);
+        assert_ordered(&[
+            Kind::Block(2),
+            Kind::Rollup {
+                id: "rollup".to_owned(),
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_prefix() {
+        let key = serialize_key(&("Rollup", "cluster", 1_u64)).unwrap();
+
+        assert!(key.starts_with(&serialize_key(&("Rollup", "cluster")).unwrap()));
+        assert!(!key.starts_with(&serialize_key(&("Rollup", "clust")).unwrap()));
+    }
+}
@@ -1,15 +1,23 @@
-#[cfg(feature = "bytes")]
+#[cfg(feature = "bincode")]
 mod bytes;
-#[cfg(any(feature = "default", feature = "json"))]
+#[cfg(feature = "cbor")]
+mod cbor;
+mod codec;
 mod json;
+mod key;
 
-#[cfg(feature = "bytes")]
-pub use bytes::{deserialize, serialize, DataTypeError};
-#[cfg(any(feature = "default", feature = "json"))]
-pub use json::{deserialize, serialize, DataTypeError};
+#[cfg(feature = "bincode")]
+pub use bytes::Bincode;
+#[cfg(feature = "cbor")]
+pub use cbor::Cbor;
+pub use codec::{default_codec, deserialize, serialize, Codec, CodecError, DataTypeError, Visit};
+pub use json::Json;
+pub use key::{deserialize_key, deserialize_key_prefix, serialize_key, KeyEncoding, KeyError};
 
 mod prelude {
     pub use std::{any, fmt::Debug};
 
     pub use serde::{de::DeserializeOwned, ser::Serialize};
+
+    pub use super::codec::{Codec, CodecError, Visit};
 }
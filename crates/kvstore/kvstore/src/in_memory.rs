@@ -1,37 +1,394 @@
 use std::{
-    any::{type_name, Any},
-    collections::HashMap,
+    any::{type_name, Any, TypeId},
+    collections::{BTreeMap, HashMap},
     fmt::Debug,
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use serde::Serialize;
 use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard};
 
-use crate::data_type::serialize;
+use crate::data_type::{default_codec, serialize, Codec};
 
 type Key = Vec<u8>;
 type ValueAny = Box<dyn Any + Send + Sync>;
+type OnEviction = Arc<dyn Fn(Evicted) + Send + Sync>;
+type Weigher = Arc<dyn Fn(&dyn Any) -> usize + Send + Sync>;
+
+/// Which entry [`CachedKvStore`] evicts first when it exceeds the limits set
+/// with [`CachedKvStoreBuilder`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// The least recently put or read entry.
+    #[default]
+    Lru,
+    /// The least frequently put or read entry, or the least recently one
+    /// among them.
+    Lfu,
+}
+
+/// Why an entry has been evicted, see [`Evicted`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionCause {
+    /// The store has exceeded the maximum number of entries or bytes.
+    Capacity,
+    /// The TTL of the entry has elapsed.
+    Expired,
+}
+
+/// Entry evicted from [`CachedKvStore`], passed to the callback set with
+/// [`CachedKvStoreBuilder::on_eviction()`]. The entries deleted or
+/// overwritten are not evicted.
+pub struct Evicted {
+    key_vec: Key,
+    value: ValueAny,
+    cause: EvictionCause,
+}
+
+impl Evicted {
+    /// The key serialized with the codec of the store.
+    pub fn key(&self) -> &[u8] {
+        &self.key_vec
+    }
+
+    pub fn cause(&self) -> EvictionCause {
+        self.cause
+    }
+
+    /// The value if it is of the type `V` and not locked by
+    /// [`CachedKvStore::get_mut()`].
+    pub fn value<V>(&self) -> Option<V>
+    where
+        V: Clone + Any + Send + 'static,
+    {
+        let value = self.value.downcast_ref::<Arc<Mutex<V>>>()?;
+        let value_inner = value.try_lock().ok()?.clone();
+
+        Some(value_inner)
+    }
+}
+
+impl Debug for Evicted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Evicted")
+            .field("key", &self.key_vec)
+            .field("cause", &self.cause)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Builder of a [`CachedKvStore`] bounded by the number of entries or by an
+/// approximate number of bytes, which is unbounded by default.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use kvstore::{CachedKvStoreBuilder, EvictionPolicy};
+///
+/// let database = CachedKvStoreBuilder::default()
+///     .set_max_entries(2)
+///     .set_eviction_policy(EvictionPolicy::Lru)
+///     .on_eviction(|evicted| println!("{:?}: {:?}", evicted.cause(), evicted.value::<u64>()))
+///     .build();
+///
+/// database.blocking_put(&"a", 1_u64).unwrap();
+/// database.blocking_put(&"b", 2_u64).unwrap();
+/// database.blocking_get::<_, u64>(&"a").unwrap();
+/// // Evicts "b", which is the least recently used.
+/// database
+///     .blocking_put_with_ttl(&"c", 3_u64, Duration::from_secs(60))
+///     .unwrap();
+/// assert!(database.blocking_get::<_, u64>(&"b").is_err());
+/// ```
+#[derive(Default)]
+pub struct CachedKvStoreBuilder {
+    codec: Option<Arc<dyn Codec>>,
+    options: CacheOptions,
+}
+
+impl CachedKvStoreBuilder {
+    /// Serialize the keys with `codec` instead of the default one.
+    pub fn set_codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Some(Arc::new(codec));
+
+        self
+    }
+
+    /// Evict entries when there are more than `max_entries`.
+    pub fn set_max_entries(mut self, max_entries: usize) -> Self {
+        self.options.max_entries = Some(max_entries);
+
+        self
+    }
+
+    /// Evict entries when their size exceeds `max_bytes`. The size of an
+    /// entry is the length of its serialized key and the size of the value
+    /// given by the weigher of its type set with
+    /// [`CachedKvStoreBuilder::set_weigher()`], or the shallow size of the
+    /// value without weigher. The entries larger than `max_bytes` are not put
+    /// and fail with [`CachedKvStoreError::TooLarge`].
+    pub fn set_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.options.max_bytes = Some(max_bytes);
+
+        self
+    }
+
+    /// Weigh the values of type `V` with `weigher` for
+    /// [`CachedKvStoreBuilder::set_max_bytes()`], e.g. to count the memory
+    /// they own on the heap. Set once for every type of values.
+    pub fn set_weigher<V>(mut self, weigher: impl Fn(&V) -> usize + Send + Sync + 'static) -> Self
+    where
+        V: Any,
+    {
+        self.options.weighers.insert(
+            TypeId::of::<V>(),
+            Arc::new(move |value| value.downcast_ref().map(&weigher).unwrap_or_default()),
+        );
+
+        self
+    }
+
+    pub fn set_eviction_policy(mut self, eviction_policy: EvictionPolicy) -> Self {
+        self.options.eviction_policy = eviction_policy;
+
+        self
+    }
+
+    /// Expire the entries `ttl` after they are put unless they are put with
+    /// their own TTL. The expired entries are evicted when they are read or
+    /// purged with [`CachedKvStore::purge_expired()`].
+    pub fn set_ttl(mut self, ttl: Duration) -> Self {
+        self.options.ttl = Some(ttl);
+
+        self
+    }
+
+    /// Call `callback` with every evicted entry, after the store is unlocked.
+    pub fn on_eviction(mut self, callback: impl Fn(Evicted) + Send + Sync + 'static) -> Self {
+        self.options.on_eviction = Some(Arc::new(callback));
+
+        self
+    }
+
+    pub fn build(self) -> CachedKvStore {
+        CachedKvStore {
+            inner: Arc::new(Mutex::new(State::default())),
+            codec: self.codec.unwrap_or_else(default_codec),
+            options: Arc::new(self.options),
+        }
+    }
+}
+
+#[derive(Default)]
+struct CacheOptions {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    weighers: HashMap<TypeId, Weigher>,
+    eviction_policy: EvictionPolicy,
+    ttl: Option<Duration>,
+    on_eviction: Option<OnEviction>,
+}
+
+impl CacheOptions {
+    /// The size of the entry, which fails if it exceeds the maximum number of
+    /// bytes by itself.
+    fn size<V>(&self, key_vec: &[u8], value: &V) -> Result<usize, CachedKvStoreError>
+    where
+        V: Any,
+    {
+        let value_size = match self.weighers.get(&TypeId::of::<V>()) {
+            Some(weigher) => weigher(value),
+            None => std::mem::size_of::<V>(),
+        };
+        let size = key_vec.len() + value_size;
+
+        match self.max_bytes {
+            Some(max_bytes) if size > max_bytes => Err(CachedKvStoreError::TooLarge(size)),
+            _others => Ok(size),
+        }
+    }
+
+    fn is_bounded(&self) -> bool {
+        self.max_entries.is_some() || self.max_bytes.is_some()
+    }
+
+    fn is_exceeded(&self, state: &State) -> bool {
+        self.max_entries
+            .is_some_and(|max_entries| state.entries.len() > max_entries)
+            || self
+                .max_bytes
+                .is_some_and(|max_bytes| state.bytes > max_bytes)
+    }
+}
+
+struct Entry {
+    value: ValueAny,
+    size: usize,
+    expiration: Option<Instant>,
+    frequency: u64,
+    // The position in `State::order`.
+    rank: Rank,
+}
+
+impl Entry {
+    /// Move the entry accessed at `clock` to the end of the order of
+    /// eviction.
+    fn touch(
+        &mut self,
+        options: &CacheOptions,
+        clock: u64,
+        order: &mut BTreeMap<Rank, Key>,
+        key_vec: &[u8],
+    ) {
+        order.remove(&self.rank);
+
+        self.frequency += 1;
+        self.rank = match options.eviction_policy {
+            EvictionPolicy::Lru => (0, clock),
+            EvictionPolicy::Lfu => (self.frequency, clock),
+        };
+        order.insert(self.rank, key_vec.to_vec());
+    }
+}
+
+/// The order of eviction: the frequency for [`EvictionPolicy::Lfu`] or 0,
+/// then the time of the last access.
+type Rank = (u64, u64);
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Key, Entry>,
+    // The keys in the order of eviction, which is kept only if the store is
+    // bounded.
+    order: BTreeMap<Rank, Key>,
+    bytes: usize,
+    // Incremented at every access.
+    clock: u64,
+    // Passed to the callback after the store is unlocked.
+    evicted: Vec<Evicted>,
+}
+
+impl State {
+    fn insert(
+        &mut self,
+        options: &CacheOptions,
+        key_vec: Key,
+        value: ValueAny,
+        size: usize,
+        ttl: Option<Duration>,
+    ) {
+        self.remove(&key_vec);
+
+        let mut entry = Entry {
+            value,
+            size,
+            expiration: ttl
+                .or(options.ttl)
+                .and_then(|ttl| Instant::now().checked_add(ttl)),
+            frequency: 0,
+            rank: (0, 0),
+        };
+        self.bytes += size;
+        if options.is_bounded() {
+            self.clock += 1;
+            entry.touch(options, self.clock, &mut self.order, &key_vec);
+        }
+        self.entries.insert(key_vec.clone(), entry);
+
+        // The new entry is evicted last.
+        while options.is_exceeded(self) {
+            let victim = self
+                .order
+                .iter()
+                .find(|(_, victim)| **victim != key_vec)
+                .or_else(|| self.order.first_key_value())
+                .map(|(_, victim)| victim.clone());
+            match victim {
+                Some(victim) => self.evict(&victim, EvictionCause::Capacity),
+                None => break,
+            }
+        }
+    }
+
+    /// Get the value unless it has expired, marking it as accessed.
+    fn get<V>(
+        &mut self,
+        options: &CacheOptions,
+        key_vec: &[u8],
+    ) -> Result<Arc<Mutex<V>>, CachedKvStoreError>
+    where
+        V: Clone + Any + Send + 'static,
+    {
+        let is_expired = self
+            .entries
+            .get(key_vec)
+            .and_then(|entry| entry.expiration)
+            .is_some_and(|expiration| expiration <= Instant::now());
+        if is_expired {
+            self.evict(key_vec, EvictionCause::Expired);
+        }
 
-fn downcast<V>(
-    database: MutexGuard<'_, HashMap<Key, ValueAny>>,
-    key_vec: Vec<u8>,
-) -> Result<Arc<Mutex<V>>, CachedKvStoreError>
-where
-    V: Clone + Any + Send + 'static,
-{
-    let value = database
-        .get(&key_vec)
-        .ok_or(CachedKvStoreError::KeyError(type_name::<V>()))?
-        .downcast_ref::<Arc<Mutex<V>>>()
-        .ok_or(CachedKvStoreError::Downcast(type_name::<V>()))?
-        .clone();
+        let entry = self
+            .entries
+            .get_mut(key_vec)
+            .ok_or(CachedKvStoreError::KeyError(type_name::<V>()))?;
+        if options.is_bounded() {
+            self.clock += 1;
+            entry.touch(options, self.clock, &mut self.order, key_vec);
+        }
 
-    Ok(value)
+        entry
+            .value
+            .downcast_ref::<Arc<Mutex<V>>>()
+            .ok_or(CachedKvStoreError::Downcast(type_name::<V>()))
+            .cloned()
+    }
+
+    fn remove(&mut self, key_vec: &[u8]) -> Option<Entry> {
+        let entry = self.entries.remove(key_vec)?;
+        self.order.remove(&entry.rank);
+        self.bytes -= entry.size;
+
+        Some(entry)
+    }
+
+    fn evict(&mut self, key_vec: &[u8], cause: EvictionCause) {
+        if let Some(entry) = self.remove(key_vec) {
+            self.evicted.push(Evicted {
+                key_vec: key_vec.to_vec(),
+                value: entry.value,
+                cause,
+            });
+        }
+    }
+
+    fn purge_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let expired: Vec<Key> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.expiration.is_some_and(|expiration| expiration <= now))
+            .map(|(key_vec, _)| key_vec.clone())
+            .collect();
+
+        for key_vec in &expired {
+            self.evict(key_vec, EvictionCause::Expired);
+        }
+
+        expired.len()
+    }
 }
 
+/// Values shared in memory without serialization, which is unbounded unless
+/// it is built with [`CachedKvStoreBuilder`].
 pub struct CachedKvStore {
-    inner: Arc<Mutex<HashMap<Key, ValueAny>>>,
+    inner: Arc<Mutex<State>>,
+    // Serializes the keys.
+    codec: Arc<dyn Codec>,
+    options: Arc<CacheOptions>,
 }
 
 unsafe impl Send for CachedKvStore {}
@@ -42,31 +399,30 @@ impl Clone for CachedKvStore {
     fn clone(&self) -> Self {
         Self {
             inner: self.inner.clone(),
+            codec: self.codec.clone(),
+            options: self.options.clone(),
         }
     }
 }
 
 impl Default for CachedKvStore {
     fn default() -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(HashMap::default())),
-        }
+        CachedKvStoreBuilder::default().build()
     }
 }
 
 impl CachedKvStore {
+    /// Serialize the keys with `codec` instead of the default one.
+    pub fn with_codec(codec: impl Codec + 'static) -> Self {
+        CachedKvStoreBuilder::default().set_codec(codec).build()
+    }
+
     pub fn blocking_put<K, V>(&self, key: &K, value: V) -> Result<(), CachedKvStoreError>
     where
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
-        let value_any: ValueAny = Box::new(Arc::new(Mutex::new(value)));
-
-        let mut database = self.inner.blocking_lock();
-        database.insert(key_vec, value_any);
-
-        Ok(())
+        self.blocking_put_inner(key, value, None)
     }
 
     pub async fn put<K, V>(&self, key: &K, value: V) -> Result<(), CachedKvStoreError>
@@ -74,13 +430,37 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
-        let value_any: ValueAny = Box::new(Arc::new(Mutex::new(value)));
+        self.put_inner(key, value, None).await
+    }
 
-        let mut database = self.inner.lock().await;
-        database.insert(key_vec, value_any);
+    /// Put the value, which expires after `ttl` instead of the TTL set with
+    /// [`CachedKvStoreBuilder::set_ttl()`].
+    pub fn blocking_put_with_ttl<K, V>(
+        &self,
+        key: &K,
+        value: V,
+        ttl: Duration,
+    ) -> Result<(), CachedKvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Clone + Any + Send + 'static,
+    {
+        self.blocking_put_inner(key, value, Some(ttl))
+    }
 
-        Ok(())
+    /// Put the value, which expires after `ttl` instead of the TTL set with
+    /// [`CachedKvStoreBuilder::set_ttl()`].
+    pub async fn put_with_ttl<K, V>(
+        &self,
+        key: &K,
+        value: V,
+        ttl: Duration,
+    ) -> Result<(), CachedKvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Clone + Any + Send + 'static,
+    {
+        self.put_inner(key, value, Some(ttl)).await
     }
 
     pub fn blocking_get<K, V>(&self, key: &K) -> Result<V, CachedKvStoreError>
@@ -88,12 +468,13 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
-        let database = self.inner.blocking_lock();
-        let value = downcast::<V>(database, key_vec)?;
+        let mut database = self.inner.blocking_lock();
+        let value = database.get::<V>(&self.options, &key_vec);
+        self.notify(database);
 
-        let value_inner = value.blocking_lock().clone();
+        let value_inner = value?.blocking_lock().clone();
 
         Ok(value_inner)
     }
@@ -103,12 +484,13 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
-        let database = self.inner.lock().await;
-        let value = downcast::<V>(database, key_vec)?;
+        let mut database = self.inner.lock().await;
+        let value = database.get::<V>(&self.options, &key_vec);
+        self.notify(database);
 
-        let value_inner = value.lock().await.clone();
+        let value_inner = value?.lock().await.clone();
 
         Ok(value_inner)
     }
@@ -118,12 +500,13 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
-        let database = self.inner.blocking_lock();
-        let value = downcast::<V>(database, key_vec)?;
+        let mut database = self.inner.blocking_lock();
+        let value = database.get::<V>(&self.options, &key_vec);
+        self.notify(database);
 
-        Ok(Value::blocking_lock(value))
+        Ok(Value::blocking_lock(value?))
     }
 
     pub async fn get_mut<K, V>(&self, key: &K) -> Result<Value<V>, CachedKvStoreError>
@@ -131,12 +514,13 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
-        let database = self.inner.lock().await;
-        let value = downcast::<V>(database, key_vec)?;
+        let mut database = self.inner.lock().await;
+        let value = database.get::<V>(&self.options, &key_vec);
+        self.notify(database);
 
-        Ok(Value::lock(value).await)
+        Ok(Value::lock(value?).await)
     }
 
     pub fn blocking_delete<K, V>(&self, key: &K) -> Result<(), CachedKvStoreError>
@@ -144,7 +528,7 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
         let mut database = self.inner.blocking_lock();
         database.remove(&key_vec);
@@ -157,13 +541,83 @@ impl CachedKvStore {
         K: Debug + Serialize,
         V: Clone + Any + Send + 'static,
     {
-        let key_vec = serialize(key)?;
+        let key_vec = serialize(self.codec.as_ref(), key)?;
 
         let mut database = self.inner.lock().await;
         database.remove(&key_vec);
 
         Ok(())
     }
+
+    /// Evict every expired entry and return the number of them.
+    pub fn blocking_purge_expired(&self) -> usize {
+        let mut database = self.inner.blocking_lock();
+        let purged = database.purge_expired();
+        self.notify(database);
+
+        purged
+    }
+
+    /// Evict every expired entry and return the number of them.
+    pub async fn purge_expired(&self) -> usize {
+        let mut database = self.inner.lock().await;
+        let purged = database.purge_expired();
+        self.notify(database);
+
+        purged
+    }
+
+    fn blocking_put_inner<K, V>(
+        &self,
+        key: &K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<(), CachedKvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Clone + Any + Send + 'static,
+    {
+        let key_vec = serialize(self.codec.as_ref(), key)?;
+        let size = self.options.size(&key_vec, &value)?;
+        let value_any: ValueAny = Box::new(Arc::new(Mutex::new(value)));
+
+        let mut database = self.inner.blocking_lock();
+        database.insert(&self.options, key_vec, value_any, size, ttl);
+        self.notify(database);
+
+        Ok(())
+    }
+
+    async fn put_inner<K, V>(
+        &self,
+        key: &K,
+        value: V,
+        ttl: Option<Duration>,
+    ) -> Result<(), CachedKvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Clone + Any + Send + 'static,
+    {
+        let key_vec = serialize(self.codec.as_ref(), key)?;
+        let size = self.options.size(&key_vec, &value)?;
+        let value_any: ValueAny = Box::new(Arc::new(Mutex::new(value)));
+
+        let mut database = self.inner.lock().await;
+        database.insert(&self.options, key_vec, value_any, size, ttl);
+        self.notify(database);
+
+        Ok(())
+    }
+
+    /// Unlock the store and pass the evicted entries to the callback.
+    fn notify(&self, mut database: MutexGuard<'_, State>) {
+        let evicted = std::mem::take(&mut database.evicted);
+        drop(database);
+
+        if let Some(on_eviction) = &self.options.on_eviction {
+            evicted.into_iter().for_each(|evicted| on_eviction(evicted));
+        }
+    }
 }
 
 /// An owned mutex equivalent to [`crate::Lock`] except that [`Value<V>`] does
@@ -234,6 +688,8 @@ pub enum CachedKvStoreError {
     DataType(crate::data_type::DataTypeError),
     KeyError(&'static str),
     Downcast(&'static str),
+    /// The size of the entry exceeds the maximum number of bytes.
+    TooLarge(usize),
 }
 
 impl std::fmt::Display for CachedKvStoreError {
@@ -249,3 +705,76 @@ impl From<crate::data_type::DataTypeError> for CachedKvStoreError {
         Self::DataType(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_eviction() {
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let database = CachedKvStoreBuilder::default()
+            .set_max_entries(2)
+            .set_eviction_policy(EvictionPolicy::Lfu)
+            .on_eviction({
+                let evicted = evicted.clone();
+                move |entry| {
+                    evicted
+                        .lock()
+                        .unwrap()
+                        .push((entry.value::<u64>().unwrap(), entry.cause()))
+                }
+            })
+            .build();
+
+        database.blocking_put(&"a", 1_u64).unwrap();
+        database.blocking_put(&"b", 2_u64).unwrap();
+        database.blocking_get::<_, u64>(&"a").unwrap();
+        database.blocking_put(&"c", 3_u64).unwrap();
+        database
+            .blocking_put_with_ttl(&"d", 4_u64, Duration::ZERO)
+            .unwrap();
+        assert!(database.blocking_get::<_, u64>(&"d").is_err());
+        assert_eq!(database.blocking_get::<_, u64>(&"a").unwrap(), 1);
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            [
+                (2, EvictionCause::Capacity),
+                (3, EvictionCause::Capacity),
+                (4, EvictionCause::Expired),
+            ]
+        );
+
+        let database = CachedKvStoreBuilder::default()
+            .set_max_bytes(2 * (3 + std::mem::size_of::<u64>()))
+            .set_ttl(Duration::ZERO)
+            .build();
+        database.blocking_put(&"a", 1_u64).unwrap();
+        database.blocking_put(&"b", 2_u64).unwrap();
+        database.blocking_put(&"c", 3_u64).unwrap();
+        assert_eq!(database.blocking_purge_expired(), 2);
+    }
+
+    #[test]
+    fn test_weigher() {
+        let database = CachedKvStoreBuilder::default()
+            .set_max_bytes(10)
+            .set_weigher(String::len)
+            .build();
+
+        // The keys are 3 bytes in JSON.
+        database.blocking_put(&"a", "12345".to_owned()).unwrap();
+        assert!(matches!(
+            database.blocking_put(&"b", "12345678".to_owned()),
+            Err(CachedKvStoreError::TooLarge(11))
+        ));
+        assert!(database.blocking_get::<_, String>(&"b").is_err());
+        assert_eq!(database.blocking_get::<_, String>(&"a").unwrap(), "12345");
+
+        database.blocking_put(&"c", "12".to_owned()).unwrap();
+        assert!(database.blocking_get::<_, String>(&"a").is_err());
+        assert_eq!(database.blocking_get::<_, String>(&"c").unwrap(), "12");
+    }
+}
@@ -2,6 +2,29 @@ mod data_type;
 mod in_memory;
 mod on_disk;
 
-pub use in_memory::{CachedKvStore, CachedKvStoreError, Value};
+#[cfg(feature = "bincode")]
+pub use data_type::Bincode;
+#[cfg(feature = "cbor")]
+pub use data_type::Cbor;
+pub use data_type::{
+    deserialize_key, deserialize_key_prefix, serialize_key, Codec, CodecError, Json, KeyEncoding,
+    KeyError, Visit,
+};
+pub use erased_serde;
+pub use in_memory::{
+    CachedKvStore, CachedKvStoreBuilder, CachedKvStoreError, Evicted, EvictionCause,
+    EvictionPolicy, Value,
+};
 pub use kvstore_macros::*;
-pub use on_disk::{kvstore, KvStore, KvStoreBuilder, KvStoreError, Lock};
+pub use on_disk::{
+    kvstore, kvstore_named, register_codec, register_schema, AsyncKvStore, AsyncLock,
+    BatchOperation, ColumnFamily, ColumnFamilyFormat, ColumnFamilyOptions, ConcurrencyMode,
+    ErrorKind, Iter, KvStore, KvStoreBuilder, KvStoreError, Lock, MemoryBackend, Schema, Snapshot,
+    SnapshotColumnFamily, StorageBackend, StorageCursor, StorageError, StorageSnapshot,
+    StorageTransaction, StoredFormat, StoredValue, Transaction, TransactionColumnFamily, Version,
+    Watch, WatchEvent, WriteBatch, WriteBatchColumnFamily,
+};
+#[cfg(feature = "rocksdb")]
+pub use on_disk::{BackupEngine, ColumnFamilyStatistics, LatencyHistogram, SstWriter, Statistics};
+#[cfg(feature = "rocksdb")]
+pub use rocksdb::{backup::BackupEngineInfo, DBCompressionType};
@@ -0,0 +1,369 @@
+use std::{
+    fmt::Debug,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+use tokio::sync::{oneshot, Semaphore};
+
+use super::{
+    ColumnFamily, KvStore, KvStoreError, Lock, Transaction, WriteBatch, DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Dedicated threads to run the blocking operations of [`AsyncKvStore`] on,
+/// so that they do not block the threads of the async runtime. The threads
+/// are spawned on the first use and never exit, since [`AsyncKvStore`] is
+/// only created from the stores which live until the program exits.
+pub(super) struct BlockingPool {
+    size: usize,
+    semaphore: Arc<Semaphore>,
+    sender: OnceLock<Sender<Job>>,
+}
+
+impl BlockingPool {
+    pub(super) fn new(size: usize) -> Self {
+        Self {
+            size,
+            semaphore: Arc::new(Semaphore::new(size)),
+            sender: OnceLock::new(),
+        }
+    }
+
+    fn sender(&self) -> &Sender<Job> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) = channel::<Job>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            for index in 0..self.size {
+                let receiver = receiver.clone();
+                thread::Builder::new()
+                    .name(format!("kvstore-blocking-{index}"))
+                    .spawn(move || work(receiver))
+                    .expect("Failed to spawn a blocking thread");
+            }
+
+            sender
+        })
+    }
+
+    /// Run `function` on one of the threads, waiting for a free thread if all
+    /// of them are busy.
+    async fn run<F, T>(&self, function: F) -> Result<T, KvStoreError>
+    where
+        F: FnOnce() -> Result<T, KvStoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| KvStoreError::Blocking)?;
+
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _permit = permit;
+            let _ = sender.send(function());
+        });
+        self.sender()
+            .send(job)
+            .map_err(|_| KvStoreError::Blocking)?;
+
+        // The sender is dropped without sending if `function` panics.
+        receiver.await.map_err(|_| KvStoreError::Blocking)?
+    }
+
+    /// Run `function` on one of the threads without waiting for it, e.g. in
+    /// `Drop`, or on the current thread if the threads have exited.
+    fn spawn<F>(&self, function: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Err(error) = self.sender().send(Box::new(function)) {
+            (error.0)();
+        }
+    }
+}
+
+fn work(receiver: Arc<Mutex<Receiver<Job>>>) {
+    loop {
+        let job = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        match job {
+            Ok(job) => {
+                let _ = catch_unwind(AssertUnwindSafe(job));
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+/// Async version of [`KvStore`] returned by [`KvStore::asynchronous()`]. The
+/// operations run on the dedicated threads of the [`KvStore`], whose number
+/// is set with
+/// [`KvStoreBuilder::set_blocking_threads()`](super::KvStoreBuilder::set_blocking_threads).
+///
+/// Unlike [`KvStore`], the keys and the values are taken by value because
+/// they are moved to the thread.
+#[derive(Clone)]
+pub struct AsyncKvStore {
+    kvstore: &'static KvStore,
+    column_family: String,
+}
+
+impl AsyncKvStore {
+    pub(super) fn new(kvstore: &'static KvStore) -> Self {
+        Self {
+            kvstore,
+            column_family: DEFAULT_COLUMN_FAMILY_NAME.to_owned(),
+        }
+    }
+
+    /// Scope the operations to the column family.
+    pub fn column_family(&self, name: &str) -> Result<Self, KvStoreError> {
+        self.kvstore.column_family(name)?;
+
+        Ok(Self {
+            kvstore: self.kvstore,
+            column_family: name.to_owned(),
+        })
+    }
+
+    async fn run<F, T>(&self, function: F) -> Result<T, KvStoreError>
+    where
+        F: FnOnce(ColumnFamily<'static>) -> Result<T, KvStoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let kvstore = self.kvstore;
+        let name = self.column_family.clone();
+
+        kvstore
+            .blocking_pool
+            .run(move || function(kvstore.column_family(&name)?))
+            .await
+    }
+
+    pub async fn put<K, V>(&self, key: K, value: V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+    {
+        self.run(move |column_family| column_family.put(&key, &value))
+            .await
+    }
+
+    pub async fn put_with_ttl<K, V>(
+        &self,
+        key: K,
+        value: V,
+        ttl: Duration,
+    ) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+    {
+        self.run(move |column_family| column_family.put_with_ttl(&key, &value, ttl))
+            .await
+    }
+
+    pub async fn get<K, V>(&self, key: K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+    {
+        self.run(move |column_family| column_family.get(&key)).await
+    }
+
+    pub async fn get_or<K, V, F>(&self, key: K, function: F) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        self.run(move |column_family| column_family.get_or(&key, function))
+            .await
+    }
+
+    pub async fn get_or_default<K, V>(&self, key: K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + Default + DeserializeOwned + Serialize + Send + 'static,
+    {
+        self.get_or(key, V::default).await
+    }
+
+    /// Lock the key like [`KvStore::get_mut()`] until the returned
+    /// [`AsyncLock`] is updated or dropped.
+    pub async fn get_mut<K, V>(&self, key: K) -> Result<AsyncLock<V>, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+    {
+        let lock = self
+            .run(move |column_family| column_family.get_mut(&key))
+            .await?;
+
+        Ok(AsyncLock::new(self.kvstore, lock))
+    }
+
+    pub async fn get_mut_or<K, V, F>(
+        &self,
+        key: K,
+        function: F,
+    ) -> Result<AsyncLock<V>, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+        F: FnOnce() -> V + Send + 'static,
+    {
+        let lock = self
+            .run(move |column_family| column_family.get_mut_or(&key, function))
+            .await?;
+
+        Ok(AsyncLock::new(self.kvstore, lock))
+    }
+
+    pub async fn get_mut_or_default<K, V>(&self, key: K) -> Result<AsyncLock<V>, KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + Default + DeserializeOwned + Serialize + Send + 'static,
+    {
+        self.get_mut_or(key, V::default).await
+    }
+
+    pub async fn apply<K, V, F>(&self, key: K, operation: F) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+        V: Debug + DeserializeOwned + Serialize + Send + 'static,
+        F: FnOnce(&mut Lock<V>) + Send + 'static,
+    {
+        self.run(move |column_family| column_family.apply(&key, operation))
+            .await
+    }
+
+    pub async fn delete<K>(&self, key: K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize + Send + 'static,
+    {
+        self.run(move |column_family| column_family.delete(&key))
+            .await
+    }
+
+    /// Async version of [`KvStore::transaction()`]. `function` runs on the
+    /// dedicated threads, so it may block.
+    pub async fn transaction<F, T>(&self, function: F) -> Result<T, KvStoreError>
+    where
+        F: FnMut(&Transaction) -> Result<T, KvStoreError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let kvstore = self.kvstore;
+
+        kvstore
+            .blocking_pool
+            .run(move || kvstore.transaction(function))
+            .await
+    }
+
+    /// Async version of [`KvStore::write_batch()`].
+    pub async fn write_batch<F>(&self, function: F) -> Result<(), KvStoreError>
+    where
+        F: FnOnce(&mut WriteBatch) -> Result<(), KvStoreError> + Send + 'static,
+    {
+        let kvstore = self.kvstore;
+
+        kvstore
+            .blocking_pool
+            .run(move || kvstore.write_batch(function))
+            .await
+    }
+}
+
+/// [`Lock`] returned by [`AsyncKvStore::get_mut()`]. The key stays locked
+/// until the value is updated with [`AsyncLock::update()`] or the lock is
+/// dropped, in which case the value is left unchanged. Dropping the lock
+/// rolls it back on the dedicated threads.
+pub struct AsyncLock<V>
+where
+    V: Debug + Serialize + DeserializeOwned + Send + 'static,
+{
+    kvstore: &'static KvStore,
+    lock: Option<Lock<'static, V>>,
+}
+
+impl<V> std::ops::Deref for AsyncLock<V>
+where
+    V: Debug + Serialize + DeserializeOwned + Send + 'static,
+{
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.lock.as_ref().unwrap()
+    }
+}
+
+impl<V> std::ops::DerefMut for AsyncLock<V>
+where
+    V: Debug + Serialize + DeserializeOwned + Send + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.lock.as_mut().unwrap()
+    }
+}
+
+impl<V> Drop for AsyncLock<V>
+where
+    V: Debug + Serialize + DeserializeOwned + Send + 'static,
+{
+    fn drop(&mut self) {
+        if let Some(lock) = self.lock.take() {
+            self.kvstore.blocking_pool.spawn(move || drop(lock));
+        }
+    }
+}
+
+impl<V> AsyncLock<V>
+where
+    V: Debug + Serialize + DeserializeOwned + Send + 'static,
+{
+    fn new(kvstore: &'static KvStore, lock: Lock<'static, V>) -> Self {
+        Self {
+            kvstore,
+            lock: Some(lock),
+        }
+    }
+
+    pub async fn update(mut self) -> Result<(), KvStoreError> {
+        let lock = self.lock.take().unwrap();
+
+        self.kvstore.blocking_pool.run(move || lock.update()).await
+    }
+
+    /// See [`Lock::update_if_changed()`].
+    pub async fn update_if_changed(mut self) -> Result<bool, KvStoreError> {
+        let lock = self.lock.take().unwrap();
+
+        self.kvstore
+            .blocking_pool
+            .run(move || lock.update_if_changed())
+            .await
+    }
+
+    /// See [`Lock::rollback()`].
+    pub async fn rollback(mut self) -> Result<(), KvStoreError> {
+        let lock = self.lock.take().unwrap();
+
+        self.kvstore
+            .blocking_pool
+            .run(move || lock.rollback())
+            .await
+    }
+}
@@ -0,0 +1,201 @@
+/// Storage of the serialized key-value pairs behind
+/// [`KvStore`](super::KvStore), used with
+/// [`KvStoreBuilder::build_with()`](super::KvStoreBuilder::build_with).
+/// [`KvStoreBuilder::build()`](super::KvStoreBuilder::build) opens RocksDB
+/// instead, and [`MemoryBackend`](super::MemoryBackend) keeps everything in
+/// memory, e.g. for tests.
+///
+/// The keys and values are opaque bytes. The keys of a column family must be
+/// iterated in the lexicographic order of the bytes.
+///
+/// The errors of the kind [`ErrorKind::TimedOut`] and [`ErrorKind::Busy`] are
+/// reported as [`KvStoreError::LockTimeout`](super::KvStoreError::LockTimeout)
+/// and as retryable errors respectively, like the ones of RocksDB.
+pub trait StorageBackend: Send + Sync {
+    fn has_column_family(&self, column_family: &str) -> bool;
+
+    fn create_column_family(&self, column_family: &str) -> Result<(), StorageError>;
+
+    /// Drop the column family and every key-value pair in it.
+    fn drop_column_family(&self, column_family: &str) -> Result<(), StorageError>;
+
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Begin a transaction, which locks the keys written or read for update
+    /// until it ends and is rolled back if it is dropped without commit.
+    fn transaction(&self) -> Box<dyn StorageTransaction + '_>;
+
+    /// Apply the operations atomically.
+    fn write(&self, operations: Vec<BatchOperation>) -> Result<(), StorageError>;
+
+    /// Take a consistent view of every column family.
+    fn snapshot(&self) -> Box<dyn StorageSnapshot + '_>;
+
+    /// Iterate over the column family as of now.
+    fn cursor(&self, column_family: &str) -> Box<dyn StorageCursor + '_>;
+}
+
+/// Transaction returned by [`StorageBackend::transaction()`]. The reads see
+/// the writes of the transaction.
+pub trait StorageTransaction: Send {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Get the value and lock the key until the transaction ends.
+    fn get_for_update(
+        &self,
+        column_family: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError>;
+
+    fn put(&self, column_family: &str, key: &[u8], value: &[u8]) -> Result<(), StorageError>;
+
+    fn delete(&self, column_family: &str, key: &[u8]) -> Result<(), StorageError>;
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError>;
+
+    /// Undo every write and release the locks. The transaction can still be
+    /// used afterwards.
+    fn rollback(&self) -> Result<(), StorageError>;
+
+    fn set_savepoint(&self);
+
+    /// Undo the writes since the last savepoint and remove the savepoint.
+    fn rollback_to_savepoint(&self) -> Result<(), StorageError>;
+}
+
+/// Snapshot returned by [`StorageBackend::snapshot()`].
+pub trait StorageSnapshot: Send + Sync {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Iterate over the column family as of the snapshot.
+    fn cursor(&self, column_family: &str) -> Box<dyn StorageCursor + '_>;
+}
+
+/// Cursor over the key-value pairs of a column family in the order of the
+/// keys, which is unpositioned until it is seeked. Moving it past either end
+/// makes [`StorageCursor::item()`] return `None`.
+pub trait StorageCursor: Send + Sync {
+    /// Position at the first key greater than or equal to `key`.
+    fn seek(&mut self, key: &[u8]);
+
+    /// Position at the last key less than or equal to `key`.
+    fn seek_for_prev(&mut self, key: &[u8]);
+
+    fn seek_to_first(&mut self);
+
+    fn seek_to_last(&mut self);
+
+    fn next(&mut self);
+
+    fn prev(&mut self);
+
+    /// The key-value pair at the position.
+    fn item(&self) -> Option<(&[u8], &[u8])>;
+
+    /// The error which has stopped the cursor, if any.
+    fn status(&self) -> Result<(), StorageError>;
+}
+
+/// Write of [`StorageBackend::write()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BatchOperation {
+    Put {
+        column_family: String,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Delete {
+        column_family: String,
+        key: Vec<u8>,
+    },
+}
+
+/// Kind of [`StorageError`], which are the kinds of the errors of RocksDB.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    NotFound,
+    Corruption,
+    NotSupported,
+    InvalidArgument,
+    IOError,
+    MergeInProgress,
+    Incomplete,
+    ShutdownInProgress,
+    TimedOut,
+    Aborted,
+    Busy,
+    Expired,
+    TryAgain,
+    CompactionTooLarge,
+    ColumnFamilyDropped,
+    Unknown,
+}
+
+#[cfg(feature = "rocksdb")]
+impl From<rocksdb::ErrorKind> for ErrorKind {
+    fn from(value: rocksdb::ErrorKind) -> Self {
+        match value {
+            rocksdb::ErrorKind::NotFound => Self::NotFound,
+            rocksdb::ErrorKind::Corruption => Self::Corruption,
+            rocksdb::ErrorKind::NotSupported => Self::NotSupported,
+            rocksdb::ErrorKind::InvalidArgument => Self::InvalidArgument,
+            rocksdb::ErrorKind::IOError => Self::IOError,
+            rocksdb::ErrorKind::MergeInProgress => Self::MergeInProgress,
+            rocksdb::ErrorKind::Incomplete => Self::Incomplete,
+            rocksdb::ErrorKind::ShutdownInProgress => Self::ShutdownInProgress,
+            rocksdb::ErrorKind::TimedOut => Self::TimedOut,
+            rocksdb::ErrorKind::Aborted => Self::Aborted,
+            rocksdb::ErrorKind::Busy => Self::Busy,
+            rocksdb::ErrorKind::Expired => Self::Expired,
+            rocksdb::ErrorKind::TryAgain => Self::TryAgain,
+            rocksdb::ErrorKind::CompactionTooLarge => Self::CompactionTooLarge,
+            rocksdb::ErrorKind::ColumnFamilyDropped => Self::ColumnFamilyDropped,
+            rocksdb::ErrorKind::Unknown => Self::Unknown,
+        }
+    }
+}
+
+/// Error of [`StorageBackend`], which is also the error of RocksDB in
+/// [`KvStoreError`](super::KvStoreError).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl StorageError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl AsRef<str> for StorageError {
+    fn as_ref(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+#[cfg(feature = "rocksdb")]
+impl From<rocksdb::Error> for StorageError {
+    fn from(value: rocksdb::Error) -> Self {
+        Self {
+            kind: value.kind().into(),
+            message: value.into_string(),
+        }
+    }
+}
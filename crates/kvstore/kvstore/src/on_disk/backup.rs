@@ -0,0 +1,198 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rocksdb::{
+    backup::{BackupEngineInfo, BackupEngineOptions, RestoreOptions},
+    Env, Options, DB,
+};
+
+use super::{database::Database, KvStore, KvStoreError};
+
+/// Incremental backups of [`KvStore`], which can be restored into a new path
+/// to open with [`KvStoreBuilder::build()`](super::KvStoreBuilder::build).
+///
+/// The backups can be created while the database is running with
+/// [`ConcurrencyMode::Optimistic`](super::ConcurrencyMode::Optimistic), or
+/// from a stopped database opened with
+/// [`KvStoreBuilder::build_read_only()`](super::KvStoreBuilder::build_read_only).
+/// TransactionDB supports no backups, so
+/// [`ConcurrencyMode::Pessimistic`](super::ConcurrencyMode::Pessimistic) fails
+/// with [`KvStoreError::Unsupported`]. See [`KvStore::create_checkpoint()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{BackupEngine, ConcurrencyMode, KvStore, KvStoreBuilder};
+///
+/// let directory = std::env::temp_dir().join("kvstore_backup_example");
+/// # let _ = std::fs::remove_dir_all(&directory);
+/// let database = KvStoreBuilder::default()
+///     .set_concurrency_mode(ConcurrencyMode::Optimistic)
+///     .build(directory.join("database"))
+///     .unwrap();
+/// database.put(&"height", &1_u64).unwrap();
+///
+/// let mut backup_engine = BackupEngine::open(directory.join("backup"))
+///     .unwrap()
+///     .set_max_backups(2);
+/// backup_engine.create_backup(&database).unwrap();
+/// database.put(&"height", &2_u64).unwrap();
+/// backup_engine.create_backup(&database).unwrap();
+///
+/// let backups = backup_engine.backups();
+/// backup_engine
+///     .restore_backup(backups[0].backup_id, directory.join("restored"))
+///     .unwrap();
+/// let restored = KvStore::open(directory.join("restored")).unwrap();
+/// assert_eq!(restored.get::<_, u64>(&"height").unwrap(), 1);
+/// ```
+pub struct BackupEngine {
+    engine: rocksdb::backup::BackupEngine,
+    max_backups: Option<usize>,
+}
+
+impl BackupEngine {
+    /// Open the backups in `path`, which is created if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, KvStoreError> {
+        let options = BackupEngineOptions::new(path).map_err(KvStoreError::OpenBackupEngine)?;
+        let env = Env::new().map_err(KvStoreError::OpenBackupEngine)?;
+        let engine = rocksdb::backup::BackupEngine::open(&options, &env)
+            .map_err(KvStoreError::OpenBackupEngine)?;
+
+        Ok(Self {
+            engine,
+            max_backups: None,
+        })
+    }
+
+    /// Keep only the latest `max_backups` backups, deleting the older ones
+    /// whenever a backup is created.
+    pub fn set_max_backups(mut self, max_backups: usize) -> Self {
+        self.max_backups = Some(max_backups.max(1));
+
+        self
+    }
+
+    /// Back up the current state of the database. Only the files changed
+    /// since the previous backup are copied.
+    pub fn create_backup(&mut self, kvstore: &KvStore) -> Result<(), KvStoreError> {
+        match kvstore.database.as_ref() {
+            Database::Optimistic(database) => self.engine.create_new_backup(database),
+            Database::ReadOnly(database) => self.engine.create_new_backup(database),
+            _others => return Err(KvStoreError::Unsupported("create_backup")),
+        }
+        .map_err(KvStoreError::CreateBackup)?;
+
+        if let Some(max_backups) = self.max_backups {
+            self.purge_old_backups(max_backups)?;
+        }
+
+        Ok(())
+    }
+
+    /// The backups in the order of creation.
+    pub fn backups(&self) -> Vec<BackupEngineInfo> {
+        self.engine.get_backup_info()
+    }
+
+    /// Delete every backup except the latest `num_backups_to_keep`.
+    pub fn purge_old_backups(&mut self, num_backups_to_keep: usize) -> Result<(), KvStoreError> {
+        self.engine
+            .purge_old_backups(num_backups_to_keep)
+            .map_err(KvStoreError::PurgeBackup)
+    }
+
+    /// Check that the files of the backup exist and have the expected sizes.
+    pub fn verify_backup(&self, backup_id: u32) -> Result<(), KvStoreError> {
+        self.engine
+            .verify_backup(backup_id)
+            .map_err(KvStoreError::VerifyBackup)
+    }
+
+    /// Restore the latest backup into `path`, which must not contain any
+    /// file.
+    pub fn restore_latest_backup(&mut self, path: impl AsRef<Path>) -> Result<(), KvStoreError> {
+        let path = restore_path(path.as_ref())?;
+
+        self.engine
+            .restore_from_latest_backup(path, path, &RestoreOptions::default())
+            .map_err(KvStoreError::RestoreBackup)
+    }
+
+    /// Restore the backup into `path`, which must not contain any file.
+    pub fn restore_backup(
+        &mut self,
+        backup_id: u32,
+        path: impl AsRef<Path>,
+    ) -> Result<(), KvStoreError> {
+        let path = restore_path(path.as_ref())?;
+
+        self.engine
+            .restore_from_backup(path, path, &RestoreOptions::default(), backup_id)
+            .map_err(KvStoreError::RestoreBackup)
+    }
+}
+
+/// Refuse to restore over an existing database.
+fn restore_path(path: &Path) -> Result<&Path, KvStoreError> {
+    let is_empty = match std::fs::read_dir(path) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(_) => true,
+    };
+
+    match is_empty {
+        true => Ok(path),
+        false => Err(KvStoreError::RestorePathNotEmpty(path.to_path_buf())),
+    }
+}
+
+/// Secondary instance following the database, which sees the same state
+/// including the unflushed writes, e.g. to read the statistics of
+/// TransactionDB.
+pub(super) struct Secondary {
+    pub(super) database: DB,
+    pub(super) column_families: Vec<String>,
+    // Dropped after the database is closed.
+    _path: SecondaryPath,
+}
+
+struct SecondaryPath(PathBuf);
+
+impl Drop for SecondaryPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+impl Secondary {
+    pub(super) fn open(kvstore: &KvStore) -> Result<Self, KvStoreError> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let primary_path = kvstore
+            .database
+            .path()
+            .ok_or(KvStoreError::Unsupported("secondary"))?;
+        let path = SecondaryPath(std::env::temp_dir().join(format!(
+            "kvstore_secondary_{}_{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        )));
+
+        let mut options = Options::default();
+        options.set_max_open_files(-1);
+        let names = DB::list_cf(&options, primary_path).map_err(KvStoreError::OpenSecondary)?;
+        let database = DB::open_cf_as_secondary(&options, primary_path, &path.0, &names)
+            .map_err(KvStoreError::OpenSecondary)?;
+        database
+            .try_catch_up_with_primary()
+            .map_err(KvStoreError::OpenSecondary)?;
+
+        Ok(Self {
+            database,
+            column_families: names,
+            _path: path,
+        })
+    }
+}
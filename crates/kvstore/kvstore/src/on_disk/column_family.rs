@@ -0,0 +1,911 @@
+use std::{
+    fmt::Debug,
+    ops::RangeBounds,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, MutexGuard,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "rocksdb")]
+use rocksdb::{compaction_filter::Decision, DBCompressionType, Options, SliceTransform};
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    backend::ErrorKind,
+    database::{Database, Handle},
+    iter::{all_bounds, prefix_bounds, range_bounds},
+    schema::{deserialize_value, is_current, register_schema, serialize_value, Schema},
+    watch::{PendingEvent, Target, Watchers},
+    Iter, KvStoreError, Lock, Watch,
+};
+use crate::data_type::{
+    deserialize, deserialize_key, serialize, serialize_key, Codec, KeyEncoding,
+};
+
+/// Options of a column family declared with
+/// [`KvStoreBuilder::column_family()`](super::KvStoreBuilder::column_family)
+/// or created with
+/// [`KvStore::create_column_family()`](super::KvStore::create_column_family).
+#[derive(Default)]
+pub struct ColumnFamilyOptions {
+    #[cfg(feature = "rocksdb")]
+    pub(super) options: Options,
+    pub(super) format: ValueFormat,
+}
+
+/// What is stored with the values of a column family besides the values
+/// themselves, declared with its [`ColumnFamilyOptions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct ValueFormat {
+    pub(super) expiring: bool,
+    pub(super) ttl: Option<Duration>,
+    pub(super) versioned: bool,
+}
+
+impl ColumnFamilyOptions {
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_compression_type
+    #[cfg(feature = "rocksdb")]
+    pub fn set_compression_type(mut self, compression_type: DBCompressionType) -> Self {
+        self.options.set_compression_type(compression_type);
+
+        self
+    }
+
+    /// Expire the values `ttl` after they are put unless they are put with
+    /// their own TTL. See [`ColumnFamilyOptions::enable_ttl()`].
+    pub fn set_ttl(mut self, ttl: Duration) -> Self {
+        self.format.ttl = Some(ttl);
+
+        self.enable_ttl()
+    }
+
+    /// Allow the values to be put with TTL by
+    /// [`KvStore::put_with_ttl()`](super::KvStore::put_with_ttl). Expired
+    /// values are not returned and are removed from the disk on compaction.
+    ///
+    /// The expiration time is stored with every value, so a column family with
+    /// TTL must be declared with TTL every time the database is opened. The
+    /// values put before TTL is enabled never expire.
+    pub fn enable_ttl(mut self) -> Self {
+        if !self.format.expiring {
+            #[cfg(feature = "rocksdb")]
+            self.options
+                .set_compaction_filter("ttl", |_level, _key, value| match is_expired(value) {
+                    true => Decision::Remove,
+                    false => Decision::Keep,
+                });
+            self.format.expiring = true;
+        }
+
+        self
+    }
+
+    /// Store a [`Version`] with every value for
+    /// [`KvStore::get_with_version()`](super::KvStore::get_with_version) and
+    /// [`KvStore::compare_and_swap()`](super::KvStore::compare_and_swap).
+    ///
+    /// Like TTL, a versioned column family must be declared versioned every
+    /// time the database is opened. The values put before versioning is
+    /// enabled have the same version until they are put again.
+    pub fn enable_versioning(mut self) -> Self {
+        self.format.versioned = true;
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.SliceTransform.html#method.create_fixed_prefix
+    #[cfg(feature = "rocksdb")]
+    pub fn set_fixed_prefix_extractor(mut self, prefix_length: usize) -> Self {
+        self.options
+            .set_prefix_extractor(SliceTransform::create_fixed_prefix(prefix_length));
+
+        self
+    }
+}
+
+/// Version of a value returned by
+/// [`KvStore::get_with_version()`](super::KvStore::get_with_version), which
+/// changes every time the value is put, even if it is put with the same value.
+/// See [`ColumnFamilyOptions::enable_versioning()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version(u64);
+
+impl Version {
+    /// The version of the values put before versioning is enabled.
+    pub(super) const UNVERSIONED: Self = Self(0);
+
+    /// A version greater than every version taken before, also by the
+    /// previous processes as long as the system clock does not go back.
+    pub(super) fn next() -> Self {
+        static LAST: AtomicU64 = AtomicU64::new(0);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let last = LAST
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                Some(now.max(last + 1))
+            })
+            .unwrap_or_default();
+
+        Self(now.max(last + 1))
+    }
+}
+
+/// The version as stored, for the tools reading the values without
+/// [`KvStore`](super::KvStore). See [`StoredValue`](super::StoredValue).
+impl From<Version> for u64 {
+    fn from(value: Version) -> Self {
+        value.0
+    }
+}
+
+impl From<u64> for Version {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+/// Column family of [`KvStore`](super::KvStore) returned by
+/// [`KvStore::column_family()`](super::KvStore::column_family). The operations
+/// are the same as the ones of [`KvStore`](super::KvStore), except that they
+/// are scoped to the column family.
+#[derive(Clone)]
+pub struct ColumnFamily<'db> {
+    pub(super) database: &'db Database,
+    pub(super) handle: Handle<'db>,
+    pub(super) format: ValueFormat,
+    pub(super) name: String,
+    pub(super) watchers: &'db Arc<Watchers>,
+    pub(super) codec: &'db Arc<dyn Codec>,
+    pub(super) key_encoding: KeyEncoding,
+}
+
+unsafe impl Send for ColumnFamily<'_> {}
+
+unsafe impl Sync for ColumnFamily<'_> {}
+
+impl<'db> ColumnFamily<'db> {
+    pub fn put<K, V>(&self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+        let value_vec = self.encode(self.serialize_value(value)?);
+
+        let transaction = self.database.transaction()?;
+
+        transaction
+            .put_cf(&self.handle, &key_vec, &value_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Put))?;
+        let _order = self.order(&key_vec);
+        transaction.commit().map_err(KvStoreError::CommitPut)?;
+        self.notify(&key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn put_with_ttl<K, V>(&self, key: &K, value: &V, ttl: Duration) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+        let value_vec = self.encode_with_ttl(self.serialize_value(value)?, ttl)?;
+
+        let transaction = self.database.transaction()?;
+
+        transaction
+            .put_cf(&self.handle, &key_vec, &value_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Put))?;
+        let _order = self.order(&key_vec);
+        transaction.commit().map_err(KvStoreError::CommitPut)?;
+        self.notify(&key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+
+        let value_slice = self
+            .database
+            .get_pinned_cf(&self.handle, key_vec)
+            .map_err(KvStoreError::Get)?;
+        let value_slice = value_slice
+            .as_deref()
+            .and_then(|value_slice| self.decode(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.deserialize_value(value_slice)?;
+
+        Ok(value)
+    }
+
+    pub fn get_with_version<K, V>(&self, key: &K) -> Result<(V, Version), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        if !self.format.versioned {
+            return Err(KvStoreError::VersioningDisabled);
+        }
+        let key_vec = self.serialize_key(key)?;
+
+        let value_slice = self
+            .database
+            .get_pinned_cf(&self.handle, key_vec)
+            .map_err(KvStoreError::Get)?;
+        let (value_slice, version) = value_slice
+            .as_deref()
+            .and_then(|value_slice| self.decode_with_version(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.deserialize_value(value_slice)?;
+
+        Ok((value, version))
+    }
+
+    pub fn compare_and_swap<K, V>(
+        &self,
+        key: &K,
+        expected: Option<Version>,
+        value: &V,
+    ) -> Result<bool, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        if !self.format.versioned {
+            return Err(KvStoreError::VersioningDisabled);
+        }
+        let key_vec = self.serialize_key(key)?;
+        let value_vec = self.encode(self.serialize_value(value)?);
+
+        loop {
+            let transaction = self.database.transaction()?;
+
+            let current_vec = transaction
+                .get_for_update_cf(&self.handle, &key_vec, true)
+                .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+            let version = current_vec
+                .as_deref()
+                .and_then(|value_slice| self.decode_with_version(value_slice))
+                .map(|(_, version)| version);
+            if version != expected {
+                return Ok(false);
+            }
+
+            transaction
+                .put_cf(&self.handle, &key_vec, &value_vec)
+                .map_err(KvStoreError::lock(KvStoreError::Put))?;
+            let order = self.order(&key_vec);
+            match transaction.commit() {
+                Ok(()) => {
+                    self.notify(&key_vec, Some(&value_vec));
+                    drop(order);
+                    break;
+                }
+                // Changed by another transaction after it was read, which is
+                // checked again.
+                Err(error) if matches!(error.kind(), ErrorKind::Busy | ErrorKind::TryAgain) => {}
+                Err(error) => return Err(KvStoreError::CommitPut(error)),
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn get_or<K, V, F>(&self, key: &K, function: F) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        match self.get(key) {
+            Err(KvStoreError::NoneType) => Ok(function()),
+            others => others,
+        }
+    }
+
+    pub fn get_or_default<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.get_or(key, V::default)
+    }
+
+    pub fn get_mut<K, V>(&self, key: &K) -> Result<Lock<'db, V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+
+        let transaction = self.database.transaction()?;
+
+        let value_vec = transaction
+            .get_for_update_cf(&self.handle, &key_vec, true)
+            .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+        let value_slice = value_vec
+            .as_deref()
+            .and_then(|value_slice| self.decode(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.deserialize_value(value_slice)?;
+        let expiration = value_vec
+            .as_deref()
+            .and_then(|value_slice| self.expiration(value_slice));
+        let locked_value = Lock::new(
+            transaction,
+            self.clone(),
+            key_vec,
+            self.serialize_value(&value)?,
+            value,
+        )
+        .with_expiration(expiration);
+
+        Ok(locked_value)
+    }
+
+    pub fn get_mut_or<K, V, F>(&self, key: &K, function: F) -> Result<Lock<'db, V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        let key_vec = self.serialize_key(key)?;
+
+        let transaction = self.database.transaction()?;
+
+        let value_vec = transaction
+            .get_for_update_cf(&self.handle, &key_vec, true)
+            .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+        let value_slice = value_vec
+            .as_deref()
+            .and_then(|value_slice| self.decode(value_slice));
+        match value_slice {
+            Some(value_slice) => {
+                let value: V = self.deserialize_value(value_slice)?;
+                let expiration = value_vec
+                    .as_deref()
+                    .and_then(|value_slice| self.expiration(value_slice));
+                let locked_value = Lock::new(
+                    transaction,
+                    self.clone(),
+                    key_vec,
+                    self.serialize_value(&value)?,
+                    value,
+                )
+                .with_expiration(expiration);
+
+                Ok(locked_value)
+            }
+            None => {
+                let value = function();
+                let value_vec = self.encode(self.serialize_value(&value)?);
+
+                transaction
+                    .put_cf(&self.handle, &key_vec, &value_vec)
+                    .map_err(KvStoreError::lock(KvStoreError::Put))?;
+
+                // After the `commit()`, other threads may access [FnOnce() -> V].
+                let order = self.order(&key_vec);
+                transaction.commit().map_err(KvStoreError::CommitPut)?;
+                self.notify(&key_vec, Some(&value_vec));
+                drop(order);
+
+                let transaction = self.database.transaction()?;
+
+                transaction
+                    .get_for_update_cf(&self.handle, &key_vec, true)
+                    .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+                let value_vec = self.strip_header(&value_vec).to_vec();
+                let locked_value = Lock::new(transaction, self.clone(), key_vec, value_vec, value);
+
+                Ok(locked_value)
+            }
+        }
+    }
+
+    pub fn get_mut_or_default<K, V>(&self, key: &K) -> Result<Lock<'db, V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.get_mut_or(key, V::default)
+    }
+
+    pub fn apply<K, V, F>(&self, key: &K, operation: F) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce(&mut Lock<V>),
+    {
+        let mut locked_value = self.get_mut(key)?;
+        operation(&mut locked_value);
+        locked_value.update()?;
+
+        Ok(())
+    }
+
+    pub fn delete<K>(&self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+
+        let transaction = self.database.transaction()?;
+
+        transaction
+            .delete_cf(&self.handle, &key_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Delete))?;
+        let _order = self.order(&key_vec);
+        transaction.commit().map_err(KvStoreError::CommitDelete)?;
+        self.notify(&key_vec, None);
+
+        Ok(())
+    }
+
+    pub fn iter<K, V>(&self) -> Iter<'db, K, V>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Iter::new(self.clone(), all_bounds())
+    }
+
+    pub fn iter_prefix<P, K, V>(&self, prefix: &P) -> Result<Iter<'db, K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(Iter::new(self.clone(), prefix_bounds(self, prefix)?))
+    }
+
+    pub fn iter_range<R, P, K, V>(&self, range: R) -> Result<Iter<'db, K, V>, KvStoreError>
+    where
+        R: RangeBounds<P>,
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(Iter::new(self.clone(), range_bounds(self, range)?))
+    }
+
+    /// Subscribe to the changes of `key`. See [`Watch`].
+    pub fn watch<P, K, V>(&self, key: &P) -> Result<Watch<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.serialize_key(key)?;
+        let receiver = self.watchers.subscribe(&self.name, Target::Key(key_vec));
+
+        Ok(Watch::new(
+            receiver,
+            self.watchers,
+            self.codec.clone(),
+            self.key_encoding,
+        ))
+    }
+
+    /// Subscribe to the changes of the keys starting with `prefix`. See
+    /// [`Watch`].
+    pub fn watch_prefix<P, K, V>(&self, prefix: &P) -> Result<Watch<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let prefix_vec = self.serialize_prefix(prefix)?;
+        let receiver = self
+            .watchers
+            .subscribe(&self.name, Target::Prefix(prefix_vec));
+
+        Ok(Watch::new(
+            receiver,
+            self.watchers,
+            self.codec.clone(),
+            self.key_encoding,
+        ))
+    }
+
+    /// Rewrite the values of the keys starting with `prefix` which are stored
+    /// with an older version of `V`, so that they are no longer migrated on
+    /// read. Returns the number of rewritten values. See [`Schema`].
+    pub fn migrate_prefix<P, V>(&self, prefix: &P) -> Result<usize, KvStoreError>
+    where
+        P: Debug + Serialize,
+        V: Schema,
+    {
+        let prefix_vec = self.serialize_prefix(prefix)?;
+
+        self.migrate::<V>(&prefix_vec, |_| true)
+    }
+
+    /// Rewrite the values of the keys of type `K` for which `filter` returns
+    /// true like [`ColumnFamily::migrate_prefix()`]. With
+    /// [`KeyEncoding::Ordered`] only the keys starting with `prefix` are read,
+    /// and with [`KeyEncoding::Codec`] every key of the column family is read
+    /// and the keys which are not of type `K` are skipped.
+    pub fn migrate_where<P, K, V>(
+        &self,
+        prefix: &P,
+        filter: impl Fn(&K) -> bool,
+    ) -> Result<usize, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Schema,
+    {
+        let prefix_vec = match self.key_encoding {
+            KeyEncoding::Codec => Vec::new(),
+            KeyEncoding::Ordered => serialize_key(prefix)?,
+        };
+
+        self.migrate::<V>(&prefix_vec, |key_slice| {
+            self.deserialize_key(key_slice)
+                .is_ok_and(|key: K| filter(&key))
+        })
+    }
+
+    fn migrate<V>(
+        &self,
+        prefix_vec: &[u8],
+        filter: impl Fn(&[u8]) -> bool,
+    ) -> Result<usize, KvStoreError>
+    where
+        V: Schema,
+    {
+        register_schema::<V>();
+
+        let mut stale_keys = Vec::new();
+        let mut iterator = self.database.raw_iterator_cf(&self.handle);
+        iterator.seek(prefix_vec);
+        while let Some((key_slice, value_slice)) = iterator.item() {
+            if !key_slice.starts_with(prefix_vec) {
+                break;
+            }
+            if self
+                .decode(value_slice)
+                .is_some_and(|value_slice| !is_current::<V>(value_slice))
+                && filter(key_slice)
+            {
+                stale_keys.push(key_slice.to_vec());
+            }
+            iterator.next();
+        }
+        iterator.status().map_err(KvStoreError::Iterate)?;
+
+        let mut migrated = 0;
+        for key_vec in stale_keys {
+            let transaction = self.database.transaction()?;
+
+            let value_vec = transaction
+                .get_for_update_cf(&self.handle, &key_vec, true)
+                .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+            // Skip the values deleted, expired or rewritten since.
+            let Some(value_slice) = value_vec
+                .as_deref()
+                .and_then(|value_slice| self.decode(value_slice))
+                .filter(|value_slice| !is_current::<V>(value_slice))
+            else {
+                continue;
+            };
+            let value: V = self.deserialize_value(value_slice)?;
+            let expiration = value_vec
+                .as_deref()
+                .and_then(|value_slice| self.expiration(value_slice));
+            let value_vec = self.encode_with_expiration(self.serialize_value(&value)?, expiration);
+
+            transaction
+                .put_cf(&self.handle, &key_vec, &value_vec)
+                .map_err(KvStoreError::lock(KvStoreError::Put))?;
+            transaction.commit().map_err(KvStoreError::CommitPut)?;
+            migrated += 1;
+        }
+
+        Ok(migrated)
+    }
+
+    pub(super) fn serialize_key<K>(&self, key: &K) -> Result<Vec<u8>, KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        encode_key(self.key_encoding, self.codec.as_ref(), key)
+    }
+
+    pub(super) fn deserialize_key<K>(&self, key_slice: &[u8]) -> Result<K, KvStoreError>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+    {
+        decode_key(self.key_encoding, self.codec.as_ref(), key_slice)
+    }
+
+    /// Serialize the prefix of the keys, which only matches the keys starting
+    /// with it with [`KeyEncoding::Ordered`].
+    pub(super) fn serialize_prefix<P>(&self, prefix: &P) -> Result<Vec<u8>, KvStoreError>
+    where
+        P: Debug + Serialize,
+    {
+        match self.key_encoding {
+            KeyEncoding::Codec => Err(KvStoreError::Unsupported("KeyEncoding::Codec")),
+            KeyEncoding::Ordered => Ok(serialize_key(prefix)?),
+        }
+    }
+
+    /// Serialize the value with the codec of the database unless the type
+    /// has its own.
+    pub(super) fn serialize_value<V>(&self, value: &V) -> Result<Vec<u8>, KvStoreError>
+    where
+        V: Debug + Serialize,
+    {
+        serialize_value(self.codec.as_ref(), value)
+    }
+
+    pub(super) fn deserialize_value<V>(&self, value_slice: &[u8]) -> Result<V, KvStoreError>
+    where
+        V: Debug + DeserializeOwned,
+    {
+        deserialize_value(self.codec.as_ref(), value_slice)
+    }
+
+    /// Keep the events of the key in the order of the commits while the
+    /// guard is held. See [`Watchers::order()`].
+    pub(super) fn order(&self, key_vec: &[u8]) -> Option<MutexGuard<'db, ()>> {
+        self.watchers.order(&self.name, key_vec)
+    }
+
+    /// Send the committed change to the subscriptions. `value_vec` is the
+    /// encoded value, or `None` if the key is deleted.
+    pub(super) fn notify(&self, key_vec: &[u8], value_vec: Option<&[u8]>) {
+        self.watchers.notify(
+            &self.name,
+            key_vec,
+            value_vec.map(|value_vec| self.strip_header(value_vec)),
+        );
+    }
+
+    /// Keep the uncommitted change to send with
+    /// [`Watchers::notify_pending()`].
+    pub(super) fn pend(
+        &self,
+        events: &mut Vec<PendingEvent>,
+        key_vec: &[u8],
+        value_vec: Option<&[u8]>,
+    ) {
+        self.watchers.pend(
+            events,
+            &self.name,
+            key_vec,
+            value_vec.map(|value_vec| self.strip_header(value_vec)),
+        );
+    }
+
+    /// Prepend the expiration time and the version to the value if the
+    /// column family has TTL and versioning.
+    pub(super) fn encode(&self, value_vec: Vec<u8>) -> Vec<u8> {
+        encode(self.format, value_vec)
+    }
+
+    /// Prepend the expiration time `ttl` from now to the value, or fail if
+    /// the column family does not have TTL.
+    pub(super) fn encode_with_ttl(
+        &self,
+        value_vec: Vec<u8>,
+        ttl: Duration,
+    ) -> Result<Vec<u8>, KvStoreError> {
+        match self.format.expiring {
+            true => Ok(encode(
+                ValueFormat {
+                    ttl: Some(ttl),
+                    ..self.format
+                },
+                value_vec,
+            )),
+            false => Err(KvStoreError::TtlDisabled),
+        }
+    }
+
+    /// Prepend `expiration` to the value instead of the expiration time of
+    /// the column family if it is not `None`.
+    pub(super) fn encode_with_expiration(
+        &self,
+        value_vec: Vec<u8>,
+        expiration: Option<u64>,
+    ) -> Vec<u8> {
+        match (self.format.expiring, expiration) {
+            (true, Some(expiration)) => prepend_expiration(
+                expiration,
+                prepend_new_version(self.format.versioned, value_vec),
+            ),
+            _others => self.encode(value_vec),
+        }
+    }
+
+    /// The expiration time of the value if the column family has TTL.
+    pub(super) fn expiration(&self, value_slice: &[u8]) -> Option<u64> {
+        match self.format.expiring {
+            true => expiration(value_slice),
+            false => None,
+        }
+    }
+
+    /// Strip the expiration time and the version from the value, or return
+    /// `None` if the value has expired.
+    pub(super) fn decode<'a>(&self, value_slice: &'a [u8]) -> Option<&'a [u8]> {
+        self.decode_with_version(value_slice)
+            .map(|(value_slice, _)| value_slice)
+    }
+
+    /// Like [`ColumnFamily::decode()`], but also return the version of the
+    /// value.
+    fn decode_with_version<'a>(&self, value_slice: &'a [u8]) -> Option<(&'a [u8], Version)> {
+        let value_slice = match self.format.expiring {
+            true => match split_expiration(value_slice) {
+                Some((expiration, value_slice)) => (expiration > now()).then_some(value_slice)?,
+                None => value_slice,
+            },
+            false => value_slice,
+        };
+
+        Some(self.split_version(value_slice))
+    }
+
+    /// Strip the expiration time and the version from the value whether it
+    /// has expired or not.
+    fn strip_header<'a>(&self, value_slice: &'a [u8]) -> &'a [u8] {
+        let value_slice = match self.format.expiring {
+            true => {
+                split_expiration(value_slice).map_or(value_slice, |(_, value_slice)| value_slice)
+            }
+            false => value_slice,
+        };
+
+        self.split_version(value_slice).0
+    }
+
+    fn split_version<'a>(&self, value_slice: &'a [u8]) -> (&'a [u8], Version) {
+        match self.format.versioned {
+            true => split_version(value_slice).map_or(
+                (value_slice, Version::UNVERSIONED),
+                |(version, value_slice)| (value_slice, version),
+            ),
+            false => (value_slice, Version::UNVERSIONED),
+        }
+    }
+}
+
+/// Serialize the key with `key_encoding`.
+pub(super) fn encode_key<K>(
+    key_encoding: KeyEncoding,
+    codec: &dyn Codec,
+    key: &K,
+) -> Result<Vec<u8>, KvStoreError>
+where
+    K: Debug + Serialize,
+{
+    match key_encoding {
+        KeyEncoding::Codec => Ok(serialize(codec, key)?),
+        KeyEncoding::Ordered => Ok(serialize_key(key)?),
+    }
+}
+
+pub(super) fn decode_key<K>(
+    key_encoding: KeyEncoding,
+    codec: &dyn Codec,
+    key_slice: &[u8],
+) -> Result<K, KvStoreError>
+where
+    K: Debug + DeserializeOwned + Serialize,
+{
+    match key_encoding {
+        KeyEncoding::Codec => Ok(deserialize(codec, key_slice)?),
+        KeyEncoding::Ordered => Ok(deserialize_key(key_slice)?),
+    }
+}
+
+/// Prepend the expiration time and a new version to the value as declared by
+/// `format`. The value never expires if the TTL is `None`.
+pub(super) fn encode(format: ValueFormat, value_vec: Vec<u8>) -> Vec<u8> {
+    let value_vec = prepend_new_version(format.versioned, value_vec);
+
+    match format.expiring {
+        true => {
+            let expiration = format
+                .ttl
+                .and_then(|ttl| SystemTime::now().checked_add(ttl))
+                .map(|expiration| {
+                    // Rounded up so that the value lives at least `ttl`.
+                    let expiration = expiration.duration_since(UNIX_EPOCH).unwrap_or_default();
+                    expiration.as_secs() + u64::from(expiration.subsec_nanos() > 0)
+                })
+                .unwrap_or(u64::MAX);
+
+            prepend_expiration(expiration, value_vec)
+        }
+        false => value_vec,
+    }
+}
+
+/// Prepend a new version to the value if `versioned`.
+fn prepend_new_version(versioned: bool, value_vec: Vec<u8>) -> Vec<u8> {
+    match versioned {
+        true => prepend_version(Version::next(), value_vec),
+        false => value_vec,
+    }
+}
+
+pub(super) fn prepend_version(version: Version, value_vec: Vec<u8>) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(VERSION_LENGTH + value_vec.len());
+    encoded.extend_from_slice(&VERSIONED);
+    encoded.extend_from_slice(&version.0.to_be_bytes());
+    encoded.extend_from_slice(&value_vec);
+    encoded
+}
+
+pub(super) fn prepend_expiration(expiration: u64, value_vec: Vec<u8>) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(EXPIRATION_LENGTH + value_vec.len());
+    encoded.extend_from_slice(&EXPIRING);
+    encoded.extend_from_slice(&expiration.to_be_bytes());
+    encoded.extend_from_slice(&value_vec);
+    encoded
+}
+
+/// Tag of the values of the column families with TTL, followed by the
+/// expiration time in seconds since the Unix epoch in big-endian. The values
+/// without the tag are put before TTL is enabled and never expire. Neither
+/// JSON nor CBOR values start with `0xfe`, unlike bincode values which can
+/// start with the tag in theory.
+const EXPIRING: [u8; 4] = [0xfe, b'k', b'v', b't'];
+
+const EXPIRATION_LENGTH: usize = EXPIRING.len() + std::mem::size_of::<u64>();
+
+/// Tag of the values of the versioned column families, followed by the
+/// version in big-endian and put after the expiration time if the column
+/// family also has TTL. Neither JSON nor CBOR values start with `0xfd`.
+const VERSIONED: [u8; 4] = [0xfd, b'k', b'v', b'n'];
+
+const VERSION_LENGTH: usize = VERSIONED.len() + std::mem::size_of::<u64>();
+
+/// Split the value into the version and the value if it has one.
+pub(super) fn split_version(value_slice: &[u8]) -> Option<(Version, &[u8])> {
+    let value_slice = value_slice.strip_prefix(&VERSIONED)?;
+    let (version, value_slice) = value_slice.split_first_chunk::<8>()?;
+
+    Some((Version(u64::from_be_bytes(*version)), value_slice))
+}
+
+/// Split the value into the expiration time and the value if it has one.
+pub(super) fn split_expiration(value_slice: &[u8]) -> Option<(u64, &[u8])> {
+    let value_slice = value_slice.strip_prefix(&EXPIRING)?;
+    let (expiration, value_slice) = value_slice.split_first_chunk::<8>()?;
+
+    Some((u64::from_be_bytes(*expiration), value_slice))
+}
+
+fn expiration(value_slice: &[u8]) -> Option<u64> {
+    split_expiration(value_slice).map(|(expiration, _)| expiration)
+}
+
+#[cfg(feature = "rocksdb")]
+fn is_expired(value_slice: &[u8]) -> bool {
+    expiration(value_slice).is_some_and(|expiration| expiration <= now())
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,564 @@
+// Without RocksDB, the arguments only used by its variants are unused.
+#![cfg_attr(not(feature = "rocksdb"), allow(unused_variables))]
+
+#[cfg(not(feature = "rocksdb"))]
+use std::{convert::Infallible, marker::PhantomData};
+#[cfg(feature = "rocksdb")]
+use std::{path::Path, sync::Arc};
+
+#[cfg(feature = "rocksdb")]
+use rocksdb::{
+    BoundColumnFamily, DBPinnableSlice, DBRawIteratorWithThreadMode, DBWithThreadMode,
+    MultiThreaded, OptimisticTransactionDB, ReadOptions, SnapshotWithThreadMode, TransactionDB,
+    TransactionOptions, WriteBatchWithTransaction, WriteOptions,
+};
+
+use super::{
+    backend::{
+        BatchOperation, StorageBackend, StorageCursor, StorageError, StorageSnapshot,
+        StorageTransaction,
+    },
+    ColumnFamilyOptions, KvStoreError,
+};
+
+/// How [`KvStore`](super::KvStore) handles the conflicts between
+/// transactions, set with
+/// [`KvStoreBuilder::set_concurrency_mode()`](super::KvStoreBuilder::set_concurrency_mode).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConcurrencyMode {
+    /// Lock the keys written or read for update until the commit, so that
+    /// the conflicting transactions wait for each other.
+    #[default]
+    Pessimistic,
+    /// Never lock the keys. The commit fails with a retryable error instead
+    /// if a key written or read for update has been changed since, which
+    /// suits the workloads with few conflicts.
+    Optimistic,
+}
+
+/// Run `$body` on the first field of whichever RocksDB variant, or
+/// `$backend` on the backend. The variants are listed for the enums without
+/// `ReadOnly`, and only exist with the `rocksdb` feature.
+macro_rules! dispatch {
+    ($value:expr, $inner:ident => $body:expr, $backend:ident => $backend_body:expr) => {
+        dispatch!(
+            $value,
+            [Pessimistic, Optimistic, ReadOnly],
+            $inner => $body,
+            $backend => $backend_body
+        )
+    };
+    (
+        $value:expr,
+        [$($variant:ident),*],
+        $inner:ident => $body:expr,
+        $backend:ident => $backend_body:expr
+    ) => {
+        match $value {
+            $(
+                #[cfg(feature = "rocksdb")]
+                Self::$variant($inner, ..) => $body,
+            )*
+            Self::Backend($backend) => $backend_body,
+        }
+    };
+}
+
+/// [`dispatch!`] over the transactions, which the read-only databases have
+/// not.
+macro_rules! dispatch_transaction {
+    ($value:expr, $($arms:tt)*) => {
+        dispatch!($value, [Pessimistic, Optimistic], $($arms)*)
+    };
+}
+
+/// The database of the [`ConcurrencyMode`], the read-only or secondary
+/// instance opened with
+/// [`KvStoreBuilder::build_read_only()`](super::KvStoreBuilder::build_read_only)
+/// or [`KvStoreBuilder::build_secondary()`](super::KvStoreBuilder::build_secondary),
+/// or the [`StorageBackend`] set with
+/// [`KvStoreBuilder::build_with()`](super::KvStoreBuilder::build_with).
+pub(super) enum Database {
+    #[cfg(feature = "rocksdb")]
+    Pessimistic(TransactionDB<MultiThreaded>, PessimisticOptions),
+    #[cfg(feature = "rocksdb")]
+    Optimistic(OptimisticTransactionDB<MultiThreaded>),
+    #[cfg(feature = "rocksdb")]
+    ReadOnly(DBWithThreadMode<MultiThreaded>),
+    Backend(Box<dyn StorageBackend>),
+}
+
+/// Options of the transactions of [`ConcurrencyMode::Pessimistic`].
+#[cfg(feature = "rocksdb")]
+#[derive(Clone, Copy, Default)]
+pub(super) struct PessimisticOptions {
+    pub(super) deadlock_detect: bool,
+}
+
+/// Column family of the [`Database`] it is returned from.
+#[derive(Clone)]
+pub(super) enum Handle<'db> {
+    #[cfg(feature = "rocksdb")]
+    RocksDb(Arc<BoundColumnFamily<'db>>),
+    Backend(String),
+    /// Uses `'db` without RocksDB.
+    #[cfg(not(feature = "rocksdb"))]
+    #[allow(dead_code)]
+    Never(Infallible, PhantomData<&'db ()>),
+}
+
+#[cfg(feature = "rocksdb")]
+impl<'db> Handle<'db> {
+    fn rocksdb(&self) -> &Arc<BoundColumnFamily<'db>> {
+        match self {
+            Self::RocksDb(handle) => handle,
+            Self::Backend(_) => unreachable!("column family of the backend used with RocksDB"),
+        }
+    }
+}
+
+impl Handle<'_> {
+    fn name(&self) -> &str {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::RocksDb(_) => unreachable!("column family of RocksDB used with the backend"),
+            Self::Backend(name) => name,
+            #[cfg(not(feature = "rocksdb"))]
+            Self::Never(never, _) => match *never {},
+        }
+    }
+}
+
+/// Value read from the [`Database`], pinned in RocksDB without copying.
+pub(super) enum Pinned<'db> {
+    #[cfg(feature = "rocksdb")]
+    RocksDb(DBPinnableSlice<'db>),
+    Backend(Vec<u8>),
+    /// Uses `'db` without RocksDB.
+    #[cfg(not(feature = "rocksdb"))]
+    #[allow(dead_code)]
+    Never(Infallible, PhantomData<&'db ()>),
+}
+
+impl std::ops::Deref for Pinned<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::RocksDb(value_slice) => value_slice,
+            Self::Backend(value_vec) => value_vec,
+            #[cfg(not(feature = "rocksdb"))]
+            Self::Never(never, _) => match *never {},
+        }
+    }
+}
+
+impl Database {
+    /// The path of RocksDB, or `None` for the backend.
+    #[cfg(feature = "rocksdb")]
+    pub(super) fn path(&self) -> Option<&Path> {
+        dispatch!(self, database => Some(database.path()), _backend => None)
+    }
+
+    pub(super) fn cf_handle(&self, name: &str) -> Option<Handle> {
+        dispatch!(
+            self,
+            database => database.cf_handle(name).map(Handle::RocksDb),
+            backend => backend
+                .has_column_family(name)
+                .then(|| Handle::Backend(name.to_owned()))
+        )
+    }
+
+    pub(super) fn create_cf(
+        &self,
+        name: &str,
+        options: &ColumnFamilyOptions,
+    ) -> Result<(), StorageError> {
+        dispatch!(
+            self,
+            database => Ok(database.create_cf(name, &options.options)?),
+            backend => backend.create_column_family(name)
+        )
+    }
+
+    pub(super) fn drop_cf(&self, name: &str) -> Result<(), StorageError> {
+        dispatch!(
+            self,
+            database => Ok(database.drop_cf(name)?),
+            backend => backend.drop_column_family(name)
+        )
+    }
+
+    pub(super) fn is_read_only(&self) -> bool {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::ReadOnly(_) => true,
+            _others => false,
+        }
+    }
+
+    /// Begin a transaction, or fail with [`KvStoreError::ReadOnly`].
+    pub(super) fn transaction(&self) -> Result<RawTransaction, KvStoreError> {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::Pessimistic(database, options) => {
+                let mut transaction_options = TransactionOptions::default();
+                transaction_options.set_deadlock_detect(options.deadlock_detect);
+
+                Ok(RawTransaction::Pessimistic(database.transaction_opt(
+                    &WriteOptions::default(),
+                    &transaction_options,
+                )))
+            }
+            #[cfg(feature = "rocksdb")]
+            Self::Optimistic(database) => Ok(RawTransaction::Optimistic(database.transaction())),
+            #[cfg(feature = "rocksdb")]
+            Self::ReadOnly(_) => Err(KvStoreError::ReadOnly),
+            Self::Backend(backend) => Ok(RawTransaction::Backend(backend.transaction())),
+        }
+    }
+
+    pub(super) fn get_pinned_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<Pinned>, StorageError> {
+        dispatch!(
+            self,
+            database => Ok(database
+                .get_pinned_cf(handle.rocksdb(), key)?
+                .map(Pinned::RocksDb)),
+            backend => Ok(backend
+                .get(handle.name(), key.as_ref())?
+                .map(Pinned::Backend))
+        )
+    }
+
+    pub(super) fn snapshot(&self) -> RawSnapshot {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::Pessimistic(database, _) => RawSnapshot::Pessimistic(database.snapshot()),
+            #[cfg(feature = "rocksdb")]
+            Self::Optimistic(database) => RawSnapshot::Optimistic(database.snapshot()),
+            #[cfg(feature = "rocksdb")]
+            Self::ReadOnly(database) => RawSnapshot::ReadOnly(database.snapshot()),
+            Self::Backend(backend) => RawSnapshot::Backend(backend.snapshot()),
+        }
+    }
+
+    pub(super) fn raw_iterator_cf(&self, handle: &Handle) -> RawIterator {
+        match self {
+            Self::Backend(backend) => RawIterator::Backend(backend.cursor(handle.name())),
+            #[cfg(feature = "rocksdb")]
+            _others => self.raw_iterator_cf_opt(handle, read_options()),
+        }
+    }
+
+    #[cfg(feature = "rocksdb")]
+    fn raw_iterator_cf_opt(&self, handle: &Handle, read_options: ReadOptions) -> RawIterator {
+        match self {
+            Self::Pessimistic(database, _) => RawIterator::Pessimistic(
+                database.raw_iterator_cf_opt(handle.rocksdb(), read_options),
+            ),
+            Self::Optimistic(database) => RawIterator::Optimistic(
+                database.raw_iterator_cf_opt(handle.rocksdb(), read_options),
+            ),
+            Self::ReadOnly(database) => {
+                RawIterator::ReadOnly(database.raw_iterator_cf_opt(handle.rocksdb(), read_options))
+            }
+            Self::Backend(backend) => RawIterator::Backend(backend.cursor(handle.name())),
+        }
+    }
+
+    /// Begin a write batch, or fail with [`KvStoreError::ReadOnly`].
+    pub(super) fn write_batch(&self) -> Result<RawWriteBatch, KvStoreError> {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::Pessimistic(..) | Self::Optimistic(_) => {
+                Ok(RawWriteBatch::RocksDb(WriteBatchWithTransaction::default()))
+            }
+            #[cfg(feature = "rocksdb")]
+            Self::ReadOnly(_) => Err(KvStoreError::ReadOnly),
+            Self::Backend(_) => Ok(RawWriteBatch::Backend(Vec::new())),
+        }
+    }
+
+    pub(super) fn write(&self, batch: RawWriteBatch) -> Result<(), StorageError> {
+        match (self, batch) {
+            #[cfg(feature = "rocksdb")]
+            (Self::Pessimistic(database, _), RawWriteBatch::RocksDb(batch)) => {
+                Ok(database.write(batch)?)
+            }
+            #[cfg(feature = "rocksdb")]
+            (Self::Optimistic(database), RawWriteBatch::RocksDb(batch)) => {
+                Ok(database.write(batch)?)
+            }
+            (Self::Backend(backend), RawWriteBatch::Backend(operations)) => {
+                backend.write(operations)
+            }
+            #[cfg(feature = "rocksdb")]
+            _others => unreachable!("write batch of another database"),
+        }
+    }
+}
+
+pub(super) enum RawTransaction<'db> {
+    #[cfg(feature = "rocksdb")]
+    Pessimistic(rocksdb::Transaction<'db, TransactionDB<MultiThreaded>>),
+    #[cfg(feature = "rocksdb")]
+    Optimistic(rocksdb::Transaction<'db, OptimisticTransactionDB<MultiThreaded>>),
+    Backend(Box<dyn StorageTransaction + 'db>),
+}
+
+impl RawTransaction<'_> {
+    pub(super) fn put_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) -> Result<(), StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction.put_cf(handle.rocksdb(), key, value)?),
+            backend => backend.put(handle.name(), key.as_ref(), value.as_ref())
+        )
+    }
+
+    pub(super) fn delete_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+    ) -> Result<(), StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction.delete_cf(handle.rocksdb(), key)?),
+            backend => backend.delete(handle.name(), key.as_ref())
+        )
+    }
+
+    pub(super) fn get_pinned_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<Pinned>, StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction
+                .get_pinned_cf(handle.rocksdb(), key)?
+                .map(Pinned::RocksDb)),
+            backend => Ok(backend
+                .get(handle.name(), key.as_ref())?
+                .map(Pinned::Backend))
+        )
+    }
+
+    pub(super) fn get_for_update_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+        exclusive: bool,
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction.get_for_update_cf(handle.rocksdb(), key, exclusive)?),
+            backend => backend.get_for_update(handle.name(), key.as_ref())
+        )
+    }
+
+    pub(super) fn get_pinned_for_update_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+        exclusive: bool,
+    ) -> Result<Option<Pinned>, StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction
+                .get_pinned_for_update_cf(handle.rocksdb(), key, exclusive)?
+                .map(Pinned::RocksDb)),
+            backend => Ok(backend
+                .get_for_update(handle.name(), key.as_ref())?
+                .map(Pinned::Backend))
+        )
+    }
+
+    pub(super) fn commit(self) -> Result<(), StorageError> {
+        dispatch_transaction!(self, transaction => Ok(transaction.commit()?), backend => backend.commit())
+    }
+
+    pub(super) fn rollback(&self) -> Result<(), StorageError> {
+        dispatch_transaction!(self, transaction => Ok(transaction.rollback()?), backend => backend.rollback())
+    }
+
+    pub(super) fn set_savepoint(&self) {
+        dispatch_transaction!(self, transaction => transaction.set_savepoint(), backend => backend.set_savepoint())
+    }
+
+    pub(super) fn rollback_to_savepoint(&self) -> Result<(), StorageError> {
+        dispatch_transaction!(
+            self,
+            transaction => Ok(transaction.rollback_to_savepoint()?),
+            backend => backend.rollback_to_savepoint()
+        )
+    }
+}
+
+pub(super) enum RawSnapshot<'db> {
+    #[cfg(feature = "rocksdb")]
+    Pessimistic(SnapshotWithThreadMode<'db, TransactionDB<MultiThreaded>>),
+    #[cfg(feature = "rocksdb")]
+    Optimistic(SnapshotWithThreadMode<'db, OptimisticTransactionDB<MultiThreaded>>),
+    #[cfg(feature = "rocksdb")]
+    ReadOnly(SnapshotWithThreadMode<'db, DBWithThreadMode<MultiThreaded>>),
+    Backend(Box<dyn StorageSnapshot + 'db>),
+}
+
+impl RawSnapshot<'_> {
+    pub(super) fn get_pinned_cf(
+        &self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+    ) -> Result<Option<Pinned>, StorageError> {
+        dispatch!(
+            self,
+            snapshot => Ok(snapshot
+                .get_pinned_cf(handle.rocksdb(), key)?
+                .map(Pinned::RocksDb)),
+            backend => Ok(backend
+                .get(handle.name(), key.as_ref())?
+                .map(Pinned::Backend))
+        )
+    }
+
+    /// Iterate over the state of `database` at the snapshot.
+    pub(super) fn raw_iterator_cf<'s>(
+        &'s self,
+        database: &'s Database,
+        handle: &Handle,
+    ) -> RawIterator<'s> {
+        #[cfg(feature = "rocksdb")]
+        let mut read_options = read_options();
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::Pessimistic(snapshot) => read_options.set_snapshot(snapshot),
+            #[cfg(feature = "rocksdb")]
+            Self::Optimistic(snapshot) => read_options.set_snapshot(snapshot),
+            #[cfg(feature = "rocksdb")]
+            Self::ReadOnly(snapshot) => read_options.set_snapshot(snapshot),
+            Self::Backend(backend) => {
+                #[allow(clippy::needless_return)]
+                return RawIterator::Backend(backend.cursor(handle.name()));
+            }
+        }
+
+        #[cfg(feature = "rocksdb")]
+        database.raw_iterator_cf_opt(handle, read_options)
+    }
+}
+
+pub(super) enum RawIterator<'db> {
+    #[cfg(feature = "rocksdb")]
+    Pessimistic(DBRawIteratorWithThreadMode<'db, TransactionDB<MultiThreaded>>),
+    #[cfg(feature = "rocksdb")]
+    Optimistic(DBRawIteratorWithThreadMode<'db, OptimisticTransactionDB<MultiThreaded>>),
+    #[cfg(feature = "rocksdb")]
+    ReadOnly(DBRawIteratorWithThreadMode<'db, DBWithThreadMode<MultiThreaded>>),
+    Backend(Box<dyn StorageCursor + 'db>),
+}
+
+impl RawIterator<'_> {
+    pub(super) fn seek(&mut self, key: impl AsRef<[u8]>) {
+        dispatch!(self, iterator => iterator.seek(key), backend => backend.seek(key.as_ref()))
+    }
+
+    pub(super) fn seek_for_prev(&mut self, key: impl AsRef<[u8]>) {
+        dispatch!(
+            self,
+            iterator => iterator.seek_for_prev(key),
+            backend => backend.seek_for_prev(key.as_ref())
+        )
+    }
+
+    pub(super) fn seek_to_first(&mut self) {
+        dispatch!(self, iterator => iterator.seek_to_first(), backend => backend.seek_to_first())
+    }
+
+    pub(super) fn seek_to_last(&mut self) {
+        dispatch!(self, iterator => iterator.seek_to_last(), backend => backend.seek_to_last())
+    }
+
+    pub(super) fn next(&mut self) {
+        dispatch!(self, iterator => iterator.next(), backend => backend.next())
+    }
+
+    pub(super) fn prev(&mut self) {
+        dispatch!(self, iterator => iterator.prev(), backend => backend.prev())
+    }
+
+    pub(super) fn item(&self) -> Option<(&[u8], &[u8])> {
+        dispatch!(self, iterator => iterator.item(), backend => backend.item())
+    }
+
+    pub(super) fn status(&self) -> Result<(), StorageError> {
+        dispatch!(self, iterator => Ok(iterator.status()?), backend => backend.status())
+    }
+}
+
+pub(super) enum RawWriteBatch {
+    #[cfg(feature = "rocksdb")]
+    RocksDb(WriteBatchWithTransaction<true>),
+    Backend(Vec<BatchOperation>),
+}
+
+impl RawWriteBatch {
+    pub(super) fn put_cf(
+        &mut self,
+        handle: &Handle,
+        key: impl AsRef<[u8]>,
+        value: impl AsRef<[u8]>,
+    ) {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::RocksDb(batch) => batch.put_cf(handle.rocksdb(), key, value),
+            Self::Backend(operations) => operations.push(BatchOperation::Put {
+                column_family: handle.name().to_owned(),
+                key: key.as_ref().to_vec(),
+                value: value.as_ref().to_vec(),
+            }),
+        }
+    }
+
+    pub(super) fn delete_cf(&mut self, handle: &Handle, key: impl AsRef<[u8]>) {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::RocksDb(batch) => batch.delete_cf(handle.rocksdb(), key),
+            Self::Backend(operations) => operations.push(BatchOperation::Delete {
+                column_family: handle.name().to_owned(),
+                key: key.as_ref().to_vec(),
+            }),
+        }
+    }
+
+    pub(super) fn len(&self) -> usize {
+        match self {
+            #[cfg(feature = "rocksdb")]
+            Self::RocksDb(batch) => batch.len(),
+            Self::Backend(operations) => operations.len(),
+        }
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Iterate in the order of the keys regardless of the prefix extractor.
+#[cfg(feature = "rocksdb")]
+fn read_options() -> ReadOptions {
+    let mut read_options = ReadOptions::default();
+    read_options.set_total_order_seek(true);
+
+    read_options
+}
@@ -0,0 +1,189 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    column_family::{
+        prepend_expiration, prepend_version, split_expiration, split_version, ValueFormat,
+    },
+    schema::{prepend_schema_version, split_schema_version},
+    ColumnFamilyOptions, KvStore, KvStoreError, Version,
+};
+use crate::data_type::{deserialize, serialize, Json, KeyEncoding};
+
+/// How the keys and the values of a database are encoded, stored as JSON
+/// under [`StoredFormat::KEY`] in the column family
+/// [`StoredFormat::COLUMN_FAMILY`] when the database is first opened for
+/// writing, so that the tools reading the database without the types such as
+/// `kvstore-cli` decode it like [`KvStore`]. The database is not opened for
+/// writing with another codec or key encoding, see
+/// [`KvStoreError::FormatMismatch`]. The models with their own codec are not
+/// described.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct StoredFormat {
+    /// The [`Codec::name()`](crate::Codec::name) of the codec of the database.
+    pub codec: String,
+    pub key_encoding: KeyEncoding,
+    pub column_families: BTreeMap<String, ColumnFamilyFormat>,
+}
+
+impl StoredFormat {
+    pub const COLUMN_FAMILY: &'static str = "__kvstore";
+
+    pub const KEY: &'static [u8] = b"format";
+
+    pub fn to_vec(&self) -> Result<Vec<u8>, KvStoreError> {
+        Ok(serialize(&Json, self)?)
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<Self, KvStoreError> {
+        Ok(deserialize(&Json, slice)?)
+    }
+}
+
+/// What is stored with the values of a column family besides the values
+/// themselves, declared with [`ColumnFamilyOptions`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ColumnFamilyFormat {
+    /// See [`ColumnFamilyOptions::enable_ttl()`].
+    pub expiring: bool,
+    /// See [`ColumnFamilyOptions::enable_versioning()`].
+    pub versioned: bool,
+}
+
+impl From<ValueFormat> for ColumnFamilyFormat {
+    fn from(value: ValueFormat) -> Self {
+        Self {
+            expiring: value.expiring,
+            versioned: value.versioned,
+        }
+    }
+}
+
+impl ColumnFamilyFormat {
+    /// Split the stored value into its parts. The values put before TTL or
+    /// versioning is enabled have no expiration time or version.
+    pub fn decode(self, value_slice: &[u8]) -> StoredValue<'_> {
+        let (expiration, value_slice) =
+            match split_expiration(value_slice).filter(|_| self.expiring) {
+                Some((expiration, value_slice)) => (Some(expiration), value_slice),
+                None => (None, value_slice),
+            };
+        let (version, value_slice) = match split_version(value_slice).filter(|_| self.versioned) {
+            Some((version, value_slice)) => (Some(version), value_slice),
+            None => (None, value_slice),
+        };
+        let (schema_version, payload) = match split_schema_version(value_slice) {
+            Some((schema_version, payload)) => (Some(schema_version), payload),
+            None => (None, value_slice),
+        };
+
+        StoredValue {
+            expiration,
+            version,
+            schema_version,
+            payload,
+        }
+    }
+
+    /// Join the parts of the value as [`KvStore`] stores them. Without an
+    /// expiration time or a version, the value never expires and takes a new
+    /// version. The parts which the column family does not store are dropped.
+    pub fn encode(self, value: &StoredValue) -> Vec<u8> {
+        let mut value_vec = match value.schema_version {
+            Some(schema_version) => prepend_schema_version(schema_version, value.payload),
+            None => value.payload.to_vec(),
+        };
+        if self.versioned {
+            let version = value.version.unwrap_or_else(Version::next);
+            value_vec = prepend_version(version, value_vec);
+        }
+        if self.expiring {
+            let expiration = value.expiration.unwrap_or(u64::MAX);
+            value_vec = prepend_expiration(expiration, value_vec);
+        }
+
+        value_vec
+    }
+}
+
+/// A value as stored in a column family: the value serialized with the
+/// codec, preceded by the version of its [`Schema`](super::Schema) if it has
+/// one, the [`Version`] if the column family is versioned and the expiration
+/// time if the column family has TTL.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StoredValue<'a> {
+    /// Seconds since the Unix epoch, which is `u64::MAX` if the value never
+    /// expires.
+    pub expiration: Option<u64>,
+    pub version: Option<Version>,
+    pub schema_version: Option<u32>,
+    pub payload: &'a [u8],
+}
+
+impl KvStore {
+    /// Store the format of the database if it has none, or fail with
+    /// [`KvStoreError::FormatMismatch`] if the database is written with
+    /// another codec or key encoding. The formats of the declared column
+    /// families are stored over the stored ones, the column families which
+    /// are not declared keep their stored format, and the ones which no
+    /// longer exist are removed.
+    pub(super) fn store_format(&self) -> Result<(), KvStoreError> {
+        if self
+            .database
+            .cf_handle(StoredFormat::COLUMN_FAMILY)
+            .is_none()
+        {
+            self.database
+                .create_cf(StoredFormat::COLUMN_FAMILY, &ColumnFamilyOptions::default())
+                .map_err(KvStoreError::CreateColumnFamily)?;
+        }
+        let handle = self
+            .database
+            .cf_handle(StoredFormat::COLUMN_FAMILY)
+            .ok_or_else(|| KvStoreError::ColumnFamily(StoredFormat::COLUMN_FAMILY.to_owned()))?;
+
+        let stored_format = self
+            .database
+            .get_pinned_cf(&handle, StoredFormat::KEY)
+            .map_err(KvStoreError::Get)?
+            .map(|value_slice| StoredFormat::from_slice(&value_slice))
+            .transpose()?;
+        let mut format = match stored_format.clone() {
+            Some(format)
+                if format.codec != self.codec.name()
+                    || format.key_encoding != self.key_encoding =>
+            {
+                return Err(KvStoreError::FormatMismatch {
+                    codec: format.codec,
+                    key_encoding: format.key_encoding,
+                })
+            }
+            Some(format) => format,
+            None => StoredFormat {
+                codec: self.codec.name().to_owned(),
+                key_encoding: self.key_encoding,
+                column_families: BTreeMap::new(),
+            },
+        };
+        format.column_families.extend(
+            self.formats
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(name, value_format)| {
+                    (name.clone(), ColumnFamilyFormat::from(*value_format))
+                }),
+        );
+        format
+            .column_families
+            .retain(|name, _| self.database.cf_handle(name).is_some());
+        if stored_format.as_ref() == Some(&format) {
+            return Ok(());
+        }
+
+        let mut batch = self.database.write_batch()?;
+        batch.put_cf(&handle, StoredFormat::KEY, format.to_vec()?);
+        self.database.write(batch).map_err(KvStoreError::WriteBatch)
+    }
+}
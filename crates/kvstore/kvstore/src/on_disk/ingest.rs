@@ -0,0 +1,125 @@
+use std::{fmt::Debug, path::Path, sync::Arc};
+
+use rocksdb::SstFileWriter;
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    column_family::{encode, encode_key, ValueFormat},
+    schema::serialize_value,
+    ColumnFamilyOptions, KvStoreError,
+};
+use crate::data_type::{default_codec, Codec, KeyEncoding};
+
+/// Writer of an SST file to bulk load into a column family with
+/// [`KvStoreBuilder::ingest()`](super::KvStoreBuilder::ingest), which is much
+/// faster than writing the key-value pairs one by one for backfills.
+///
+/// The files are only ingested when the database is opened, so a running
+/// database must be closed and reopened to load them.
+///
+/// The keys must be put in ascending order of the serialized keys, which is
+/// the order of the keys returned by [`KvStore::iter()`](super::KvStore::iter)
+/// and only the order of the keys themselves with
+/// [`KeyEncoding::Ordered`](crate::KeyEncoding::Ordered).
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{ColumnFamilyOptions, KeyEncoding, KvStoreBuilder, SstWriter};
+///
+/// let directory = std::env::temp_dir().join("kvstore_ingest_example");
+/// # let _ = std::fs::remove_dir_all(&directory);
+/// std::fs::create_dir_all(&directory).unwrap();
+/// let sst_path = directory.join("heights.sst");
+///
+/// let options = ColumnFamilyOptions::default();
+/// let mut writer = SstWriter::create(&sst_path, &options)
+///     .unwrap()
+///     .set_key_encoding(KeyEncoding::Ordered);
+/// for height in 0..1000_u64 {
+///     writer
+///         .put(&("Height", height), &format!("block {height}"))
+///         .unwrap();
+/// }
+/// writer.finish().unwrap();
+///
+/// let database = KvStoreBuilder::default()
+///     .set_key_encoding(KeyEncoding::Ordered)
+///     .ingest("default", [sst_path])
+///     .build(directory.join("database"))
+///     .unwrap();
+/// assert_eq!(
+///     database.get::<_, String>(&("Height", 999_u64)).unwrap(),
+///     "block 999"
+/// );
+/// ```
+pub struct SstWriter<'a> {
+    writer: SstFileWriter<'a>,
+    format: ValueFormat,
+    codec: Arc<dyn Codec>,
+    key_encoding: KeyEncoding,
+}
+
+impl<'a> SstWriter<'a> {
+    /// Create the file at `path` for a column family with `options`.
+    pub fn create(
+        path: impl AsRef<Path>,
+        options: &'a ColumnFamilyOptions,
+    ) -> Result<Self, KvStoreError> {
+        let writer = SstFileWriter::create(&options.options);
+        writer.open(path).map_err(KvStoreError::WriteSstFile)?;
+
+        Ok(Self {
+            writer,
+            format: options.format,
+            codec: default_codec(),
+            key_encoding: KeyEncoding::default(),
+        })
+    }
+
+    /// Serialize the values with `codec`, which must be the codec of the
+    /// database to ingest the file into.
+    pub fn set_codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+
+        self
+    }
+
+    /// Serialize the keys with `key_encoding`, which must be the key encoding
+    /// of the database to ingest the file into.
+    pub fn set_key_encoding(mut self, key_encoding: KeyEncoding) -> Self {
+        self.key_encoding = key_encoding;
+
+        self
+    }
+
+    pub fn put<K, V>(&mut self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = encode_key(self.key_encoding, self.codec.as_ref(), key)?;
+        let value_vec = encode(self.format, serialize_value(self.codec.as_ref(), value)?);
+
+        self.writer
+            .put(key_vec, value_vec)
+            .map_err(KvStoreError::WriteSstFile)
+    }
+
+    pub fn delete<K>(&mut self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        let key_vec = encode_key(self.key_encoding, self.codec.as_ref(), key)?;
+
+        self.writer
+            .delete(key_vec)
+            .map_err(KvStoreError::WriteSstFile)
+    }
+
+    /// Finish writing the file, which must be called before the file is
+    /// ingested.
+    pub fn finish(mut self) -> Result<(), KvStoreError> {
+        self.writer.finish().map_err(KvStoreError::WriteSstFile)
+    }
+}
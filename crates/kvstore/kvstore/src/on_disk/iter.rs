@@ -0,0 +1,240 @@
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    ops::{Bound, RangeBounds},
+};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    database::{RawIterator, RawSnapshot},
+    ColumnFamily, KvStoreError,
+};
+
+/// Iterator over the key-value pairs in the order of the keys, returned by
+/// [`KvStore::iter()`](super::KvStore::iter),
+/// [`KvStore::iter_prefix()`](super::KvStore::iter_prefix),
+/// [`KvStore::iter_range()`](super::KvStore::iter_range) and the equivalent
+/// methods of [`ColumnFamily`] and [`Snapshot`](super::Snapshot).
+pub struct Iter<'db, K, V> {
+    iterator: RawIterator<'db>,
+    // Also keeps the column family alive while iterating even if it is dropped.
+    column_family: ColumnFamily<'db>,
+    lower_bound: Bound<Vec<u8>>,
+    upper_bound: Bound<Vec<u8>>,
+    reverse: bool,
+    state: State,
+    _type: PhantomData<(K, V)>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Unpositioned,
+    Positioned,
+    Finished,
+}
+
+impl<'db, K, V> Iter<'db, K, V> {
+    pub(super) fn new(
+        column_family: ColumnFamily<'db>,
+        (lower_bound, upper_bound): KeyBounds,
+    ) -> Self {
+        let iterator = column_family
+            .database
+            .raw_iterator_cf(&column_family.handle);
+
+        Self::with_iterator(iterator, column_family, lower_bound, upper_bound)
+    }
+
+    /// Iterate over the state of the database at the snapshot.
+    pub(super) fn with_snapshot(
+        column_family: ColumnFamily<'db>,
+        snapshot: &'db RawSnapshot<'db>,
+        (lower_bound, upper_bound): KeyBounds,
+    ) -> Self {
+        let iterator = snapshot.raw_iterator_cf(column_family.database, &column_family.handle);
+
+        Self::with_iterator(iterator, column_family, lower_bound, upper_bound)
+    }
+
+    fn with_iterator(
+        iterator: RawIterator<'db>,
+        column_family: ColumnFamily<'db>,
+        lower_bound: Bound<Vec<u8>>,
+        upper_bound: Bound<Vec<u8>>,
+    ) -> Self {
+        Self {
+            iterator,
+            column_family,
+            lower_bound,
+            upper_bound,
+            reverse: false,
+            state: State::Unpositioned,
+            _type: PhantomData,
+        }
+    }
+
+    /// Iterate from the last key in reverse order. The iteration starts over
+    /// if it has already started.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self.state = State::Unpositioned;
+
+        self
+    }
+
+    fn advance(&mut self) {
+        match (self.state, self.reverse) {
+            (State::Unpositioned, false) => match &self.lower_bound {
+                Bound::Included(key_vec) | Bound::Excluded(key_vec) => self.iterator.seek(key_vec),
+                Bound::Unbounded => self.iterator.seek_to_first(),
+            },
+            (State::Unpositioned, true) => match &self.upper_bound {
+                Bound::Included(key_vec) | Bound::Excluded(key_vec) => {
+                    self.iterator.seek_for_prev(key_vec)
+                }
+                Bound::Unbounded => self.iterator.seek_to_last(),
+            },
+            (_, false) => self.iterator.next(),
+            (_, true) => self.iterator.prev(),
+        }
+        self.state = State::Positioned;
+    }
+
+    /// Whether `key_slice` is past the bound where the iteration ends.
+    fn is_past_end(&self, key_slice: &[u8]) -> bool {
+        match self.reverse {
+            false => match &self.upper_bound {
+                Bound::Included(key_vec) => key_slice > key_vec.as_slice(),
+                Bound::Excluded(key_vec) => key_slice >= key_vec.as_slice(),
+                Bound::Unbounded => false,
+            },
+            true => match &self.lower_bound {
+                Bound::Included(key_vec) => key_slice < key_vec.as_slice(),
+                Bound::Excluded(key_vec) => key_slice <= key_vec.as_slice(),
+                Bound::Unbounded => false,
+            },
+        }
+    }
+
+    /// Whether `key_slice` is the excluded bound where the iteration starts.
+    fn is_excluded_start(&self, key_slice: &[u8]) -> bool {
+        let start_bound = match self.reverse {
+            false => &self.lower_bound,
+            true => &self.upper_bound,
+        };
+
+        match start_bound {
+            Bound::Excluded(key_vec) => key_slice == key_vec.as_slice(),
+            _others => false,
+        }
+    }
+}
+
+impl<K, V> Iterator for Iter<'_, K, V>
+where
+    K: Debug + DeserializeOwned + Serialize,
+    V: Debug + DeserializeOwned + Serialize,
+{
+    type Item = Result<(K, V), KvStoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.state != State::Finished {
+            self.advance();
+
+            let (key_slice, value_slice) = match self.iterator.item() {
+                Some(key_value) => key_value,
+                None => {
+                    self.state = State::Finished;
+                    return self
+                        .iterator
+                        .status()
+                        .err()
+                        .map(|error| Err(KvStoreError::Iterate(error)));
+                }
+            };
+
+            if self.is_past_end(key_slice) {
+                self.state = State::Finished;
+                return None;
+            }
+            if self.is_excluded_start(key_slice) {
+                continue;
+            }
+
+            // Skip the expired values which have not been compacted yet.
+            if let Some(value_slice) = self.column_family.decode(value_slice) {
+                let key_value = self
+                    .column_family
+                    .deserialize_key(key_slice)
+                    .and_then(|key| Ok((key, self.column_family.deserialize_value(value_slice)?)));
+
+                return Some(key_value);
+            }
+        }
+
+        None
+    }
+}
+
+/// The lower and upper bounds of the serialized keys to iterate over.
+pub(super) type KeyBounds = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+/// The bounds of every key.
+pub(super) fn all_bounds() -> KeyBounds {
+    (Bound::Unbounded, Bound::Unbounded)
+}
+
+/// The bounds of the keys starting with `prefix`.
+pub(super) fn prefix_bounds<P>(
+    column_family: &ColumnFamily,
+    prefix: &P,
+) -> Result<KeyBounds, KvStoreError>
+where
+    P: Debug + Serialize,
+{
+    let prefix_vec = column_family.serialize_prefix(prefix)?;
+    let upper_bound = match prefix_end(&prefix_vec) {
+        Some(end_vec) => Bound::Excluded(end_vec),
+        None => Bound::Unbounded,
+    };
+
+    Ok((Bound::Included(prefix_vec), upper_bound))
+}
+
+/// The bounds of the keys in `range`.
+pub(super) fn range_bounds<R, P>(
+    column_family: &ColumnFamily,
+    range: R,
+) -> Result<KeyBounds, KvStoreError>
+where
+    R: RangeBounds<P>,
+    P: Debug + Serialize,
+{
+    let serialize_bound = |bound: Bound<&P>| -> Result<Bound<Vec<u8>>, KvStoreError> {
+        Ok(match bound {
+            Bound::Included(key) => Bound::Included(column_family.serialize_prefix(key)?),
+            Bound::Excluded(key) => Bound::Excluded(column_family.serialize_prefix(key)?),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    };
+
+    Ok((
+        serialize_bound(range.start_bound())?,
+        serialize_bound(range.end_bound())?,
+    ))
+}
+
+/// The smallest key greater than every key starting with `prefix`, or `None`
+/// if there is no such key.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(last) = end.pop() {
+        if last < u8::MAX {
+            end.push(last + 1);
+            return Some(end);
+        }
+    }
+
+    None
+}
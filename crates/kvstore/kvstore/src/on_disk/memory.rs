@@ -0,0 +1,471 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use super::backend::{
+    BatchOperation, ErrorKind, StorageBackend, StorageCursor, StorageError, StorageSnapshot,
+    StorageTransaction,
+};
+
+/// [`StorageBackend`] keeping every column family in a `BTreeMap`, which
+/// needs neither the file system nor RocksDB. Everything is lost when it is
+/// dropped, which suits the tests of the code using [`KvStore`](super::KvStore)
+/// and the models.
+///
+/// Like RocksDB, the transactions lock the keys they write or read for
+/// update, and fail with
+/// [`KvStoreError::LockTimeout`](super::KvStoreError::LockTimeout)
+/// if a key stays locked by another transaction for the lock timeout.
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{KvStore, Model};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+/// #[kvstore(path = kvstore)]
+/// #[kvstore(database = "memory_example")]
+/// #[kvstore(key(owner: &str))]
+/// struct Account {
+///     balance: u64,
+/// }
+///
+/// KvStore::in_memory().init_named("memory_example");
+///
+/// Account { balance: 10 }.put("alice").unwrap();
+/// assert_eq!(Account::get("alice").unwrap(), Account { balance: 10 });
+/// ```
+pub struct MemoryBackend {
+    state: Mutex<State>,
+    // Notified when any lock is released.
+    unlocked: Condvar,
+    next_transaction: AtomicU64,
+    lock_timeout: Duration,
+}
+
+/// The key-value pairs of a column family, which are copied on write only
+/// while a snapshot or a cursor is reading them.
+type Map = Arc<BTreeMap<Vec<u8>, Vec<u8>>>;
+
+#[derive(Default)]
+struct State {
+    column_families: HashMap<String, Map>,
+    // The transaction holding the lock of each key.
+    locks: HashMap<(String, Vec<u8>), u64>,
+}
+
+impl Default for MemoryBackend {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            unlocked: Condvar::new(),
+            next_transaction: AtomicU64::new(0),
+            lock_timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+impl MemoryBackend {
+    /// Set how long a transaction waits for a key locked by another one. The
+    /// default is 1 second, which is the default of RocksDB.
+    pub fn set_lock_timeout(mut self, lock_timeout: Duration) -> Self {
+        self.lock_timeout = lock_timeout;
+
+        self
+    }
+
+    fn state(&self) -> MutexGuard<State> {
+        self.state.lock().unwrap()
+    }
+
+    /// Lock the key for the transaction, waiting for the other transaction
+    /// holding it up to the lock timeout. Returns whether the key was newly
+    /// locked.
+    fn lock(
+        &self,
+        transaction: u64,
+        column_family: &str,
+        key: &[u8],
+    ) -> Result<bool, StorageError> {
+        let lock_key = (column_family.to_owned(), key.to_vec());
+        let deadline = Instant::now() + self.lock_timeout;
+
+        let mut state = self.state();
+        loop {
+            match state.locks.get(&lock_key).copied() {
+                None => {
+                    state.locks.insert(lock_key, transaction);
+                    return Ok(true);
+                }
+                Some(owner) if owner == transaction => return Ok(false),
+                Some(_) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    if timeout.is_zero() {
+                        return Err(StorageError::new(
+                            ErrorKind::TimedOut,
+                            "Operation timed out: Timeout waiting to lock key",
+                        ));
+                    }
+                    state = self.unlocked.wait_timeout(state, timeout).unwrap().0;
+                }
+            }
+        }
+    }
+
+    fn unlock(&self, lock_keys: Vec<(String, Vec<u8>)>) {
+        if lock_keys.is_empty() {
+            return;
+        }
+
+        let mut state = self.state();
+        for lock_key in lock_keys {
+            state.locks.remove(&lock_key);
+        }
+        self.unlocked.notify_all();
+    }
+}
+
+impl State {
+    fn column_family(&self, column_family: &str) -> Result<&Map, StorageError> {
+        self.column_families
+            .get(column_family)
+            .ok_or_else(|| column_family_not_found(column_family))
+    }
+
+    /// Apply every operation, or none of them if any column family does not
+    /// exist.
+    fn apply(&mut self, operations: Vec<BatchOperation>) -> Result<(), StorageError> {
+        for operation in &operations {
+            match operation {
+                BatchOperation::Put { column_family, .. }
+                | BatchOperation::Delete { column_family, .. } => {
+                    self.column_family(column_family)?;
+                }
+            }
+        }
+
+        for operation in operations {
+            match operation {
+                BatchOperation::Put {
+                    column_family,
+                    key,
+                    value,
+                } => {
+                    let map = self.column_families.get_mut(&column_family).unwrap();
+                    Arc::make_mut(map).insert(key, value);
+                }
+                BatchOperation::Delete { column_family, key } => {
+                    let map = self.column_families.get_mut(&column_family).unwrap();
+                    Arc::make_mut(map).remove(&key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn column_family_not_found(column_family: &str) -> StorageError {
+    StorageError::new(
+        ErrorKind::InvalidArgument,
+        format!("Invalid argument: Column family {column_family} not found"),
+    )
+}
+
+impl StorageBackend for MemoryBackend {
+    fn has_column_family(&self, column_family: &str) -> bool {
+        self.state().column_families.contains_key(column_family)
+    }
+
+    fn create_column_family(&self, column_family: &str) -> Result<(), StorageError> {
+        let mut state = self.state();
+        if state.column_families.contains_key(column_family) {
+            return Err(StorageError::new(
+                ErrorKind::InvalidArgument,
+                "Invalid argument: Column family already exists",
+            ));
+        }
+        state
+            .column_families
+            .insert(column_family.to_owned(), Map::default());
+
+        Ok(())
+    }
+
+    fn drop_column_family(&self, column_family: &str) -> Result<(), StorageError> {
+        self.state()
+            .column_families
+            .remove(column_family)
+            .map(|_| ())
+            .ok_or_else(|| column_family_not_found(column_family))
+    }
+
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        Ok(self.state().column_family(column_family)?.get(key).cloned())
+    }
+
+    fn transaction(&self) -> Box<dyn StorageTransaction + '_> {
+        Box::new(MemoryTransaction {
+            backend: self,
+            id: self.next_transaction.fetch_add(1, Ordering::Relaxed),
+            writes: RefCell::new(Vec::new()),
+            savepoints: RefCell::new(Vec::new()),
+            lock_keys: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn write(&self, operations: Vec<BatchOperation>) -> Result<(), StorageError> {
+        // Like the write batches of TransactionDB, waits for the keys locked
+        // by the transactions.
+        let transaction = self.transaction();
+        for operation in operations {
+            match operation {
+                BatchOperation::Put {
+                    column_family,
+                    key,
+                    value,
+                } => transaction.put(&column_family, &key, &value)?,
+                BatchOperation::Delete { column_family, key } => {
+                    transaction.delete(&column_family, &key)?
+                }
+            }
+        }
+
+        transaction.commit()
+    }
+
+    fn snapshot(&self) -> Box<dyn StorageSnapshot + '_> {
+        Box::new(MemorySnapshot {
+            column_families: self.state().column_families.clone(),
+        })
+    }
+
+    fn cursor(&self, column_family: &str) -> Box<dyn StorageCursor + '_> {
+        let map = self.state().column_families.get(column_family).cloned();
+
+        Box::new(MemoryCursor::new(map.unwrap_or_default()))
+    }
+}
+
+struct MemoryTransaction<'db> {
+    backend: &'db MemoryBackend,
+    id: u64,
+    // Applied in order on commit.
+    writes: RefCell<Vec<BatchOperation>>,
+    // The number of the writes at each savepoint.
+    savepoints: RefCell<Vec<usize>>,
+    // Released when the transaction ends.
+    lock_keys: RefCell<Vec<(String, Vec<u8>)>>,
+}
+
+impl MemoryTransaction<'_> {
+    fn lock(&self, column_family: &str, key: &[u8]) -> Result<(), StorageError> {
+        if self.backend.lock(self.id, column_family, key)? {
+            self.lock_keys
+                .borrow_mut()
+                .push((column_family.to_owned(), key.to_vec()));
+        }
+
+        Ok(())
+    }
+}
+
+impl StorageTransaction for MemoryTransaction<'_> {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let written = self
+            .writes
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|operation| match operation {
+                BatchOperation::Put {
+                    column_family: written_column_family,
+                    key: written_key,
+                    value,
+                } if written_column_family == column_family && written_key == key => {
+                    Some(Some(value.clone()))
+                }
+                BatchOperation::Delete {
+                    column_family: written_column_family,
+                    key: written_key,
+                } if written_column_family == column_family && written_key == key => Some(None),
+                _others => None,
+            });
+
+        match written {
+            Some(value) => Ok(value),
+            None => self.backend.get(column_family, key),
+        }
+    }
+
+    fn get_for_update(
+        &self,
+        column_family: &str,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, StorageError> {
+        self.lock(column_family, key)?;
+
+        self.get(column_family, key)
+    }
+
+    fn put(&self, column_family: &str, key: &[u8], value: &[u8]) -> Result<(), StorageError> {
+        self.lock(column_family, key)?;
+        self.writes.borrow_mut().push(BatchOperation::Put {
+            column_family: column_family.to_owned(),
+            key: key.to_vec(),
+            value: value.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    fn delete(&self, column_family: &str, key: &[u8]) -> Result<(), StorageError> {
+        self.lock(column_family, key)?;
+        self.writes.borrow_mut().push(BatchOperation::Delete {
+            column_family: column_family.to_owned(),
+            key: key.to_vec(),
+        });
+
+        Ok(())
+    }
+
+    fn commit(self: Box<Self>) -> Result<(), StorageError> {
+        // The locks are released when the transaction is dropped.
+        self.backend.state().apply(self.writes.take())
+    }
+
+    fn rollback(&self) -> Result<(), StorageError> {
+        self.writes.borrow_mut().clear();
+        self.savepoints.borrow_mut().clear();
+        self.backend.unlock(self.lock_keys.take());
+
+        Ok(())
+    }
+
+    fn set_savepoint(&self) {
+        self.savepoints
+            .borrow_mut()
+            .push(self.writes.borrow().len());
+    }
+
+    fn rollback_to_savepoint(&self) -> Result<(), StorageError> {
+        match self.savepoints.borrow_mut().pop() {
+            Some(length) => {
+                self.writes.borrow_mut().truncate(length);
+
+                Ok(())
+            }
+            None => Err(StorageError::new(
+                ErrorKind::NotFound,
+                "NotFound: No SavePoint set",
+            )),
+        }
+    }
+}
+
+impl Drop for MemoryTransaction<'_> {
+    fn drop(&mut self) {
+        self.backend.unlock(self.lock_keys.take());
+    }
+}
+
+struct MemorySnapshot {
+    column_families: HashMap<String, Map>,
+}
+
+impl StorageSnapshot for MemorySnapshot {
+    fn get(&self, column_family: &str, key: &[u8]) -> Result<Option<Vec<u8>>, StorageError> {
+        let map = self
+            .column_families
+            .get(column_family)
+            .ok_or_else(|| column_family_not_found(column_family))?;
+
+        Ok(map.get(key).cloned())
+    }
+
+    fn cursor(&self, column_family: &str) -> Box<dyn StorageCursor + '_> {
+        let map = self.column_families.get(column_family).cloned();
+
+        Box::new(MemoryCursor::new(map.unwrap_or_default()))
+    }
+}
+
+struct MemoryCursor {
+    map: Map,
+    position: Option<Vec<u8>>,
+}
+
+impl MemoryCursor {
+    fn new(map: Map) -> Self {
+        Self {
+            map,
+            position: None,
+        }
+    }
+}
+
+impl StorageCursor for MemoryCursor {
+    fn seek(&mut self, key: &[u8]) {
+        self.position = self
+            .map
+            .range::<[u8], _>((Bound::Included(key), Bound::Unbounded))
+            .next()
+            .map(|(key, _)| key.clone());
+    }
+
+    fn seek_for_prev(&mut self, key: &[u8]) {
+        self.position = self
+            .map
+            .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+            .next_back()
+            .map(|(key, _)| key.clone());
+    }
+
+    fn seek_to_first(&mut self) {
+        self.position = self.map.keys().next().cloned();
+    }
+
+    fn seek_to_last(&mut self) {
+        self.position = self.map.keys().next_back().cloned();
+    }
+
+    fn next(&mut self) {
+        if let Some(position) = self.position.take() {
+            self.position = self
+                .map
+                .range::<[u8], _>((Bound::Excluded(position.as_slice()), Bound::Unbounded))
+                .next()
+                .map(|(key, _)| key.clone());
+        }
+    }
+
+    fn prev(&mut self) {
+        if let Some(position) = self.position.take() {
+            self.position = self
+                .map
+                .range::<[u8], _>((Bound::Unbounded, Bound::Excluded(position.as_slice())))
+                .next_back()
+                .map(|(key, _)| key.clone());
+        }
+    }
+
+    fn item(&self) -> Option<(&[u8], &[u8])> {
+        let position = self.position.as_deref()?;
+
+        self.map
+            .get_key_value(position)
+            .map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+
+    fn status(&self) -> Result<(), StorageError> {
+        Ok(())
+    }
+}
@@ -0,0 +1,2885 @@
+mod asynchronous;
+mod backend;
+#[cfg(feature = "rocksdb")]
+mod backup;
+mod column_family;
+mod database;
+mod format;
+#[cfg(feature = "rocksdb")]
+mod ingest;
+mod iter;
+mod memory;
+mod schema;
+mod snapshot;
+#[cfg(feature = "rocksdb")]
+mod statistics;
+mod transaction;
+mod watch;
+mod write_batch;
+
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    mem::MaybeUninit,
+    ops::RangeBounds,
+    sync::{Arc, Once, OnceLock, RwLock},
+    time::Duration,
+};
+#[cfg(feature = "rocksdb")]
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use asynchronous::BlockingPool;
+pub use asynchronous::{AsyncKvStore, AsyncLock};
+pub use backend::{
+    BatchOperation, ErrorKind, StorageBackend, StorageCursor, StorageError, StorageSnapshot,
+    StorageTransaction,
+};
+#[cfg(feature = "rocksdb")]
+pub use backup::BackupEngine;
+#[cfg(feature = "rocksdb")]
+use backup::Secondary;
+use column_family::ValueFormat;
+pub use column_family::{ColumnFamily, ColumnFamilyOptions, Version};
+pub use database::ConcurrencyMode;
+#[cfg(feature = "rocksdb")]
+use database::PessimisticOptions;
+use database::{Database, RawTransaction};
+pub use format::{ColumnFamilyFormat, StoredFormat, StoredValue};
+#[cfg(feature = "rocksdb")]
+pub use ingest::SstWriter;
+pub use iter::Iter;
+pub use memory::MemoryBackend;
+#[cfg(feature = "rocksdb")]
+use rocksdb::{
+    checkpoint::Checkpoint, Cache, ColumnFamilyDescriptor, DBCompressionType, DBWithThreadMode,
+    IngestExternalFileOptions, MultiThreaded, OptimisticTransactionDB, Options, TransactionDB,
+    TransactionDBOptions, DB,
+};
+pub use schema::{register_codec, register_schema, Schema};
+use serde::{de::DeserializeOwned, ser::Serialize};
+pub use snapshot::{Snapshot, SnapshotColumnFamily};
+#[cfg(feature = "rocksdb")]
+use statistics::SharedOptions;
+#[cfg(feature = "rocksdb")]
+pub use statistics::{ColumnFamilyStatistics, LatencyHistogram, Statistics};
+pub use transaction::{Transaction, TransactionColumnFamily};
+use watch::Watchers;
+pub use watch::{Watch, WatchEvent};
+pub use write_batch::{WriteBatch, WriteBatchColumnFamily};
+
+use crate::data_type::{default_codec, Codec, KeyEncoding};
+
+/// The name of the column family of the operations of [`KvStore`], which is
+/// the default column family of RocksDB.
+const DEFAULT_COLUMN_FAMILY_NAME: &str = "default";
+
+/// The message of the `Busy` status with the `kDeadlock` subcode, which
+/// rust-rocksdb does not expose otherwise.
+const DEADLOCK_MESSAGE: &str = "Deadlock";
+
+static mut KVSTORE: MaybeUninit<KvStore> = MaybeUninit::uninit();
+static INIT: Once = Once::new();
+static NAMED_KVSTORE: OnceLock<RwLock<HashMap<String, &'static KvStore>>> = OnceLock::new();
+
+#[allow(static_mut_refs)]
+pub fn kvstore() -> Result<&'static KvStore, KvStoreError> {
+    match INIT.is_completed() {
+        true => unsafe { Ok(KVSTORE.assume_init_ref()) },
+        false => Err(KvStoreError::Initialize),
+    }
+}
+
+/// Get the database registered with [`KvStore::init_named()`].
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{kvstore_named, KvStore};
+///
+/// KvStore::open(std::env::temp_dir().join("kvstore_named_example"))
+///     .unwrap()
+///     .init_named("ledger");
+///
+/// let ledger = kvstore_named("ledger").unwrap();
+/// ledger.put(&"key", &"value".to_owned()).unwrap();
+/// ```
+pub fn kvstore_named(name: &str) -> Result<&'static KvStore, KvStoreError> {
+    NAMED_KVSTORE
+        .get()
+        .and_then(|registry| registry.read().unwrap().get(name).copied())
+        .ok_or_else(|| KvStoreError::InitializeNamed(name.to_owned()))
+}
+
+pub struct KvStoreBuilder {
+    #[cfg(feature = "rocksdb")]
+    database_options: Options,
+    #[cfg(feature = "rocksdb")]
+    transaction_database_options: TransactionDBOptions,
+    #[cfg(feature = "rocksdb")]
+    concurrency_mode: ConcurrencyMode,
+    #[cfg(feature = "rocksdb")]
+    deadlock_detect: bool,
+    column_families: Vec<(String, ColumnFamilyOptions)>,
+    transaction_retry_limit: usize,
+    blocking_threads: usize,
+    #[cfg(feature = "rocksdb")]
+    ingest: Vec<(String, Vec<PathBuf>)>,
+    codec: Arc<dyn Codec>,
+    key_encoding: KeyEncoding,
+    #[cfg(feature = "rocksdb")]
+    statistics: bool,
+    #[cfg(feature = "rocksdb")]
+    shared_options: SharedOptions,
+}
+
+impl Default for KvStoreBuilder {
+    fn default() -> Self {
+        #[cfg(feature = "rocksdb")]
+        let mut database_options = Options::default();
+        #[cfg(feature = "rocksdb")]
+        database_options.create_if_missing(true);
+
+        Self {
+            #[cfg(feature = "rocksdb")]
+            database_options,
+            #[cfg(feature = "rocksdb")]
+            transaction_database_options: TransactionDBOptions::default(),
+            #[cfg(feature = "rocksdb")]
+            concurrency_mode: ConcurrencyMode::default(),
+            #[cfg(feature = "rocksdb")]
+            deadlock_detect: false,
+            column_families: Vec::new(),
+            transaction_retry_limit: 8,
+            blocking_threads: std::thread::available_parallelism()
+                .map(usize::from)
+                .unwrap_or(4),
+            #[cfg(feature = "rocksdb")]
+            ingest: Vec::new(),
+            codec: default_codec(),
+            key_encoding: KeyEncoding::default(),
+            #[cfg(feature = "rocksdb")]
+            statistics: false,
+            #[cfg(feature = "rocksdb")]
+            shared_options: SharedOptions::default(),
+        }
+    }
+}
+
+impl KvStoreBuilder {
+    /// Set the format of the values, which is JSON by default. The models
+    /// with `#[kvstore(codec = ...)]` keep their own codec. See [`Codec`].
+    pub fn set_codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Arc::new(codec);
+
+        self
+    }
+
+    /// Set the format of the keys, which is [`KeyEncoding::Codec`] by default.
+    /// [`KeyEncoding::Ordered`] is required by the prefix and range iterators
+    /// but cannot read the keys written with [`KeyEncoding::Codec`], so it is
+    /// only for new databases. See [`KeyEncoding`].
+    pub fn set_key_encoding(mut self, key_encoding: KeyEncoding) -> Self {
+        self.key_encoding = key_encoding;
+
+        self
+    }
+
+    /// Set how many times [`KvStore::transaction()`] is retried when it fails
+    /// with a retryable error. The default is 8.
+    pub fn set_transaction_retry_limit(mut self, transaction_retry_limit: usize) -> Self {
+        self.transaction_retry_limit = transaction_retry_limit;
+
+        self
+    }
+
+    /// Set the number of threads to run the operations of [`AsyncKvStore`]
+    /// on, which is also the number of the operations running at once. The
+    /// default is the number of the available CPUs.
+    pub fn set_blocking_threads(mut self, blocking_threads: usize) -> Self {
+        self.blocking_threads = blocking_threads.max(1);
+
+        self
+    }
+
+    /// Declare a column family, which is created if it does not exist.
+    pub fn column_family(mut self, name: impl Into<String>, options: ColumnFamilyOptions) -> Self {
+        self.column_families.push((name.into(), options));
+
+        self
+    }
+
+    /// Build the database on `backend` instead of RocksDB, creating the
+    /// declared column families which do not exist. The RocksDB options of
+    /// the builder and the column families have no effect, and the backups,
+    /// checkpoints and SST files fail with [`KvStoreError::Unsupported`].
+    pub fn build_with(
+        self,
+        backend: impl StorageBackend + 'static,
+    ) -> Result<KvStore, KvStoreError> {
+        #[cfg(feature = "rocksdb")]
+        if !self.ingest.is_empty() {
+            return Err(KvStoreError::Unsupported("ingest"));
+        }
+
+        let mut formats = Formats::new();
+        for name in self
+            .column_families
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .chain([DEFAULT_COLUMN_FAMILY_NAME])
+        {
+            if !backend.has_column_family(name) {
+                backend
+                    .create_column_family(name)
+                    .map_err(KvStoreError::CreateColumnFamily)?;
+            }
+        }
+        for (name, options) in self.column_families {
+            formats.insert(name, options.format);
+        }
+
+        let kvstore = KvStore {
+            database: Arc::new(Database::Backend(Box::new(backend))),
+            formats: Arc::new(RwLock::new(formats)),
+            transaction_retry_limit: self.transaction_retry_limit,
+            blocking_pool: Arc::new(BlockingPool::new(self.blocking_threads)),
+            watchers: Arc::new(Watchers::default()),
+            codec: self.codec,
+            key_encoding: self.key_encoding,
+            #[cfg(feature = "rocksdb")]
+            shared_options: Arc::new(SharedOptions::default()),
+            #[cfg(feature = "rocksdb")]
+            secondary: Arc::default(),
+        };
+        kvstore.store_format()?;
+
+        Ok(kvstore)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl KvStoreBuilder {
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.increase_parallelism
+    pub fn increase_parallelism(mut self, parallelism: i32) -> Self {
+        self.database_options.increase_parallelism(parallelism);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.TransactionDBOptions.html#method.set_default_lock_timeout
+    pub fn set_default_lock_timeout(mut self, default_lock_timeout: i64) -> Self {
+        self.transaction_database_options
+            .set_default_lock_timeout(default_lock_timeout);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.TransactionDBOptions.html#method.set_max_num_locks
+    pub fn set_max_num_locks(mut self, max_num_locks: i64) -> Self {
+        self.transaction_database_options
+            .set_max_num_locks(max_num_locks);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.TransactionDBOptions.html#method.set_num_stripes
+    pub fn set_num_stripes(mut self, num_stripes: usize) -> Self {
+        self.transaction_database_options
+            .set_num_stripes(num_stripes);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.TransactionDBOptions.html#method.set_txn_lock_timeout
+    pub fn set_txn_lock_timeout(mut self, txn_lock_timeout: i64) -> Self {
+        self.transaction_database_options
+            .set_txn_lock_timeout(txn_lock_timeout);
+
+        self
+    }
+
+    /// Collect the block cache hits and misses and the latencies of the
+    /// operations returned by [`KvStore::statistics()`], which costs a little
+    /// performance.
+    ///
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.enable_statistics
+    pub fn enable_statistics(mut self) -> Self {
+        self.database_options.enable_statistics();
+        self.statistics = true;
+
+        self
+    }
+
+    /// Share a block cache of `block_cache_size` bytes among every column
+    /// family instead of the default cache of each column family.
+    ///
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Cache.html#method.new_lru_cache
+    pub fn set_block_cache_size(mut self, block_cache_size: usize) -> Self {
+        self.shared_options.block_cache = Some(Cache::new_lru_cache(block_cache_size));
+
+        self
+    }
+
+    /// Add bloom filters of `bits_per_key` bits per key to the SST files of
+    /// every column family, which saves the disk reads of the missing keys.
+    /// 10 bits per key give about 1% false positives.
+    ///
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.BlockBasedOptions.html#method.set_bloom_filter
+    pub fn set_bloom_filter(mut self, bits_per_key: f64) -> Self {
+        self.shared_options.bloom_filter_bits = Some(bits_per_key);
+
+        self
+    }
+
+    /// The compression of the default column family. The other column
+    /// families are compressed with
+    /// [`ColumnFamilyOptions::set_compression_type()`].
+    ///
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_compression_type
+    pub fn set_compression_type(mut self, compression_type: DBCompressionType) -> Self {
+        self.database_options.set_compression_type(compression_type);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_wal_dir
+    pub fn set_wal_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.database_options.set_wal_dir(path);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_max_total_wal_size
+    pub fn set_max_total_wal_size(mut self, size: u64) -> Self {
+        self.database_options.set_max_total_wal_size(size);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_wal_ttl_seconds
+    pub fn set_wal_ttl_seconds(mut self, seconds: u64) -> Self {
+        self.database_options.set_wal_ttl_seconds(seconds);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_wal_size_limit_mb
+    pub fn set_wal_size_limit_mb(mut self, size: u64) -> Self {
+        self.database_options.set_wal_size_limit_mb(size);
+
+        self
+    }
+
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.Options.html#method.set_wal_bytes_per_sync
+    pub fn set_wal_bytes_per_sync(mut self, bytes: u64) -> Self {
+        self.database_options.set_wal_bytes_per_sync(bytes);
+
+        self
+    }
+
+    /// Fail a transaction with [`KvStoreError::Deadlock`] instead of waiting
+    /// for the lock timeout when it deadlocks with other transactions.
+    ///
+    /// https://docs.rs/rocksdb/0.22.0/rocksdb/struct.TransactionOptions.html#method.set_deadlock_detect
+    pub fn set_deadlock_detect(mut self, deadlock_detect: bool) -> Self {
+        self.deadlock_detect = deadlock_detect;
+
+        self
+    }
+
+    /// Set how the conflicts between transactions are handled. The default is
+    /// [`ConcurrencyMode::Pessimistic`]. The lock options above have no effect
+    /// with [`ConcurrencyMode::Optimistic`].
+    pub fn set_concurrency_mode(mut self, concurrency_mode: ConcurrencyMode) -> Self {
+        self.concurrency_mode = concurrency_mode;
+
+        self
+    }
+
+    /// Bulk load the SST files written with [`SstWriter`] into the column
+    /// family when the database is opened. The ingested key-value pairs
+    /// overwrite the existing ones.
+    ///
+    /// There is no way to ingest into an open database, because
+    /// `TransactionDB` cannot ingest SST files. The files are ingested into the
+    /// database opened without transactions, which is then reopened.
+    pub fn ingest<P>(mut self, column_family: &str, paths: impl IntoIterator<Item = P>) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let paths = paths
+            .into_iter()
+            .map(|path| path.as_ref().to_path_buf())
+            .collect();
+        self.ingest.push((column_family.to_owned(), paths));
+
+        self
+    }
+
+    pub fn build(mut self, path: impl AsRef<Path>) -> Result<KvStore, KvStoreError> {
+        self.database_options.create_missing_column_families(true);
+
+        // The list is empty if the database does not exist yet.
+        let existing = TransactionDB::<MultiThreaded>::list_cf(&self.database_options, &path)
+            .unwrap_or_default();
+        let (column_families, formats) = self.column_family_options(existing);
+
+        // TransactionDB cannot ingest SST files, so they are ingested into the
+        // plain database before it is opened as TransactionDB.
+        if !self.ingest.is_empty() {
+            let descriptors = column_families
+                .iter()
+                .map(|(name, options)| ColumnFamilyDescriptor::new(name, options.clone()));
+            let database = DB::open_cf_descriptors(&self.database_options, &path, descriptors)
+                .map_err(KvStoreError::Open)?;
+
+            for (name, paths) in std::mem::take(&mut self.ingest) {
+                let handle = database
+                    .cf_handle(&name)
+                    .ok_or(KvStoreError::ColumnFamily(name.clone()))?;
+                database
+                    .ingest_external_file_cf_opts(
+                        handle,
+                        &IngestExternalFileOptions::default(),
+                        paths,
+                    )
+                    .map_err(KvStoreError::IngestSstFile)?;
+            }
+        }
+
+        let descriptors = column_families
+            .into_iter()
+            .map(|(name, options)| ColumnFamilyDescriptor::new(name, options));
+        let database = match self.concurrency_mode {
+            ConcurrencyMode::Pessimistic => TransactionDB::open_cf_descriptors(
+                &self.database_options,
+                &self.transaction_database_options,
+                path,
+                descriptors,
+            )
+            .map(|database| {
+                Database::Pessimistic(
+                    database,
+                    PessimisticOptions {
+                        deadlock_detect: self.deadlock_detect,
+                    },
+                )
+            }),
+            ConcurrencyMode::Optimistic => OptimisticTransactionDB::open_cf_descriptors(
+                &self.database_options,
+                path,
+                descriptors,
+            )
+            .map(Database::Optimistic),
+        }
+        .map_err(KvStoreError::Open)?;
+
+        let kvstore = self.into_kvstore(database, formats);
+        kvstore.store_format()?;
+
+        Ok(kvstore)
+    }
+
+    /// Open the existing database read-only without taking its lock, so that
+    /// it can be read while another process writes to it. The handle sees the
+    /// database as of when it is opened, and the writes fail with
+    /// [`KvStoreError::ReadOnly`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::{KvStore, KvStoreBuilder, KvStoreError};
+    ///
+    /// let path = std::env::temp_dir().join("kvstore_read_only_example");
+    /// let database = KvStore::open(&path).unwrap();
+    /// database.put(&"height", &1_u64).unwrap();
+    ///
+    /// let read_only = KvStoreBuilder::default().build_read_only(&path).unwrap();
+    /// assert_eq!(read_only.get::<_, u64>(&"height").unwrap(), 1);
+    /// assert!(matches!(
+    ///     read_only.put(&"height", &2_u64),
+    ///     Err(KvStoreError::ReadOnly)
+    /// ));
+    /// ```
+    pub fn build_read_only(mut self, path: impl AsRef<Path>) -> Result<KvStore, KvStoreError> {
+        if !self.ingest.is_empty() {
+            return Err(KvStoreError::Unsupported("ingest"));
+        }
+
+        let existing = DB::list_cf(&self.database_options, &path).map_err(KvStoreError::Open)?;
+        let (column_families, formats) = self.column_family_options(existing);
+
+        let descriptors = column_families
+            .into_iter()
+            .map(|(name, options)| ColumnFamilyDescriptor::new(name, options));
+        let database = DBWithThreadMode::open_cf_descriptors_read_only(
+            &self.database_options,
+            path,
+            descriptors,
+            false,
+        )
+        .map_err(KvStoreError::Open)?;
+
+        Ok(self.into_kvstore(Database::ReadOnly(database), formats))
+    }
+
+    /// Open a secondary instance of the database at `primary_path`, which
+    /// keeps its own logs in `secondary_path`. Like
+    /// [`KvStoreBuilder::build_read_only()`], it can be opened while another
+    /// process writes to the database and its writes fail with
+    /// [`KvStoreError::ReadOnly`], but it follows the writes with
+    /// [`KvStore::try_catch_up_with_primary()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::{KvStore, KvStoreBuilder};
+    ///
+    /// let directory = std::env::temp_dir().join("kvstore_secondary_example");
+    /// let database = KvStore::open(directory.join("primary")).unwrap();
+    /// database.put(&"height", &1_u64).unwrap();
+    ///
+    /// let secondary = KvStoreBuilder::default()
+    ///     .build_secondary(directory.join("primary"), directory.join("secondary"))
+    ///     .unwrap();
+    /// database.put(&"height", &2_u64).unwrap();
+    /// assert_eq!(secondary.get::<_, u64>(&"height").unwrap(), 1);
+    ///
+    /// secondary.try_catch_up_with_primary().unwrap();
+    /// assert_eq!(secondary.get::<_, u64>(&"height").unwrap(), 2);
+    /// ```
+    pub fn build_secondary(
+        mut self,
+        primary_path: impl AsRef<Path>,
+        secondary_path: impl AsRef<Path>,
+    ) -> Result<KvStore, KvStoreError> {
+        if !self.ingest.is_empty() {
+            return Err(KvStoreError::Unsupported("ingest"));
+        }
+        // Keeps the files deleted by the compactions of the primary readable.
+        self.database_options.set_max_open_files(-1);
+
+        let existing =
+            DB::list_cf(&self.database_options, &primary_path).map_err(KvStoreError::Open)?;
+        let (column_families, formats) = self.column_family_options(existing);
+
+        let descriptors = column_families
+            .into_iter()
+            .map(|(name, options)| ColumnFamilyDescriptor::new(name, options));
+        let database = DBWithThreadMode::open_cf_descriptors_as_secondary(
+            &self.database_options,
+            primary_path.as_ref(),
+            secondary_path.as_ref(),
+            descriptors,
+        )
+        .map_err(KvStoreError::Open)?;
+
+        Ok(self.into_kvstore(Database::ReadOnly(database), formats))
+    }
+
+    /// The options of the declared column families, followed by the
+    /// `existing` ones which are not declared and the default one, and the
+    /// value formats of the declared column families.
+    fn column_family_options(
+        &mut self,
+        existing: Vec<String>,
+    ) -> (Vec<(String, Options)>, Formats) {
+        let mut names: Vec<String> = Vec::new();
+        let mut column_families: Vec<(String, Options)> = Vec::new();
+        let mut formats = Formats::new();
+        for (name, options) in std::mem::take(&mut self.column_families) {
+            formats.insert(name.clone(), options.format);
+            column_families.push((name.clone(), options.options));
+            names.push(name);
+        }
+
+        // RocksDB refuses to open the database unless every existing column
+        // family is listed, so the undeclared ones are opened with the default
+        // options.
+        for name in existing
+            .into_iter()
+            .chain([DEFAULT_COLUMN_FAMILY_NAME.to_owned()])
+        {
+            if !names.contains(&name) {
+                let options = match name == DEFAULT_COLUMN_FAMILY_NAME {
+                    true => self.database_options.clone(),
+                    false => Options::default(),
+                };
+                column_families.push((name.clone(), options));
+                names.push(name);
+            }
+        }
+        for (_, options) in &mut column_families {
+            self.shared_options.apply(options);
+        }
+        if self.statistics {
+            // Shares the statistics with the database.
+            self.shared_options.statistics = Some(self.database_options.clone());
+        }
+
+        (column_families, formats)
+    }
+
+    fn into_kvstore(self, database: Database, formats: Formats) -> KvStore {
+        KvStore {
+            database: Arc::new(database),
+            formats: Arc::new(RwLock::new(formats)),
+            transaction_retry_limit: self.transaction_retry_limit,
+            blocking_pool: Arc::new(BlockingPool::new(self.blocking_threads)),
+            watchers: Arc::new(Watchers::default()),
+            codec: self.codec,
+            key_encoding: self.key_encoding,
+            #[cfg(feature = "rocksdb")]
+            shared_options: Arc::new(self.shared_options),
+            #[cfg(feature = "rocksdb")]
+            secondary: Arc::default(),
+        }
+    }
+}
+
+/// The value formats of the declared column families.
+type Formats = HashMap<String, ValueFormat>;
+
+pub struct KvStore {
+    database: Arc<Database>,
+    formats: Arc<RwLock<Formats>>,
+    transaction_retry_limit: usize,
+    blocking_pool: Arc<BlockingPool>,
+    watchers: Arc<Watchers>,
+    codec: Arc<dyn Codec>,
+    key_encoding: KeyEncoding,
+    #[cfg(feature = "rocksdb")]
+    shared_options: Arc<SharedOptions>,
+    /// The secondary instance reading the statistics of TransactionDB, which
+    /// is opened once and caught up with the primary on every read.
+    #[cfg(feature = "rocksdb")]
+    secondary: Arc<Mutex<Option<Secondary>>>,
+}
+
+unsafe impl Send for KvStore {}
+
+unsafe impl Sync for KvStore {}
+
+impl Clone for KvStore {
+    fn clone(&self) -> Self {
+        Self {
+            database: self.database.clone(),
+            formats: self.formats.clone(),
+            transaction_retry_limit: self.transaction_retry_limit,
+            blocking_pool: self.blocking_pool.clone(),
+            watchers: self.watchers.clone(),
+            codec: self.codec.clone(),
+            key_encoding: self.key_encoding,
+            #[cfg(feature = "rocksdb")]
+            shared_options: self.shared_options.clone(),
+            #[cfg(feature = "rocksdb")]
+            secondary: self.secondary.clone(),
+        }
+    }
+}
+
+impl KvStore {
+    /// Open the database with default options.
+    #[cfg(feature = "rocksdb")]
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, KvStoreError> {
+        let builder = KvStoreBuilder::default();
+
+        builder.build(path)
+    }
+
+    /// Open an empty database in memory with default options, which is lost
+    /// when it is dropped. See [`MemoryBackend`].
+    pub fn in_memory() -> Self {
+        KvStoreBuilder::default()
+            .build_with(MemoryBackend::default())
+            .expect("MemoryBackend creates any column family")
+    }
+
+    #[allow(static_mut_refs)]
+    pub fn init(self) {
+        unsafe {
+            INIT.call_once(|| {
+                KVSTORE.write(self);
+            });
+        }
+    }
+
+    /// Register the database under `name` so that it can be accessed with
+    /// [`kvstore_named()`] alongside the one registered with
+    /// [`KvStore::init()`]. Like [`KvStore::init()`], only the first
+    /// database registered under the same name is kept.
+    pub fn init_named(self, name: impl Into<String>) {
+        let mut registry = NAMED_KVSTORE
+            .get_or_init(|| RwLock::new(HashMap::new()))
+            .write()
+            .unwrap();
+
+        registry
+            .entry(name.into())
+            .or_insert_with(|| Box::leak(Box::new(self)));
+    }
+
+    /// Get the column family to put, get, delete and iterate the values
+    /// separately from the other column families.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::{ColumnFamilyOptions, KvStoreBuilder};
+    ///
+    /// let database = KvStoreBuilder::default()
+    ///     .column_family("block_commitment", ColumnFamilyOptions::default())
+    ///     .build(std::env::temp_dir().join("kvstore_column_family_example"))
+    ///     .unwrap();
+    ///
+    /// let block_commitment = database.column_family("block_commitment").unwrap();
+    /// block_commitment
+    ///     .put(&0_u64, &"commitment".to_owned())
+    ///     .unwrap();
+    ///
+    /// // Drop every block commitment at once.
+    /// database.drop_column_family("block_commitment").unwrap();
+    /// ```
+    pub fn column_family(&self, name: &str) -> Result<ColumnFamily, KvStoreError> {
+        let handle = self
+            .database
+            .cf_handle(name)
+            .ok_or_else(|| KvStoreError::ColumnFamily(name.to_owned()))?;
+
+        let format = self
+            .formats
+            .read()
+            .unwrap()
+            .get(name)
+            .copied()
+            .unwrap_or_default();
+
+        Ok(ColumnFamily {
+            database: &self.database,
+            handle,
+            format,
+            name: name.to_owned(),
+            watchers: &self.watchers,
+            codec: &self.codec,
+            key_encoding: self.key_encoding,
+        })
+    }
+
+    /// Create a column family which has not been declared with
+    /// [`KvStoreBuilder::column_family()`].
+    pub fn create_column_family(
+        &self,
+        name: &str,
+        #[cfg_attr(not(feature = "rocksdb"), allow(unused_mut))] mut options: ColumnFamilyOptions,
+    ) -> Result<(), KvStoreError> {
+        if self.database.is_read_only() {
+            return Err(KvStoreError::ReadOnly);
+        }
+
+        #[cfg(feature = "rocksdb")]
+        self.shared_options.apply(&mut options.options);
+        self.database
+            .create_cf(name, &options)
+            .map_err(KvStoreError::CreateColumnFamily)?;
+        self.formats
+            .write()
+            .unwrap()
+            .insert(name.to_owned(), options.format);
+
+        self.store_format()
+    }
+
+    /// Drop the column family and every value in it.
+    pub fn drop_column_family(&self, name: &str) -> Result<(), KvStoreError> {
+        if self.database.is_read_only() {
+            return Err(KvStoreError::ReadOnly);
+        }
+        if self.database.cf_handle(name).is_none() {
+            return Err(KvStoreError::ColumnFamily(name.to_owned()));
+        }
+
+        self.database
+            .drop_cf(name)
+            .map_err(KvStoreError::DropColumnFamily)?;
+        self.formats.write().unwrap().remove(name);
+
+        self.store_format()
+    }
+
+    pub fn put<K, V>(&self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put(key, value)
+    }
+
+    /// Put the value which expires `ttl` after now. After it expires, the
+    /// value is treated as deleted. The column family must have TTL enabled
+    /// with [`ColumnFamilyOptions::enable_ttl()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use kvstore::{ColumnFamilyOptions, KvStoreBuilder};
+    ///
+    /// let database = KvStoreBuilder::default()
+    ///     .column_family("default", ColumnFamilyOptions::default().enable_ttl())
+    ///     .build(std::env::temp_dir().join("kvstore_put_with_ttl_example"))
+    ///     .unwrap();
+    ///
+    /// database
+    ///     .put_with_ttl(&("Nonce", "0xa"), &1_u64, Duration::from_secs(60))
+    ///     .unwrap();
+    /// assert_eq!(database.get::<_, u64>(&("Nonce", "0xa")).unwrap(), 1);
+    /// ```
+    pub fn put_with_ttl<K, V>(&self, key: &K, value: &V, ttl: Duration) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put_with_ttl(key, value, ttl)
+    }
+
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.get(key)
+    }
+
+    /// Get the value with its [`Version`] to pass to
+    /// [`KvStore::compare_and_swap()`], or fail with
+    /// [`KvStoreError::VersioningDisabled`] unless the column family is
+    /// versioned. See [`ColumnFamilyOptions::enable_versioning()`].
+    pub fn get_with_version<K, V>(&self, key: &K) -> Result<(V, Version), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_with_version(key)
+    }
+
+    /// Put the value only if the current version is `expected`, or only if
+    /// there is no value when `expected` is `None`. Returns whether the value
+    /// is put. Unlike [`KvStore::get_mut()`], the key is not locked between
+    /// reading and writing the value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::{ColumnFamilyOptions, ConcurrencyMode, KvStoreBuilder};
+    ///
+    /// let database = KvStoreBuilder::default()
+    ///     .set_concurrency_mode(ConcurrencyMode::Optimistic)
+    ///     .column_family(
+    ///         "default",
+    ///         ColumnFamilyOptions::default().enable_versioning(),
+    ///     )
+    ///     .build(std::env::temp_dir().join("kvstore_compare_and_swap_example"))
+    ///     .unwrap();
+    /// # database.delete(&"height").unwrap();
+    /// assert!(database.compare_and_swap(&"height", None, &1_u64).unwrap());
+    ///
+    /// // Increment the height unless it is changed in the meantime.
+    /// loop {
+    ///     let (height, version) = database.get_with_version::<_, u64>(&"height").unwrap();
+    ///     if database
+    ///         .compare_and_swap(&"height", Some(version), &(height + 1))
+    ///         .unwrap()
+    ///     {
+    ///         break;
+    ///     }
+    /// }
+    /// assert_eq!(database.get::<_, u64>(&"height").unwrap(), 2);
+    /// ```
+    pub fn compare_and_swap<K, V>(
+        &self,
+        key: &K,
+        expected: Option<Version>,
+        value: &V,
+    ) -> Result<bool, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .compare_and_swap(key, expected, value)
+    }
+
+    pub fn get_or<K, V, F>(&self, key: &K, function: F) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_or(key, function)
+    }
+
+    pub fn get_or_default<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_or_default(key)
+    }
+
+    pub fn get_mut<K, V>(&self, key: &K) -> Result<Lock<V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.get_mut(key)
+    }
+
+    pub fn get_mut_or<K, V, F>(&self, key: &K, function: F) -> Result<Lock<V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_mut_or(key, function)
+    }
+
+    pub fn get_mut_or_default<K, V>(&self, key: &K) -> Result<Lock<V>, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_mut_or_default(key)
+    }
+
+    pub fn apply<K, V, F>(&self, key: &K, operation: F) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce(&mut Lock<V>),
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .apply(key, operation)
+    }
+
+    pub fn delete<K>(&self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.delete(key)
+    }
+
+    /// Run `function` in a transaction, which is committed if `function`
+    /// returns `Ok` and rolled back otherwise. If the transaction fails with
+    /// a retryable error (see [`KvStoreError::is_retryable()`]), it is rolled
+    /// back and `function` is called again up to the limit set with
+    /// [`KvStoreBuilder::set_transaction_retry_limit()`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::KvStore;
+    ///
+    /// let database = KvStore::open(std::env::temp_dir().join("kvstore_transaction_example")).unwrap();
+    /// database.put(&("Balance", "alice"), &100_u64).unwrap();
+    ///
+    /// // Move the balance to the other account at once.
+    /// database
+    ///     .transaction(|transaction| {
+    ///         let balance: u64 = transaction.get_for_update(&("Balance", "alice"))?;
+    ///         transaction.delete(&("Balance", "alice"))?;
+    ///         transaction.put(&("Balance", "bob"), &balance)
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn transaction<F, T>(&self, mut function: F) -> Result<T, KvStoreError>
+    where
+        F: FnMut(&Transaction) -> Result<T, KvStoreError>,
+    {
+        let mut retry = 0;
+        loop {
+            let transaction = Transaction::new(self)?;
+
+            // The transaction is rolled back when it is dropped without commit.
+            let result = function(&transaction).and_then(|output| {
+                let events = transaction.events.take();
+                let _order = self.watchers.order_pending(&events);
+                transaction
+                    .transaction
+                    .commit()
+                    .map_err(KvStoreError::CommitTransaction)?;
+                self.watchers.notify_pending(events);
+
+                Ok(output)
+            });
+
+            match result {
+                Err(error) if error.is_retryable() && retry < self.transaction_retry_limit => {
+                    retry += 1;
+                }
+                others => return others,
+            }
+        }
+    }
+
+    /// Run `function` to fill a [`WriteBatch`], which is applied atomically if
+    /// `function` returns `Ok` and discarded otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::KvStore;
+    ///
+    /// let database = KvStore::open(std::env::temp_dir().join("kvstore_write_batch_example")).unwrap();
+    ///
+    /// database
+    ///     .write_batch(|batch| {
+    ///         for index in 0..1000_u64 {
+    ///             batch.put(&("Transaction", index), &format!("transaction {index}"))?;
+    ///         }
+    ///         batch.delete(&("Transaction", 0_u64))
+    ///     })
+    ///     .unwrap();
+    /// assert!(database
+    ///     .get::<_, String>(&("Transaction", 0_u64))
+    ///     .unwrap_err()
+    ///     .is_none_type());
+    /// ```
+    pub fn write_batch<F>(&self, function: F) -> Result<(), KvStoreError>
+    where
+        F: FnOnce(&mut WriteBatch) -> Result<(), KvStoreError>,
+    {
+        let mut batch = WriteBatch {
+            batch: self.database.write_batch()?,
+            kvstore: self,
+            events: Vec::new(),
+        };
+        function(&mut batch)?;
+
+        let _order = self.watchers.order_pending(&batch.events);
+        self.database
+            .write(batch.batch)
+            .map_err(KvStoreError::lock(KvStoreError::WriteBatch))?;
+        self.watchers.notify_pending(batch.events);
+
+        Ok(())
+    }
+
+    /// Create a checkpoint of the current state of the database in `path`,
+    /// which can be opened as another database. The files are hard-linked
+    /// instead of copied if `path` is on the same file system.
+    ///
+    /// TransactionDB cannot create checkpoints, so
+    /// [`ConcurrencyMode::Optimistic`] is required to create checkpoints while
+    /// the database is running, and [`ConcurrencyMode::Pessimistic`] fails
+    /// with [`KvStoreError::Unsupported`]. A stopped database can be
+    /// checkpointed with [`KvStoreBuilder::build_read_only()`].
+    #[cfg(feature = "rocksdb")]
+    pub fn create_checkpoint(&self, path: impl AsRef<Path>) -> Result<(), KvStoreError> {
+        match self.database.as_ref() {
+            Database::Optimistic(database) => Checkpoint::new(database),
+            Database::ReadOnly(database) => Checkpoint::new(database),
+            _others => return Err(KvStoreError::Unsupported("create_checkpoint")),
+        }
+        .and_then(|checkpoint| checkpoint.create_checkpoint(path))
+        .map_err(KvStoreError::CreateCheckpoint)
+    }
+
+    /// Whether the database is opened with
+    /// [`KvStoreBuilder::build_read_only()`] or
+    /// [`KvStoreBuilder::build_secondary()`], so that the writes fail with
+    /// [`KvStoreError::ReadOnly`].
+    pub fn is_read_only(&self) -> bool {
+        self.database.is_read_only()
+    }
+
+    /// Apply the writes of the primary since the secondary instance was
+    /// opened or last caught up. See [`KvStoreBuilder::build_secondary()`].
+    #[cfg(feature = "rocksdb")]
+    pub fn try_catch_up_with_primary(&self) -> Result<(), KvStoreError> {
+        match self.database.as_ref() {
+            Database::ReadOnly(database) => database
+                .try_catch_up_with_primary()
+                .map_err(KvStoreError::CatchUpWithPrimary),
+            _others => Err(KvStoreError::Unsupported("try_catch_up_with_primary")),
+        }
+    }
+
+    /// Read the statistics of the database. With
+    /// [`ConcurrencyMode::Pessimistic`], the properties are read from a
+    /// secondary instance caught up with the database, which has its own
+    /// memtables and compactions. See [`Statistics`].
+    #[cfg(feature = "rocksdb")]
+    pub fn statistics(&self) -> Result<Statistics, KvStoreError> {
+        statistics::collect(self)
+    }
+
+    /// Take a [`Snapshot`] to read a consistent state of the database.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            snapshot: self.database.snapshot(),
+            kvstore: self,
+        }
+    }
+
+    /// Get the async version of the database, which runs the operations on
+    /// dedicated threads instead of blocking the async runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::KvStore;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// KvStore::open(std::env::temp_dir().join("kvstore_asynchronous_example"))
+    ///     .unwrap()
+    ///     .init();
+    /// let database = kvstore::kvstore().unwrap().asynchronous();
+    ///
+    /// database.put("count", 0_u64).await.unwrap();
+    /// let mut count = database.get_mut::<_, u64>("count").await.unwrap();
+    /// *count += 1;
+    /// count.update().await.unwrap();
+    /// assert_eq!(database.get::<_, u64>("count").await.unwrap(), 1);
+    /// # }
+    /// ```
+    pub fn asynchronous(&'static self) -> AsyncKvStore {
+        AsyncKvStore::new(self)
+    }
+
+    /// Iterate over every key-value pair in the order of the keys.
+    pub fn iter<K, V>(&self) -> Result<Iter<K, V>, KvStoreError>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.iter())
+    }
+
+    /// Iterate over the key-value pairs whose keys start with `prefix`. Keys
+    /// are tuples, so the prefix is a tuple of the leading key components.
+    /// Requires [`KeyEncoding::Ordered`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use kvstore::{KeyEncoding, KvStoreBuilder};
+    ///
+    /// let database = KvStoreBuilder::default()
+    ///     .set_key_encoding(KeyEncoding::Ordered)
+    ///     .build(std::env::temp_dir().join("kvstore_iter_prefix_example"))
+    ///     .unwrap();
+    /// database
+    ///     .put(&("Rollup", "cluster", 0_u64), &"first".to_owned())
+    ///     .unwrap();
+    /// database
+    ///     .put(&("Rollup", "cluster", 1_u64), &"second".to_owned())
+    ///     .unwrap();
+    ///
+    /// // Every rollup of the cluster, the last one first.
+    /// for rollup in database
+    ///     .iter_prefix::<_, (String, String, u64), String>(&("Rollup", "cluster"))
+    ///     .unwrap()
+    ///     .reverse()
+    /// {
+    ///     println!("{:?}", rollup.unwrap());
+    /// }
+    /// ```
+    pub fn iter_prefix<P, K, V>(&self, prefix: &P) -> Result<Iter<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .iter_prefix(prefix)
+    }
+
+    /// Iterate over the key-value pairs whose keys are in `range`, e.g. the
+    /// blocks between two heights with `("Block", 10)..("Block", 20)`.
+    /// Requires [`KeyEncoding::Ordered`].
+    pub fn iter_range<R, P, K, V>(&self, range: R) -> Result<Iter<K, V>, KvStoreError>
+    where
+        R: RangeBounds<P>,
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .iter_range(range)
+    }
+
+    /// Subscribe to the changes of `key` made after the subscription. `K` is
+    /// the owned type of the key returned with the events. See [`Watch`].
+    pub fn watch<P, K, V>(&self, key: &P) -> Result<Watch<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.watch(key)
+    }
+
+    /// Subscribe to the changes of the keys starting with `prefix` made
+    /// after the subscription. Requires [`KeyEncoding::Ordered`]. See
+    /// [`Watch`].
+    pub fn watch_prefix<P, K, V>(&self, prefix: &P) -> Result<Watch<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .watch_prefix(prefix)
+    }
+
+    /// Rewrite the values of the keys starting with `prefix` which are stored
+    /// with an older version of `V`. See [`ColumnFamily::migrate_prefix()`].
+    pub fn migrate_prefix<P, V>(&self, prefix: &P) -> Result<usize, KvStoreError>
+    where
+        P: Debug + Serialize,
+        V: Schema,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .migrate_prefix::<_, V>(prefix)
+    }
+
+    /// Rewrite the values of the keys of type `K` for which `filter` returns
+    /// true. See [`ColumnFamily::migrate_where()`].
+    pub fn migrate_where<P, K, V>(
+        &self,
+        prefix: &P,
+        filter: impl Fn(&K) -> bool,
+    ) -> Result<usize, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Schema,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .migrate_where::<_, K, V>(prefix, filter)
+    }
+}
+
+/// Value locked by [`KvStore::get_mut()`] until it is put with
+/// [`Lock::update()`] or discarded with [`Lock::rollback()`]. Dropping the
+/// lock discards the changes like [`Lock::rollback()`], but without the error
+/// of the rollback, and fails a debug assertion if the value has changed.
+pub struct Lock<'db, V>
+where
+    V: Debug + Serialize + DeserializeOwned,
+{
+    transaction: Option<RawTransaction<'db>>,
+    column_family: ColumnFamily<'db>,
+    key_vec: Vec<u8>,
+    value: V,
+    // The serialized value when it is read, to tell whether it has changed.
+    value_vec: Vec<u8>,
+    // Kept on update, e.g. for the values put with TTL.
+    expiration: Option<u64>,
+}
+
+impl<V> std::ops::Deref for Lock<'_, V>
+where
+    V: Debug + Serialize + DeserializeOwned,
+{
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<V> std::ops::DerefMut for Lock<'_, V>
+where
+    V: Debug + Serialize + DeserializeOwned,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'db, V> Lock<'db, V>
+where
+    V: Debug + Serialize + DeserializeOwned,
+{
+    fn new(
+        transaction: RawTransaction<'db>,
+        column_family: ColumnFamily<'db>,
+        key_vec: Vec<u8>,
+        value_vec: Vec<u8>,
+        value: V,
+    ) -> Self {
+        Self {
+            transaction: Some(transaction),
+            column_family,
+            key_vec,
+            value,
+            value_vec,
+            expiration: None,
+        }
+    }
+
+    pub(super) fn with_expiration(mut self, expiration: Option<u64>) -> Self {
+        self.expiration = expiration;
+
+        self
+    }
+
+    pub fn update(mut self) -> Result<(), KvStoreError> {
+        let value_vec = self.column_family.serialize_value(&self.value)?;
+
+        self.commit(value_vec)
+    }
+
+    /// Put the value only if it has changed since it is read, and release the
+    /// lock either way. Returns whether the value is put.
+    pub fn update_if_changed(mut self) -> Result<bool, KvStoreError> {
+        let value_vec = self.column_family.serialize_value(&self.value)?;
+
+        match value_vec == self.value_vec {
+            true => self.rollback().map(|_| false),
+            false => self.commit(value_vec).map(|_| true),
+        }
+    }
+
+    /// Discard the changes to the value and release the lock.
+    pub fn rollback(mut self) -> Result<(), KvStoreError> {
+        if let Some(transaction) = self.transaction.take() {
+            transaction
+                .rollback()
+                .map_err(KvStoreError::RollbackTransaction)?;
+        }
+
+        Ok(())
+    }
+
+    fn commit(&mut self, value_vec: Vec<u8>) -> Result<(), KvStoreError> {
+        if let Some(transaction) = self.transaction.take() {
+            let value_vec = self
+                .column_family
+                .encode_with_expiration(value_vec, self.expiration);
+
+            transaction
+                .put_cf(&self.column_family.handle, &self.key_vec, &value_vec)
+                .map_err(KvStoreError::lock(KvStoreError::Update))?;
+            let _order = self.column_family.order(&self.key_vec);
+            transaction.commit().map_err(KvStoreError::CommitUpdate)?;
+            self.column_family.notify(&self.key_vec, Some(&value_vec));
+        }
+
+        Ok(())
+    }
+}
+
+impl<V> Drop for Lock<'_, V>
+where
+    V: Debug + Serialize + DeserializeOwned,
+{
+    fn drop(&mut self) {
+        // Dropping the lock discards the changes, which is most likely a bug
+        // unless it is done explicitly with `rollback()`.
+        if cfg!(debug_assertions) && self.transaction.is_some() && !std::thread::panicking() {
+            let is_changed = self
+                .column_family
+                .serialize_value(&self.value)
+                .map(|value_vec| value_vec != self.value_vec)
+                .unwrap_or(false);
+            debug_assert!(
+                !is_changed,
+                "Lock dropped with the changed value {:?}. Call update() or rollback().",
+                self.value
+            );
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum KvStoreError {
+    #[cfg(feature = "rocksdb")]
+    Open(rocksdb::Error),
+    /// Write to the database opened with
+    /// [`KvStoreBuilder::build_read_only()`] or
+    /// [`KvStoreBuilder::build_secondary()`].
+    ReadOnly,
+    #[cfg(feature = "rocksdb")]
+    CatchUpWithPrimary(rocksdb::Error),
+    DataType(crate::data_type::DataTypeError),
+    Key(crate::data_type::KeyError),
+    UnknownVersion {
+        type_name: &'static str,
+        version: u32,
+    },
+    /// The database is written with the codec and the key encoding of its
+    /// [`StoredFormat`], not with the ones of [`KvStoreBuilder`].
+    FormatMismatch {
+        codec: String,
+        key_encoding: KeyEncoding,
+    },
+    Get(StorageError),
+    GetMut(StorageError),
+    Put(StorageError),
+    CommitPut(StorageError),
+    Delete(StorageError),
+    CommitDelete(StorageError),
+    Update(StorageError),
+    CommitUpdate(StorageError),
+    Iterate(StorageError),
+    ColumnFamily(String),
+    CreateColumnFamily(StorageError),
+    DropColumnFamily(StorageError),
+    CommitTransaction(StorageError),
+    RollbackTransaction(StorageError),
+    LockTimeout(StorageError),
+    Deadlock(StorageError),
+    Blocking,
+    WriteBatch(StorageError),
+    #[cfg(feature = "rocksdb")]
+    WriteSstFile(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    IngestSstFile(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    OpenSecondary(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    ReadProperty(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    CreateCheckpoint(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    OpenBackupEngine(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    CreateBackup(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    PurgeBackup(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    VerifyBackup(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    RestoreBackup(rocksdb::Error),
+    #[cfg(feature = "rocksdb")]
+    RestorePathNotEmpty(PathBuf),
+    Unsupported(&'static str),
+    TtlDisabled,
+    VersioningDisabled,
+    NoneType,
+    Initialize,
+    InitializeNamed(String),
+}
+
+impl std::fmt::Display for KvStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}",)
+    }
+}
+
+impl std::error::Error for KvStoreError {}
+
+impl From<crate::data_type::DataTypeError> for KvStoreError {
+    fn from(value: crate::data_type::DataTypeError) -> Self {
+        Self::DataType(value)
+    }
+}
+
+impl From<crate::data_type::KeyError> for KvStoreError {
+    fn from(value: crate::data_type::KeyError) -> Self {
+        Self::Key(value)
+    }
+}
+
+impl KvStoreError {
+    /// Whether the operation failed because of a conflict with another
+    /// transaction and may succeed if it is tried again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Get(error)
+            | Self::GetMut(error)
+            | Self::Put(error)
+            | Self::CommitPut(error)
+            | Self::Delete(error)
+            | Self::CommitDelete(error)
+            | Self::Update(error)
+            | Self::CommitUpdate(error)
+            | Self::CommitTransaction(error)
+            | Self::WriteBatch(error) => {
+                matches!(error.kind(), ErrorKind::Busy | ErrorKind::TryAgain)
+            }
+            Self::Deadlock(_) => true,
+            _others => false,
+        }
+    }
+
+    /// Map the error of an operation locking the key to `variant` unless the
+    /// lock cannot be acquired.
+    pub(super) fn lock(variant: fn(StorageError) -> Self) -> impl FnOnce(StorageError) -> Self {
+        move |error| match error.kind() {
+            ErrorKind::TimedOut => Self::LockTimeout(error),
+            ErrorKind::Busy if error.as_ref().contains(DEADLOCK_MESSAGE) => Self::Deadlock(error),
+            _others => variant(error),
+        }
+    }
+
+    pub fn is_none_type(&self) -> bool {
+        match self {
+            Self::NoneType => true,
+            _others => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rocksdb"))]
+mod tests {
+    use std::ops::Bound;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::Model;
+
+    fn database_path(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("kvstore_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+
+        path
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_ledger")]
+    #[kvstore(key(id: u64))]
+    struct Entry {
+        amount: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_column_family")]
+    #[kvstore(column_family = "block")]
+    #[kvstore(key(height: u64))]
+    struct Block {
+        hash: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_iter")]
+    #[kvstore(key(cluster_id: &str, rollup_id: u64))]
+    struct Rollup {
+        rollup_id: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_transaction")]
+    #[kvstore(key(owner: &str))]
+    struct Account {
+        balance: u64,
+    }
+
+    #[derive(Debug, Default, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_async")]
+    #[kvstore(key(name: &str))]
+    struct Counter {
+        count: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_write_batch")]
+    #[kvstore(key(height: u64, index: u32))]
+    struct Transfer {
+        amount: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_snapshot")]
+    #[kvstore(key(rollup_id: &str, index: u32))]
+    struct Executor {
+        address: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_ttl")]
+    #[kvstore(column_family = "nonce")]
+    #[kvstore(ttl = "1s")]
+    #[kvstore(key(address: &str))]
+    struct Nonce {
+        nonce: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_watch")]
+    #[kvstore(key(id: u64))]
+    struct Order {
+        status: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(version = 1)]
+    struct ProfileV1 {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(version = 2, migrate_from = ProfileV1)]
+    struct ProfileV2 {
+        name: String,
+        age: u32,
+    }
+
+    impl From<ProfileV1> for ProfileV2 {
+        fn from(profile: ProfileV1) -> Self {
+            Self {
+                name: profile.name,
+                age: 0,
+            }
+        }
+    }
+
+    // Only accessed with `KvStore` directly.
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(version = 2)]
+    struct Setting {
+        value: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_migrate_codec_keys")]
+    #[kvstore(version = 1)]
+    #[kvstore(key(id: u64))]
+    struct Badge {
+        label: String,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_schema")]
+    #[kvstore(version = 3, migrate_from = ProfileV2)]
+    #[kvstore(key(id: u64))]
+    struct Profile {
+        name: String,
+        age: u32,
+        verified: bool,
+    }
+
+    impl From<ProfileV2> for Profile {
+        fn from(profile: ProfileV2) -> Self {
+            Self {
+                name: profile.name,
+                age: profile.age,
+                verified: false,
+            }
+        }
+    }
+
+    /// JSON prefixed with the tag.
+    struct TaggedJson(&'static [u8]);
+
+    impl Codec for TaggedJson {
+        fn serialize(
+            &self,
+            value: &dyn erased_serde::Serialize,
+        ) -> Result<Vec<u8>, crate::CodecError> {
+            let mut value_vec = self.0.to_vec();
+            erased_serde::serialize(value, &mut serde_json::Serializer::new(&mut value_vec))?;
+
+            Ok(value_vec)
+        }
+
+        fn deserialize<'de>(
+            &self,
+            slice: &'de [u8],
+            visit: &mut crate::Visit<'_, 'de>,
+        ) -> Result<(), crate::CodecError> {
+            let slice = slice.strip_prefix(self.0).ok_or("Unknown tag")?;
+            let mut deserializer = serde_json::Deserializer::from_slice(slice);
+            visit(&mut <dyn erased_serde::Deserializer>::erase(
+                &mut deserializer,
+            ))?;
+
+            Ok(deserializer.end()?)
+        }
+    }
+
+    const RECEIPT_CODEC: TaggedJson = TaggedJson(b"receipt:");
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_codec")]
+    #[kvstore(codec = RECEIPT_CODEC)]
+    #[kvstore(key(id: u64))]
+    struct Receipt {
+        amount: u64,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+    #[kvstore(path = crate)]
+    #[kvstore(database = "test_memory")]
+    #[kvstore(column_family = "ticket")]
+    #[kvstore(key(event: &str, seat: u32))]
+    struct Ticket {
+        holder: String,
+    }
+
+    #[test]
+    fn test_named() {
+        KvStore::open(database_path("test_ledger"))
+            .unwrap()
+            .init_named("test_ledger");
+        KvStore::open(database_path("test_cache"))
+            .unwrap()
+            .init_named("test_cache");
+
+        let ledger = kvstore_named("test_ledger").unwrap();
+        let cache = kvstore_named("test_cache").unwrap();
+        ledger.put(&"key", &1_u64).unwrap();
+        assert!(cache.get::<_, u64>(&"key").unwrap_err().is_none_type());
+
+        Entry { amount: 10 }.put(0).unwrap();
+        assert_eq!(Entry::get(0).unwrap(), Entry { amount: 10 });
+        assert_eq!(
+            ledger.get::<_, Entry>(&(Entry::ID, 0_u64)).unwrap(),
+            Entry { amount: 10 }
+        );
+
+        assert!(matches!(
+            kvstore_named("unknown"),
+            Err(KvStoreError::InitializeNamed(_))
+        ));
+    }
+
+    #[test]
+    fn test_column_family() {
+        let path = database_path("column_family");
+        let build = || {
+            KvStoreBuilder::default()
+                .column_family(
+                    "block",
+                    ColumnFamilyOptions::default()
+                        .set_compression_type(crate::DBCompressionType::Snappy),
+                )
+                .column_family(
+                    "expiring",
+                    ColumnFamilyOptions::default().set_ttl(Duration::from_secs(1)),
+                )
+                .build(&path)
+                .unwrap()
+        };
+
+        {
+            let kvstore = build();
+            kvstore.put(&0_u64, &"default".to_owned()).unwrap();
+            let block = kvstore.column_family("block").unwrap();
+            block.put(&0_u64, &"block".to_owned()).unwrap();
+            block.put(&1_u64, &"block".to_owned()).unwrap();
+            assert_eq!(kvstore.get::<_, String>(&0_u64).unwrap(), "default");
+            assert_eq!(block.get::<_, String>(&0_u64).unwrap(), "block");
+
+            block.delete(&1_u64).unwrap();
+            let entries: Vec<(u64, String)> = block.iter().collect::<Result<_, _>>().unwrap();
+            assert_eq!(entries, vec![(0, "block".to_owned())]);
+
+            kvstore
+                .create_column_family("created", ColumnFamilyOptions::default())
+                .unwrap();
+            kvstore
+                .column_family("created")
+                .unwrap()
+                .put(&0_u64, &"created".to_owned())
+                .unwrap();
+        }
+
+        // Undeclared column families are opened as well.
+        let kvstore = build();
+        let created = kvstore.column_family("created").unwrap();
+        assert_eq!(created.get::<_, String>(&0_u64).unwrap(), "created");
+        drop(created);
+        kvstore.drop_column_family("created").unwrap();
+        assert!(matches!(
+            kvstore.column_family("created"),
+            Err(KvStoreError::ColumnFamily(_))
+        ));
+
+        let expiring = kvstore.column_family("expiring").unwrap();
+        expiring.put(&0_u64, &"expiring".to_owned()).unwrap();
+        assert_eq!(expiring.get::<_, String>(&0_u64).unwrap(), "expiring");
+        std::thread::sleep(Duration::from_millis(2100));
+        assert!(expiring
+            .get::<_, String>(&0_u64)
+            .unwrap_err()
+            .is_none_type());
+        assert_eq!(expiring.iter::<u64, String>().count(), 0);
+        drop(expiring);
+
+        kvstore.init_named("test_column_family");
+        Block {
+            hash: "hash".to_owned(),
+        }
+        .put(1)
+        .unwrap();
+        let block = kvstore_named("test_column_family")
+            .unwrap()
+            .column_family("block")
+            .unwrap();
+        assert_eq!(
+            block.get::<_, Block>(&(Block::ID, 1_u64)).unwrap(),
+            Block::get(1).unwrap()
+        );
+        assert!(kvstore_named("test_column_family")
+            .unwrap()
+            .get::<_, Block>(&(Block::ID, 1_u64))
+            .unwrap_err()
+            .is_none_type());
+    }
+
+    #[test]
+    fn test_iter() {
+        let kvstore = KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(database_path("iter"))
+            .unwrap();
+        for height in [1_u64, 9, 10, 11, 256] {
+            kvstore.put(&("Block", height), &height).unwrap();
+        }
+        kvstore.put(&("Blocks", 0_u64), &0_u64).unwrap();
+
+        let heights = |iter: Iter<(String, u64), u64>| -> Vec<u64> {
+            iter.map(|key_value| key_value.unwrap().1).collect()
+        };
+        assert_eq!(
+            heights(kvstore.iter_prefix(&("Block",)).unwrap()),
+            vec![1, 9, 10, 11, 256]
+        );
+        assert_eq!(
+            heights(kvstore.iter_prefix(&("Block",)).unwrap().reverse()),
+            vec![256, 11, 10, 9, 1]
+        );
+        assert_eq!(
+            heights(
+                kvstore
+                    .iter_range(("Block", 9_u64)..("Block", 11_u64))
+                    .unwrap()
+            ),
+            vec![9, 10]
+        );
+        assert_eq!(
+            heights(
+                kvstore
+                    .iter_range(("Block", 9_u64)..=("Block", 11_u64))
+                    .unwrap()
+                    .reverse()
+            ),
+            vec![11, 10, 9]
+        );
+        assert_eq!(
+            heights(
+                kvstore
+                    .iter_range((
+                        Bound::Excluded(("Block", 9_u64)),
+                        Bound::Excluded(("Block", 256_u64))
+                    ))
+                    .unwrap()
+            ),
+            vec![10, 11]
+        );
+        assert_eq!(kvstore.iter::<(String, u64), u64>().unwrap().count(), 6);
+
+        kvstore.init_named("test_iter");
+        for (cluster_id, rollup_id) in [("a", 2_u64), ("a", 10), ("b", 1)] {
+            Rollup { rollup_id }.put(cluster_id, rollup_id).unwrap();
+        }
+        let rollups: Vec<(String, String, u64)> = Rollup::iter_by("a")
+            .unwrap()
+            .map(|key_value| key_value.unwrap().0)
+            .collect();
+        assert_eq!(
+            rollups,
+            vec![
+                ("Rollup".to_owned(), "a".to_owned(), 2),
+                ("Rollup".to_owned(), "a".to_owned(), 10)
+            ]
+        );
+        assert_eq!(Rollup::iter_all().unwrap().count(), 3);
+
+        // The keys serialized with the codec do not sort by their components.
+        let codec_keys = KvStore::open(database_path("iter_codec")).unwrap();
+        codec_keys.put(&("Block", 1_u64), &1_u64).unwrap();
+        assert_eq!(heights(codec_keys.iter().unwrap()), vec![1]);
+        assert!(matches!(
+            codec_keys.iter_prefix::<_, (String, u64), u64>(&("Block",)),
+            Err(KvStoreError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_transaction() {
+        KvStore::open(database_path("test_transaction"))
+            .unwrap()
+            .init_named("test_transaction");
+        let database = kvstore_named("test_transaction").unwrap();
+
+        Account { balance: 100 }.put("alice").unwrap();
+        Account { balance: 0 }.put("bob").unwrap();
+        let transfer = |amount: u64| {
+            database.transaction(|transaction| {
+                let mut alice = Account::get_for_update_in(transaction, "alice")?;
+                let mut bob = Account::get_for_update_in(transaction, "bob")?;
+                alice.balance -= amount;
+                bob.balance += amount;
+                alice.put_in(transaction, "alice")?;
+                bob.put_in(transaction, "bob")?;
+                match Account::get_in(transaction, "alice")?.balance {
+                    0 => Err(KvStoreError::NoneType),
+                    _others => Ok(()),
+                }
+            })
+        };
+
+        // Committed at once.
+        transfer(40).unwrap();
+        assert_eq!(Account::get("alice").unwrap().balance, 60);
+        assert_eq!(Account::get("bob").unwrap().balance, 40);
+
+        // Rolled back on `Err`.
+        assert!(transfer(60).unwrap_err().is_none_type());
+        assert_eq!(Account::get("alice").unwrap().balance, 60);
+        assert_eq!(Account::get("bob").unwrap().balance, 40);
+
+        // Rolled back to the savepoint.
+        database
+            .transaction(|transaction| {
+                transaction.put(&"kept", &1_u64)?;
+                transaction.set_savepoint();
+                transaction.put(&"discarded", &2_u64)?;
+                Account::delete_in(transaction, "bob")?;
+                transaction.rollback_to_savepoint()
+            })
+            .unwrap();
+        assert_eq!(database.get::<_, u64>(&"kept").unwrap(), 1);
+        assert!(database
+            .get::<_, u64>(&"discarded")
+            .unwrap_err()
+            .is_none_type());
+        assert_eq!(Account::get("bob").unwrap().balance, 40);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_async() {
+        KvStoreBuilder::default()
+            .set_blocking_threads(2)
+            .build(database_path("test_async"))
+            .unwrap()
+            .init_named("test_async");
+
+        Counter { count: 1 }.put_async("a").await.unwrap();
+        assert_eq!(Counter::get_async("a").await.unwrap().count, 1);
+        assert!(Counter::get_async("b").await.unwrap_err().is_none_type());
+        assert_eq!(
+            Counter::get_or_async("b", Counter::default).await.unwrap(),
+            Counter::default()
+        );
+
+        // The key stays locked until the lock is updated.
+        let mut counter = Counter::get_mut_async("a").await.unwrap();
+        let handle = tokio::spawn(async {
+            Counter::apply_async("a", |counter| counter.count *= 10)
+                .await
+                .unwrap();
+        });
+        counter.count += 1;
+        counter.update().await.unwrap();
+        handle.await.unwrap();
+        assert_eq!(Counter::get_async("a").await.unwrap().count, 20);
+
+        // Rolling back the lock leaves the value unchanged.
+        let mut counter = Counter::get_mut_or_async("b", Counter::default)
+            .await
+            .unwrap();
+        counter.count = 100;
+        counter.rollback().await.unwrap();
+        assert_eq!(Counter::get_async("b").await.unwrap().count, 0);
+
+        // Dropping the lock releases the key.
+        drop(Counter::get_mut_async("b").await.unwrap());
+        Counter::apply_async("b", |counter| counter.count += 1)
+            .await
+            .unwrap();
+        assert_eq!(Counter::get_async("b").await.unwrap().count, 1);
+
+        Counter::delete_async("a").await.unwrap();
+        assert!(Counter::get_async("a").await.unwrap_err().is_none_type());
+    }
+
+    #[test]
+    fn test_write_batch() {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(database_path("test_write_batch"))
+            .unwrap()
+            .init_named("test_write_batch");
+        let database = kvstore_named("test_write_batch").unwrap();
+
+        database
+            .write_batch(|batch| {
+                for index in 0..100 {
+                    Transfer { amount: 1 }.put_in_batch(batch, 0, index)?;
+                }
+                Transfer::delete_in_batch(batch, 0, 99)?;
+                assert_eq!(batch.len(), 101);
+
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(Transfer::iter_by(0).unwrap().count(), 99);
+
+        // Discarded on `Err`.
+        let result = database.write_batch(|batch| {
+            Transfer { amount: 2 }.put_in_batch(batch, 1, 0)?;
+            Err(KvStoreError::NoneType)
+        });
+        assert!(result.unwrap_err().is_none_type());
+        assert!(Transfer::get(1, 0).unwrap_err().is_none_type());
+
+        let directory = database_path("ingest");
+        std::fs::create_dir_all(&directory).unwrap();
+        let sst_path = directory.join("block.sst");
+        let options = ColumnFamilyOptions::default();
+        let mut writer = SstWriter::create(&sst_path, &options)
+            .unwrap()
+            .set_key_encoding(KeyEncoding::Ordered);
+        for height in 0..10_u64 {
+            writer.put(&("Block", height), &height).unwrap();
+        }
+        writer.finish().unwrap();
+
+        // Out of order keys are rejected.
+        let mut writer = SstWriter::create(directory.join("invalid.sst"), &options).unwrap();
+        writer.put(&("Block", 1_u64), &1_u64).unwrap();
+        assert!(matches!(
+            writer.put(&("Block", 0_u64), &0_u64),
+            Err(KvStoreError::WriteSstFile(_))
+        ));
+
+        let path = directory.join("database");
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(&path)
+            .unwrap()
+            .put(&("Block", 0_u64), &100_u64)
+            .unwrap();
+        let database = KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .ingest(DEFAULT_COLUMN_FAMILY_NAME, [&sst_path])
+            .build(&path)
+            .unwrap();
+        let blocks: Vec<((String, u64), u64)> = database
+            .iter_prefix(&("Block",))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(blocks.len(), 10);
+        assert_eq!(blocks[0], (("Block".to_owned(), 0), 0));
+    }
+
+    #[test]
+    fn test_snapshot() {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(database_path("test_snapshot"))
+            .unwrap()
+            .init_named("test_snapshot");
+        let database = kvstore_named("test_snapshot").unwrap();
+
+        let executor = |address: &str| Executor {
+            address: address.to_owned(),
+        };
+        executor("0xa").put("rollup", 0).unwrap();
+        executor("0xb").put("rollup", 1).unwrap();
+        database.put(&("Info", "rollup"), &2_u32).unwrap();
+
+        let snapshot = database.snapshot();
+        database
+            .write_batch(|batch| {
+                executor("0xc").put_in_batch(batch, "rollup", 2)?;
+                Executor::delete_in_batch(batch, "rollup", 0)?;
+                batch.put(&("Info", "rollup"), &2_u32)
+            })
+            .unwrap();
+
+        assert_eq!(snapshot.get::<_, u32>(&("Info", "rollup")).unwrap(), 2);
+        assert_eq!(
+            Executor::get_at(&snapshot, "rollup", 0).unwrap(),
+            executor("0xa")
+        );
+        assert!(Executor::get_at(&snapshot, "rollup", 2)
+            .unwrap_err()
+            .is_none_type());
+        let addresses: Vec<String> = Executor::iter_by_at(&snapshot, "rollup")
+            .unwrap()
+            .map(|key_value| key_value.unwrap().1.address)
+            .collect();
+        assert_eq!(addresses, ["0xa", "0xb"]);
+        assert_eq!(Executor::iter_all_at(&snapshot).unwrap().count(), 2);
+
+        let addresses: Vec<String> = Executor::iter_by("rollup")
+            .unwrap()
+            .map(|key_value| key_value.unwrap().1.address)
+            .collect();
+        assert_eq!(addresses, ["0xb", "0xc"]);
+    }
+
+    #[test]
+    fn test_backup() {
+        let directory = database_path("backup");
+        let build = |path: PathBuf| {
+            KvStoreBuilder::default()
+                .set_concurrency_mode(ConcurrencyMode::Optimistic)
+                .column_family("block", ColumnFamilyOptions::default())
+                .build(path)
+                .unwrap()
+        };
+        let database = build(directory.join("database"));
+        database.put(&"height", &1_u64).unwrap();
+        let block = database.column_family("block").unwrap();
+        block.put(&1_u64, &"0x1".to_owned()).unwrap();
+
+        database
+            .create_checkpoint(directory.join("checkpoint"))
+            .unwrap();
+        let checkpoint = build(directory.join("checkpoint"));
+        assert_eq!(checkpoint.get::<_, u64>(&"height").unwrap(), 1);
+        let checkpoint_block = checkpoint.column_family("block").unwrap();
+        assert_eq!(checkpoint_block.get::<_, String>(&1_u64).unwrap(), "0x1");
+
+        let mut backup_engine = BackupEngine::open(directory.join("backup"))
+            .unwrap()
+            .set_max_backups(2);
+        for height in 2..5_u64 {
+            database.put(&"height", &height).unwrap();
+            backup_engine.create_backup(&database).unwrap();
+        }
+        let backups = backup_engine.backups();
+        assert_eq!(backups.len(), 2);
+        backup_engine.verify_backup(backups[0].backup_id).unwrap();
+
+        backup_engine
+            .restore_backup(backups[0].backup_id, directory.join("restored"))
+            .unwrap();
+        let restored = build(directory.join("restored"));
+        assert_eq!(restored.get::<_, u64>(&"height").unwrap(), 3);
+        let restored_block = restored.column_family("block").unwrap();
+        assert_eq!(restored_block.get::<_, String>(&1_u64).unwrap(), "0x1");
+
+        assert!(matches!(
+            backup_engine.restore_latest_backup(directory.join("restored")),
+            Err(KvStoreError::RestorePathNotEmpty(_))
+        ));
+        backup_engine
+            .restore_latest_backup(directory.join("latest"))
+            .unwrap();
+        assert_eq!(
+            build(directory.join("latest"))
+                .get::<_, u64>(&"height")
+                .unwrap(),
+            4
+        );
+
+        // TransactionDB cannot keep the files while they are copied.
+        let path = directory.join("pessimistic");
+        let pessimistic = KvStoreBuilder::default()
+            .set_concurrency_mode(ConcurrencyMode::Pessimistic)
+            .build(path.join("database"))
+            .unwrap();
+        assert!(matches!(
+            pessimistic.create_checkpoint(path.join("checkpoint")),
+            Err(KvStoreError::Unsupported(_))
+        ));
+        assert!(matches!(
+            BackupEngine::open(path.join("backup"))
+                .unwrap()
+                .create_backup(&pessimistic),
+            Err(KvStoreError::Unsupported(_))
+        ));
+
+        let read_only = KvStoreBuilder::default()
+            .build_read_only(directory.join("database"))
+            .unwrap();
+        read_only
+            .create_checkpoint(directory.join("read_only_checkpoint"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_ttl() {
+        KvStoreBuilder::default()
+            .column_family("nonce", ColumnFamilyOptions::default().enable_ttl())
+            .build(database_path("test_ttl"))
+            .unwrap()
+            .init_named("test_ttl");
+        let database = kvstore_named("test_ttl").unwrap();
+        let nonce = database.column_family("nonce").unwrap();
+
+        assert!(matches!(
+            database.put_with_ttl(&"key", &0_u64, Duration::from_secs(1)),
+            Err(KvStoreError::TtlDisabled)
+        ));
+
+        Nonce { nonce: 1 }.put("0xa").unwrap();
+        nonce
+            .put_with_ttl(&"updated", &0_u64, Duration::from_secs(1))
+            .unwrap();
+        nonce
+            .put_with_ttl(&"long", &0_u64, Duration::from_secs(3600))
+            .unwrap();
+        nonce.put(&"forever", &0_u64).unwrap();
+        assert_eq!(Nonce::get("0xa").unwrap(), Nonce { nonce: 1 });
+
+        // Updating a value keeps its expiration time.
+        nonce
+            .apply(&"updated", |value: &mut Lock<u64>| **value += 1)
+            .unwrap();
+        assert_eq!(nonce.get::<_, u64>(&"updated").unwrap(), 1);
+
+        std::thread::sleep(Duration::from_millis(2100));
+        assert!(Nonce::get("0xa").unwrap_err().is_none_type());
+        assert!(nonce.get::<_, u64>(&"updated").unwrap_err().is_none_type());
+        assert_eq!(nonce.get::<_, u64>(&"long").unwrap(), 0);
+        assert_eq!(nonce.get::<_, u64>(&"forever").unwrap(), 0);
+
+        // The values put before TTL is enabled never expire.
+        let path = database_path("test_ttl_enabled");
+        KvStore::open(&path).unwrap().put(&"short", &1_u8).unwrap();
+        let database = KvStoreBuilder::default()
+            .column_family("default", ColumnFamilyOptions::default().enable_ttl())
+            .build(&path)
+            .unwrap();
+        assert_eq!(database.get::<_, u8>(&"short").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_watch() {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(database_path("test_watch"))
+            .unwrap()
+            .init_named("test_watch");
+        let database = kvstore_named("test_watch").unwrap();
+        let order = |status: &str| Order {
+            status: status.to_owned(),
+        };
+        let put = |id: u64, status: &str| WatchEvent::Put {
+            key: (Order::ID.to_owned(), id),
+            value: order(status),
+        };
+
+        let mut first = Order::watch(0).unwrap();
+        let mut all = Order::watch_all().unwrap();
+        database.put(&"unrelated", &0_u64).unwrap();
+
+        order("created").put(0).unwrap();
+        Order::apply(0, |order| order.status = "paid".to_owned()).unwrap();
+        order("created").put(1).unwrap();
+        Order::delete(0).unwrap();
+
+        // Only the committed writes are sent.
+        database
+            .transaction(|transaction| {
+                order("kept").put_in(transaction, 2)?;
+                transaction.set_savepoint();
+                order("rolled back").put_in(transaction, 3)?;
+                transaction.rollback_to_savepoint()?;
+                order("committed").put_in(transaction, 4)
+            })
+            .unwrap();
+        let _ = database.write_batch(|batch| {
+            order("discarded").put_in_batch(batch, 5)?;
+            Err(KvStoreError::NoneType)
+        });
+        database
+            .write_batch(|batch| Order::delete_in_batch(batch, 1))
+            .unwrap();
+
+        let events: Vec<_> = std::iter::from_fn(|| first.try_recv())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                put(0, "created"),
+                put(0, "paid"),
+                WatchEvent::Delete {
+                    key: (Order::ID.to_owned(), 0)
+                },
+            ]
+        );
+
+        let events: Vec<_> = std::iter::from_fn(|| all.try_recv())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 7);
+        assert_eq!(events[4], put(2, "kept"));
+        assert_eq!(events[5], put(4, "committed"));
+        assert_eq!(
+            events[6],
+            WatchEvent::Delete {
+                key: (Order::ID.to_owned(), 1)
+            }
+        );
+
+        // Dropped subscriptions are removed at once.
+        drop(first);
+        drop(all);
+        assert!(!database.watchers.is_watching(
+            DEFAULT_COLUMN_FAMILY_NAME,
+            &crate::data_type::serialize_key(&(Order::ID, 0_u64)).unwrap()
+        ));
+
+        // Concurrent updates are sent in the order of the commits.
+        database.put(&"counter", &0_u64).unwrap();
+        let mut counter = database.watch::<_, String, u64>(&"counter").unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..50 {
+                        database
+                            .apply(&"counter", |count: &mut Lock<u64>| **count += 1)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        for count in 1..=200_u64 {
+            assert_eq!(
+                counter.try_recv().unwrap().unwrap(),
+                WatchEvent::Put {
+                    key: "counter".to_owned(),
+                    value: count
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn test_optimistic() {
+        let database = KvStoreBuilder::default()
+            .set_concurrency_mode(ConcurrencyMode::Optimistic)
+            .set_transaction_retry_limit(usize::MAX)
+            .column_family(
+                "default",
+                ColumnFamilyOptions::default().enable_versioning(),
+            )
+            .column_family("unversioned", ColumnFamilyOptions::default())
+            .build(database_path("test_optimistic"))
+            .unwrap();
+
+        assert!(database.compare_and_swap(&"count", None, &0_u64).unwrap());
+        assert!(!database.compare_and_swap(&"count", None, &0_u64).unwrap());
+
+        let (count, version) = database.get_with_version::<_, u64>(&"count").unwrap();
+        assert_eq!(count, 0);
+        assert!(database
+            .compare_and_swap(&"count", Some(version), &1_u64)
+            .unwrap());
+        assert!(!database
+            .compare_and_swap(&"count", Some(version), &2_u64)
+            .unwrap());
+        assert_eq!(database.get::<_, u64>(&"count").unwrap(), 1);
+
+        // The version changes even if the same value is put back.
+        let (_, version) = database.get_with_version::<_, u64>(&"count").unwrap();
+        database.put(&"count", &2_u64).unwrap();
+        database.put(&"count", &1_u64).unwrap();
+        assert!(!database
+            .compare_and_swap(&"count", Some(version), &2_u64)
+            .unwrap());
+        assert!(matches!(
+            database
+                .column_family("unversioned")
+                .unwrap()
+                .compare_and_swap(&"count", None, &0_u64),
+            Err(KvStoreError::VersioningDisabled)
+        ));
+
+        // The keys are not locked, so the later update of a changed value
+        // fails instead of waiting.
+        let mut first = database.get_mut::<_, u64>(&"count").unwrap();
+        let mut second = database.get_mut::<_, u64>(&"count").unwrap();
+        *first += 1;
+        first.update().unwrap();
+        *second += 1;
+        assert!(second.update().unwrap_err().is_retryable());
+        assert_eq!(database.get::<_, u64>(&"count").unwrap(), 2);
+
+        // The conflicting transactions are retried.
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        database
+                            .transaction(|transaction| {
+                                let count: u64 = transaction.get_for_update(&"count")?;
+                                transaction.put(&"count", &(count + 1))
+                            })
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(database.get::<_, u64>(&"count").unwrap(), 42);
+        // The keys are estimated from every put.
+        let statistics = database.statistics().unwrap();
+        assert!(statistics.column_families[0].estimated_keys >= 1);
+        assert!(statistics.column_families[0].memtable_size.unwrap() > 0);
+    }
+
+    #[test]
+    fn test_lock() {
+        let database = KvStoreBuilder::default()
+            .set_txn_lock_timeout(100)
+            .set_deadlock_detect(true)
+            .set_transaction_retry_limit(0)
+            .build(database_path("test_lock"))
+            .unwrap();
+        database.put(&"a", &0_u64).unwrap();
+        database.put(&"b", &0_u64).unwrap();
+
+        let lock = database.get_mut::<_, u64>(&"a").unwrap();
+        assert!(!lock.update_if_changed().unwrap());
+        let mut lock = database.get_mut::<_, u64>(&"a").unwrap();
+        *lock += 1;
+        assert!(lock.update_if_changed().unwrap());
+        let mut lock = database.get_mut::<_, u64>(&"a").unwrap();
+        *lock += 1;
+        lock.rollback().unwrap();
+        assert_eq!(database.get::<_, u64>(&"a").unwrap(), 1);
+
+        let lock = database.get_mut::<_, u64>(&"a").unwrap();
+        assert!(matches!(
+            database.get_mut::<_, u64>(&"a"),
+            Err(KvStoreError::LockTimeout(_))
+        ));
+        lock.rollback().unwrap();
+
+        // One of the transactions locking the keys in the opposite order
+        // fails instead of waiting for the other one.
+        let barrier = std::sync::Barrier::new(2);
+        let results: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = [("a", "b"), ("b", "a")]
+                .map(|(first, second)| {
+                    let (database, barrier) = (&database, &barrier);
+                    scope.spawn(move || {
+                        database.transaction(|transaction| {
+                            transaction.get_for_update::<_, u64>(&first)?;
+                            barrier.wait();
+                            transaction.get_for_update::<_, u64>(&second)?;
+                            transaction.put(&second, &1_u64)
+                        })
+                    })
+                })
+                .into_iter()
+                .collect();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        let deadlocks: Vec<_> = results
+            .iter()
+            .filter_map(|result| match result {
+                Err(KvStoreError::Deadlock(error)) => Some(error),
+                _others => None,
+            })
+            .collect();
+        assert_eq!(deadlocks.len(), 1);
+        // Fails if RocksDB changes the message of the status.
+        assert_eq!(deadlocks[0].kind(), ErrorKind::Busy);
+        assert!(deadlocks[0].as_ref().ends_with(DEADLOCK_MESSAGE));
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+
+        // Dropping a changed lock is detected in debug builds.
+        let mut lock = database.get_mut::<_, u64>(&"a").unwrap();
+        *lock += 1;
+        let dropped = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| drop(lock)));
+        assert_eq!(dropped.is_err(), cfg!(debug_assertions));
+        assert_eq!(database.get::<_, u64>(&"a").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_schema() {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .build(database_path("test_schema"))
+            .unwrap()
+            .init_named("test_schema");
+        let database = kvstore_named("test_schema").unwrap();
+        let profile = |name: &str, age, verified| Profile {
+            name: name.to_owned(),
+            age,
+            verified,
+        };
+
+        // Stored before the versioning, then with the version 2.
+        let name = "before".to_owned();
+        database
+            .put(&(Profile::ID, 0_u64), &ProfileV1 { name })
+            .unwrap();
+        register_schema::<ProfileV2>();
+        let name = "version 2".to_owned();
+        database
+            .put(&(Profile::ID, 1_u64), &ProfileV2 { name, age: 30 })
+            .unwrap();
+        profile("current", 40, true).put(2).unwrap();
+
+        assert_eq!(Profile::get(0).unwrap(), profile("before", 0, false));
+        assert_eq!(Profile::get(1).unwrap(), profile("version 2", 30, false));
+        assert_eq!(Profile::get(2).unwrap(), profile("current", 40, true));
+
+        // A migrated value is not changed until it is modified.
+        let lock = Profile::get_mut(0).unwrap();
+        assert!(!lock.update_if_changed().unwrap());
+        let mut lock = Profile::get_mut(0).unwrap();
+        lock.verified = true;
+        lock.update().unwrap();
+
+        assert_eq!(Profile::migrate_all().unwrap(), 1);
+        assert_eq!(Profile::migrate_all().unwrap(), 0);
+        assert_eq!(Profile::get(1).unwrap(), profile("version 2", 30, false));
+        assert_eq!(Profile::get(0).unwrap(), profile("before", 0, true));
+    }
+
+    #[test]
+    fn test_migrate_codec_keys() {
+        KvStoreBuilder::default()
+            .build(database_path("test_migrate_codec_keys"))
+            .unwrap()
+            .init_named("test_migrate_codec_keys");
+        let database = kvstore_named("test_migrate_codec_keys").unwrap();
+        let unversioned = serde_json::json!({ "label": "gold" });
+
+        // Stored before the versioning, next to other keys of other types.
+        database.put(&(Badge::ID, 0_u64), &unversioned).unwrap();
+        database.put(&("Other", 0_u64), &unversioned).unwrap();
+        database.put(&"height", &unversioned).unwrap();
+
+        assert_eq!(Badge::migrate_all().unwrap(), 1);
+        assert_eq!(Badge::migrate_all().unwrap(), 0);
+        let label = "gold".to_owned();
+        assert_eq!(Badge::get(0).unwrap(), Badge { label });
+        assert_eq!(
+            database
+                .get::<_, serde_json::Value>(&("Other", 0_u64))
+                .unwrap(),
+            unversioned
+        );
+    }
+
+    #[test]
+    fn test_codec() {
+        KvStoreBuilder::default()
+            .set_codec(TaggedJson(b"database:"))
+            .build(database_path("test_codec"))
+            .unwrap()
+            .init_named("test_codec");
+        let database = kvstore_named("test_codec").unwrap();
+
+        database.put(&"height", &1_u64).unwrap();
+        assert_eq!(database.get::<_, u64>(&"height").unwrap(), 1);
+
+        // The model keeps its own codec.
+        Receipt { amount: 2 }.put(0).unwrap();
+        assert_eq!(Receipt::get(0).unwrap(), Receipt { amount: 2 });
+        assert!(matches!(
+            database.get::<_, u64>(&(Receipt::ID, 0_u64)),
+            Err(KvStoreError::DataType(_))
+        ));
+    }
+
+    #[test]
+    fn test_memory() {
+        KvStoreBuilder::default()
+            .set_key_encoding(KeyEncoding::Ordered)
+            .column_family("ticket", ColumnFamilyOptions::default())
+            .build_with(MemoryBackend::default().set_lock_timeout(Duration::from_millis(100)))
+            .unwrap()
+            .init_named("test_memory");
+        let database = kvstore_named("test_memory").unwrap();
+        let ticket = |holder: &str| Ticket {
+            holder: holder.to_owned(),
+        };
+
+        for seat in 0..3 {
+            ticket("alice").put("concert", seat).unwrap();
+        }
+        ticket("bob").put("play", 0).unwrap();
+        let snapshot = database.snapshot();
+
+        let mut lock = Ticket::get_mut("concert", 1).unwrap();
+        assert!(matches!(
+            Ticket::get_mut("concert", 1),
+            Err(KvStoreError::LockTimeout(_))
+        ));
+        assert!(matches!(
+            database.write_batch(|batch| ticket("carol").put_in_batch(batch, "concert", 1)),
+            Err(KvStoreError::LockTimeout(_))
+        ));
+        lock.holder = "bob".to_owned();
+        lock.update().unwrap();
+
+        database
+            .transaction(|transaction| {
+                let ticket = transaction.column_family("ticket")?;
+                ticket.delete(&(Ticket::ID, "concert", 2_u32))?;
+                transaction.set_savepoint();
+                ticket.delete(&(Ticket::ID, "concert", 0_u32))?;
+                transaction.rollback_to_savepoint()
+            })
+            .unwrap();
+
+        let holders = |iter: Iter<(String, String, u32), Ticket>| -> Vec<String> {
+            iter.map(|key_value| key_value.unwrap().1.holder).collect()
+        };
+        assert_eq!(
+            holders(Ticket::iter_by("concert").unwrap()),
+            vec!["alice", "bob"]
+        );
+        assert_eq!(
+            holders(Ticket::iter_all_at(&snapshot).unwrap().reverse()),
+            vec!["bob", "alice", "alice", "alice"]
+        );
+        assert!(matches!(
+            database.create_checkpoint(database_path("test_memory")),
+            Err(KvStoreError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_schema_registered_at_load() {
+        let database = KvStoreBuilder::default()
+            .build(database_path("test_schema_registered_at_load"))
+            .unwrap();
+        let setting = Setting { value: 1 };
+        database.put(&(Setting::ID, 0_u64), &setting).unwrap();
+
+        let key_vec =
+            crate::data_type::serialize(database.codec.as_ref(), &(Setting::ID, 0_u64)).unwrap();
+        let handle = database
+            .database
+            .cf_handle(DEFAULT_COLUMN_FAMILY_NAME)
+            .unwrap();
+        let value_vec = database
+            .database
+            .get_pinned_cf(&handle, key_vec)
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        assert_eq!(
+            schema::split_schema_version(&value_vec).map(|(version, _)| version),
+            Some(2)
+        );
+        assert_eq!(
+            database.get::<_, Setting>(&(Setting::ID, 0_u64)).unwrap(),
+            setting
+        );
+    }
+
+    #[test]
+    fn test_stored_format() {
+        let path = database_path("test_stored_format");
+        let builder = || {
+            KvStoreBuilder::default()
+                .set_key_encoding(KeyEncoding::Ordered)
+                .column_family(
+                    "session",
+                    ColumnFamilyOptions::default()
+                        .set_ttl(Duration::from_secs(60))
+                        .enable_versioning(),
+                )
+        };
+        let key = ("Session", 1_u64);
+        let version = {
+            let database = builder().build(&path).unwrap();
+            database
+                .create_column_family("block", ColumnFamilyOptions::default())
+                .unwrap();
+            let session = database.column_family("session").unwrap();
+            session.put(&key, &"alice".to_owned()).unwrap();
+            session.get_with_version::<_, String>(&key).unwrap().1
+        };
+
+        // The column families which are not declared keep their format.
+        let database = builder().build(&path).unwrap();
+        database.drop_column_family("block").unwrap();
+        let read = |name: &str, key: &[u8]| {
+            let handle = database.database.cf_handle(name).unwrap();
+            database
+                .database
+                .get_pinned_cf(&handle, key)
+                .unwrap()
+                .unwrap()
+                .to_vec()
+        };
+        let format =
+            StoredFormat::from_slice(&read(StoredFormat::COLUMN_FAMILY, StoredFormat::KEY))
+                .unwrap();
+        assert_eq!(format.codec, "json");
+        assert_eq!(format.key_encoding, KeyEncoding::Ordered);
+        let session_format = ColumnFamilyFormat {
+            expiring: true,
+            versioned: true,
+        };
+        assert_eq!(
+            format.column_families.into_iter().collect::<Vec<_>>(),
+            [("session".to_owned(), session_format)]
+        );
+
+        let value_vec = read("session", &crate::serialize_key(&key).unwrap());
+        let value = session_format.decode(&value_vec);
+        assert!(value
+            .expiration
+            .is_some_and(|expiration| expiration < u64::MAX));
+        assert_eq!(value.version, Some(version));
+        assert_eq!(value.schema_version, None);
+        assert_eq!(value.payload, b"\"alice\"");
+        assert_eq!(session_format.encode(&value), value_vec);
+        drop(database);
+
+        // The database is not opened with another key encoding.
+        assert!(matches!(
+            KvStoreBuilder::default().build(&path),
+            Err(KvStoreError::FormatMismatch {
+                key_encoding: KeyEncoding::Ordered,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_statistics() {
+        let database = KvStoreBuilder::default()
+            .enable_statistics()
+            .set_block_cache_size(1 << 20)
+            .set_bloom_filter(10.0)
+            .column_family("block", ColumnFamilyOptions::default())
+            .build(database_path("test_statistics"))
+            .unwrap();
+        for height in 0..100_u64 {
+            database.put(&("Height", height), &height).unwrap();
+        }
+        database.get::<_, u64>(&("Height", 0_u64)).unwrap();
+
+        let statistics = database.statistics().unwrap();
+        let names: Vec<&str> = statistics
+            .column_families
+            .iter()
+            .map(|column_family| column_family.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["default", "block"]);
+        assert_eq!(statistics.column_families[0].estimated_keys, 100);
+        assert_eq!(statistics.column_families[0].memtable_size, None);
+        assert!(statistics.block_cache_usage.is_some());
+        // The stored format is read when the database is opened.
+        assert_eq!(statistics.get_latency.as_ref().unwrap().count, 2);
+
+        let text = statistics.to_prometheus();
+        assert!(text.contains("kvstore_estimated_keys{column_family=\"default\"} 100\n"));
+        assert!(text.contains("kvstore_get_latency_microseconds_count 2\n"));
+        assert!(!text.contains("kvstore_memtable_bytes"));
+
+        for height in 100..200_u64 {
+            database.put(&("Height", height), &height).unwrap();
+        }
+        let statistics = database.statistics().unwrap();
+        assert_eq!(statistics.column_families[0].estimated_keys, 200);
+
+        assert!(matches!(
+            KvStore::in_memory().statistics(),
+            Err(KvStoreError::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_only() {
+        let path = database_path("test_read_only");
+        let database = KvStoreBuilder::default()
+            .column_family("block", ColumnFamilyOptions::default())
+            .build(&path)
+            .unwrap();
+        database.put(&("Height", 0_u64), &0_u64).unwrap();
+
+        let read_only = KvStoreBuilder::default().build_read_only(&path).unwrap();
+        assert!(read_only.is_read_only());
+        assert_eq!(read_only.get::<_, u64>(&("Height", 0_u64)).unwrap(), 0);
+        assert_eq!(read_only.iter::<(String, u64), u64>().unwrap().count(), 1);
+        assert!(matches!(
+            read_only.put(&("Height", 1_u64), &1_u64),
+            Err(KvStoreError::ReadOnly)
+        ));
+        assert!(matches!(
+            read_only.get_mut::<_, u64>(&("Height", 0_u64)),
+            Err(KvStoreError::ReadOnly)
+        ));
+        assert!(matches!(
+            read_only.transaction(|transaction| transaction.delete(&("Height", 0_u64))),
+            Err(KvStoreError::ReadOnly)
+        ));
+        assert!(matches!(
+            read_only.write_batch(|batch| batch.delete(&("Height", 0_u64))),
+            Err(KvStoreError::ReadOnly)
+        ));
+        assert!(matches!(
+            read_only.drop_column_family("block"),
+            Err(KvStoreError::ReadOnly)
+        ));
+        assert!(matches!(
+            read_only.try_catch_up_with_primary(),
+            Err(KvStoreError::CatchUpWithPrimary(_))
+        ));
+
+        let secondary = KvStoreBuilder::default()
+            .build_secondary(&path, database_path("test_read_only_secondary"))
+            .unwrap();
+        database.put(&("Height", 1_u64), &1_u64).unwrap();
+        database
+            .column_family("block")
+            .unwrap()
+            .put(&0_u64, &"genesis".to_owned())
+            .unwrap();
+        assert!(secondary
+            .get::<_, u64>(&("Height", 1_u64))
+            .unwrap_err()
+            .is_none_type());
+
+        secondary.try_catch_up_with_primary().unwrap();
+        assert_eq!(secondary.get::<_, u64>(&("Height", 1_u64)).unwrap(), 1);
+        assert_eq!(
+            secondary
+                .column_family("block")
+                .unwrap()
+                .get::<_, String>(&0_u64)
+                .unwrap(),
+            "genesis"
+        );
+        assert!(secondary.statistics().unwrap().column_families[0].estimated_keys >= 1);
+        assert!(matches!(
+            database.try_catch_up_with_primary(),
+            Err(KvStoreError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    any::{self, TypeId},
+    collections::HashMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock, RwLock,
+    },
+};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::KvStoreError;
+use crate::data_type::{deserialize, serialize, Codec};
+
+/// Version of the values of a model, implemented by `#[derive(Model)]` with
+/// `#[kvstore(version = 2, migrate_from = ModelV1)]`. The values are stored
+/// with the version, and the values of the older versions are migrated with
+/// `From<Previous>` when they are read.
+///
+/// Models must be registered with [`register_schema()`] before their values
+/// are accessed. `#[derive(Model)]` registers them when the program is loaded,
+/// so that the values accessed with [`KvStore`](super::KvStore) directly are
+/// versioned too, and on the targets without constructors such as WebAssembly
+/// when a generated method is first called.
+///
+/// The versioned values start with a 4-byte tag, which no JSON or CBOR value
+/// starts with. A bincode value stored before its type is versioned can start
+/// with the tag, e.g. a struct whose first field is a `u32` of `0x76766bff`,
+/// and is then misread as a versioned value, so the values of a bincode model
+/// are best versioned from the start.
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{KeyEncoding, KvStoreBuilder, Model};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, Serialize, Model)]
+/// #[kvstore(path = kvstore)]
+/// #[kvstore(version = 1)]
+/// struct AccountV1 {
+///     balance: u64,
+/// }
+///
+/// #[derive(Debug, Deserialize, PartialEq, Serialize, Model)]
+/// #[kvstore(path = kvstore)]
+/// #[kvstore(version = 2, migrate_from = AccountV1)]
+/// #[kvstore(key(owner: &str))]
+/// struct Account {
+///     balance: u64,
+///     nonce: u64,
+/// }
+///
+/// impl From<AccountV1> for Account {
+///     fn from(account: AccountV1) -> Self {
+///         Self {
+///             balance: account.balance,
+///             nonce: 0,
+///         }
+///     }
+/// }
+///
+/// let database = KvStoreBuilder::default()
+///     .set_key_encoding(KeyEncoding::Ordered)
+///     .build(std::env::temp_dir().join("kvstore_schema_example"))
+///     .unwrap();
+/// // Stored with the first version.
+/// database
+///     .put(&(Account::ID, "alice"), &AccountV1 { balance: 10 })
+///     .unwrap();
+/// database.init();
+///
+/// assert_eq!(
+///     Account::get("alice").unwrap(),
+///     Account {
+///         balance: 10,
+///         nonce: 0
+///     }
+/// );
+/// assert_eq!(Account::migrate_all().unwrap(), 1);
+/// ```
+pub trait Schema: Debug + DeserializeOwned + Serialize {
+    /// The version of the values, which must be greater than 0.
+    const VERSION: u32;
+
+    /// The model of the previous version to migrate from, or `Self` for the
+    /// first version. The values stored without version are read as the first
+    /// version.
+    type Previous: Schema + Into<Self>;
+}
+
+/// Store the values of `T` with its version and migrate the values of the
+/// older versions when they are read. Registering the same model again has no
+/// effect.
+pub fn register_schema<T>()
+where
+    T: Schema,
+{
+    register::<T>(|registration| {
+        registration.schema.get_or_insert(SchemaRegistration {
+            version: T::VERSION,
+            migrate: |codec, version, payload| {
+                Ok(serialize(codec, &migrate::<T>(codec, version, payload)?)?)
+            },
+        });
+    });
+}
+
+/// Serialize the values of `T` with `codec` instead of the codec of the
+/// database, which the methods generated by `#[derive(Model)]` with
+/// `#[kvstore(codec = ...)]` do.
+pub fn register_codec<T>(codec: &'static dyn Codec) {
+    register::<T>(|registration| {
+        registration.codec.get_or_insert(codec);
+    });
+}
+
+/// Serialize the value with the codec of the type or `codec`, with the
+/// version if the type is versioned.
+pub(super) fn serialize_value<T>(codec: &dyn Codec, value: &T) -> Result<Vec<u8>, KvStoreError>
+where
+    T: Debug + Serialize,
+{
+    let registration = registration::<T>();
+    let codec = registration.codec.unwrap_or(codec);
+    let payload = serialize(codec, value)?;
+
+    match registration.schema {
+        Some(SchemaRegistration { version, .. }) => Ok(prepend_schema_version(version, &payload)),
+        None => Ok(payload),
+    }
+}
+
+/// Deserialize the value with the codec of the type or `codec`, migrating it
+/// first if the type is versioned and the value is of an older version.
+pub(super) fn deserialize_value<T>(codec: &dyn Codec, value_slice: &[u8]) -> Result<T, KvStoreError>
+where
+    T: Debug + DeserializeOwned,
+{
+    let registration = registration::<T>();
+    let codec = registration.codec.unwrap_or(codec);
+
+    match registration.schema {
+        Some(SchemaRegistration { version, migrate }) => {
+            let (stored_version, payload) = split_version(value_slice);
+            match stored_version == version {
+                true => Ok(deserialize(codec, payload)?),
+                false => Ok(deserialize(
+                    codec,
+                    migrate(codec, stored_version, payload)?,
+                )?),
+            }
+        }
+        None => Ok(deserialize(codec, value_slice)?),
+    }
+}
+
+/// Whether the value is of the current version of the registered type `T`.
+pub(super) fn is_current<T>(value_slice: &[u8]) -> bool
+where
+    T: Schema,
+{
+    split_version(value_slice).0 == T::VERSION
+}
+
+/// Deserialize the payload stored with `version` as `T`, migrating it through
+/// the previous versions.
+fn migrate<T>(codec: &dyn Codec, version: u32, payload: &[u8]) -> Result<T, KvStoreError>
+where
+    T: Schema,
+{
+    let is_first = T::Previous::VERSION == T::VERSION;
+
+    if version == T::VERSION || (version == UNVERSIONED && is_first) {
+        Ok(deserialize(codec, payload)?)
+    } else if version > T::VERSION || is_first {
+        Err(KvStoreError::UnknownVersion {
+            type_name: any::type_name::<T>(),
+            version,
+        })
+    } else {
+        migrate::<T::Previous>(codec, version, payload).map(Into::into)
+    }
+}
+
+/// Prefix of the values stored with the version, which is followed by the
+/// version in big-endian. Neither JSON nor CBOR values start with `0xff`, but
+/// the unversioned bincode values can, e.g. a struct starting with the `u32`
+/// `0x76766bff`. See [`Schema`].
+const MAGIC: [u8; 4] = [0xff, b'k', b'v', b'v'];
+
+const VERSION_LENGTH: usize = std::mem::size_of::<u32>();
+
+const HEADER_LENGTH: usize = MAGIC.len() + VERSION_LENGTH;
+
+/// The version of the values stored before the versioning.
+const UNVERSIONED: u32 = 0;
+
+fn split_version(value_slice: &[u8]) -> (u32, &[u8]) {
+    split_schema_version(value_slice).unwrap_or((UNVERSIONED, value_slice))
+}
+
+/// Split the value into the version of its schema and the payload if it has
+/// one.
+pub(super) fn split_schema_version(value_slice: &[u8]) -> Option<(u32, &[u8])> {
+    let rest = value_slice.strip_prefix(&MAGIC)?;
+    let (version, payload) = rest.split_first_chunk::<VERSION_LENGTH>()?;
+
+    Some((u32::from_be_bytes(*version), payload))
+}
+
+pub(super) fn prepend_schema_version(version: u32, payload: &[u8]) -> Vec<u8> {
+    let mut value_vec = Vec::with_capacity(HEADER_LENGTH + payload.len());
+    value_vec.extend_from_slice(&MAGIC);
+    value_vec.extend_from_slice(&version.to_be_bytes());
+    value_vec.extend_from_slice(payload);
+
+    value_vec
+}
+
+/// How the values of a type are stored, registered with
+/// [`register_schema()`] and [`register_codec()`].
+#[derive(Clone, Copy, Default)]
+struct Registration {
+    schema: Option<SchemaRegistration>,
+    codec: Option<&'static dyn Codec>,
+}
+
+#[derive(Clone, Copy)]
+struct SchemaRegistration {
+    version: u32,
+    migrate: Migrate,
+}
+
+/// Migrate the payload of the version and serialize it as the current one.
+type Migrate = fn(&dyn Codec, u32, &[u8]) -> Result<Vec<u8>, KvStoreError>;
+
+// Skips the lookup until any type is registered.
+static IS_REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// The registrations by the type with its lifetimes erased, since the values
+/// may borrow.
+fn registry() -> &'static RwLock<HashMap<TypeId, Registration>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<TypeId, Registration>>> = OnceLock::new();
+
+    REGISTRY.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn register<T>(update: impl FnOnce(&mut Registration)) {
+    update(
+        registry()
+            .write()
+            .unwrap()
+            .entry(typeid::of::<T>())
+            .or_default(),
+    );
+    IS_REGISTERED.store(true, Ordering::Release);
+}
+
+fn registration<T>() -> Registration {
+    if !IS_REGISTERED.load(Ordering::Acquire) {
+        return Registration::default();
+    }
+
+    registry()
+        .read()
+        .unwrap()
+        .get(&typeid::of::<T>())
+        .copied()
+        .unwrap_or_default()
+}
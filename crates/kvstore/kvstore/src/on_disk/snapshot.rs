@@ -0,0 +1,180 @@
+use std::{fmt::Debug, ops::RangeBounds};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    database::RawSnapshot,
+    iter::{all_bounds, prefix_bounds, range_bounds},
+    ColumnFamily, Iter, KvStore, KvStoreError, DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+/// Read-only view of the database at the time it is taken with
+/// [`KvStore::snapshot()`](super::KvStore::snapshot). Every read sees the
+/// same state regardless of the writes after the snapshot.
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::KvStore;
+///
+/// let database = KvStore::open(std::env::temp_dir().join("kvstore_snapshot_example")).unwrap();
+/// database.put(&("Rollup", "info"), &1_u64).unwrap();
+///
+/// let snapshot = database.snapshot();
+/// database.put(&("Rollup", "info"), &2_u64).unwrap();
+///
+/// assert_eq!(snapshot.get::<_, u64>(&("Rollup", "info")).unwrap(), 1);
+/// assert_eq!(database.get::<_, u64>(&("Rollup", "info")).unwrap(), 2);
+/// ```
+pub struct Snapshot<'db> {
+    pub(super) snapshot: RawSnapshot<'db>,
+    pub(super) kvstore: &'db KvStore,
+}
+
+impl Snapshot<'_> {
+    /// Get the column family to read at the snapshot.
+    pub fn column_family(&self, name: &str) -> Result<SnapshotColumnFamily, KvStoreError> {
+        Ok(SnapshotColumnFamily {
+            snapshot: &self.snapshot,
+            column_family: self.kvstore.column_family(name)?,
+        })
+    }
+
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.get(key)
+    }
+
+    pub fn get_or<K, V, F>(&self, key: &K, function: F) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_or(key, function)
+    }
+
+    pub fn get_or_default<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.get_or(key, V::default)
+    }
+
+    pub fn iter<K, V>(&self) -> Result<Iter<K, V>, KvStoreError>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.iter())
+    }
+
+    pub fn iter_prefix<P, K, V>(&self, prefix: &P) -> Result<Iter<K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .iter_prefix(prefix)
+    }
+
+    pub fn iter_range<R, P, K, V>(&self, range: R) -> Result<Iter<K, V>, KvStoreError>
+    where
+        R: RangeBounds<P>,
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .iter_range(range)
+    }
+}
+
+/// Column family of [`Snapshot`] returned by [`Snapshot::column_family()`].
+pub struct SnapshotColumnFamily<'s, 'db> {
+    snapshot: &'s RawSnapshot<'db>,
+    column_family: ColumnFamily<'db>,
+}
+
+impl<'s> SnapshotColumnFamily<'s, '_> {
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+
+        let value_slice = self
+            .snapshot
+            .get_pinned_cf(&self.column_family.handle, key_vec)
+            .map_err(KvStoreError::Get)?;
+        let value_slice = value_slice
+            .as_deref()
+            .and_then(|value_slice| self.column_family.decode(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.column_family.deserialize_value(value_slice)?;
+
+        Ok(value)
+    }
+
+    pub fn get_or<K, V, F>(&self, key: &K, function: F) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+        F: FnOnce() -> V,
+    {
+        match self.get(key) {
+            Err(KvStoreError::NoneType) => Ok(function()),
+            others => others,
+        }
+    }
+
+    pub fn get_or_default<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + Default + DeserializeOwned + Serialize,
+    {
+        self.get_or(key, V::default)
+    }
+
+    pub fn iter<K, V>(&self) -> Iter<'s, K, V>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Iter::with_snapshot(self.column_family.clone(), self.snapshot, all_bounds())
+    }
+
+    pub fn iter_prefix<P, K, V>(&self, prefix: &P) -> Result<Iter<'s, K, V>, KvStoreError>
+    where
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(Iter::with_snapshot(
+            self.column_family.clone(),
+            self.snapshot,
+            prefix_bounds(&self.column_family, prefix)?,
+        ))
+    }
+
+    pub fn iter_range<R, P, K, V>(&self, range: R) -> Result<Iter<'s, K, V>, KvStoreError>
+    where
+        R: RangeBounds<P>,
+        P: Debug + Serialize,
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        Ok(Iter::with_snapshot(
+            self.column_family.clone(),
+            self.snapshot,
+            range_bounds(&self.column_family, range)?,
+        ))
+    }
+}
@@ -0,0 +1,328 @@
+use std::{fmt::Write, path::Path};
+
+use rocksdb::{
+    properties::{self, PropName},
+    statistics::{Histogram, HistogramData, Ticker},
+    BlockBasedOptions, Cache, Options, DB,
+};
+
+use super::{backup::Secondary, database::Database, KvStore, KvStoreError, StoredFormat};
+
+/// Statistics of [`KvStore`] returned by
+/// [`KvStore::statistics()`](super::KvStore::statistics). The block cache
+/// hits, misses and the latencies are `None` unless the statistics are
+/// enabled with
+/// [`KvStoreBuilder::enable_statistics()`](super::KvStoreBuilder::enable_statistics).
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::KvStoreBuilder;
+///
+/// let database = KvStoreBuilder::default()
+///     .enable_statistics()
+///     .set_block_cache_size(64 << 20)
+///     .build(std::env::temp_dir().join("kvstore_statistics_example"))
+///     .unwrap();
+/// database.put(&"height", &1_u64).unwrap();
+///
+/// let statistics = database.statistics().unwrap();
+/// println!("{:?}", statistics.block_cache_hit_rate());
+/// // Serve it to Prometheus.
+/// println!("{}", statistics.to_prometheus());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Statistics {
+    pub column_families: Vec<ColumnFamilyStatistics>,
+    /// The bytes used by the block cache set with
+    /// [`KvStoreBuilder::set_block_cache_size()`](super::KvStoreBuilder::set_block_cache_size).
+    pub block_cache_usage: Option<u64>,
+    pub block_cache_hits: Option<u64>,
+    pub block_cache_misses: Option<u64>,
+    pub get_latency: Option<LatencyHistogram>,
+    pub write_latency: Option<LatencyHistogram>,
+    pub seek_latency: Option<LatencyHistogram>,
+}
+
+/// Statistics of a column family, which are estimated by RocksDB.
+#[derive(Clone, Debug, Default)]
+pub struct ColumnFamilyStatistics {
+    pub name: String,
+    pub estimated_keys: u64,
+    /// The bytes of the SST files of the current version.
+    pub live_sst_size: u64,
+    /// The bytes of every SST file including the obsolete ones in use.
+    pub total_sst_size: u64,
+    /// The bytes of the active and unflushed immutable memtables. The
+    /// memtables and the compactions are `None` with
+    /// [`ConcurrencyMode::Pessimistic`](super::ConcurrencyMode::Pessimistic),
+    /// whose properties are read from a secondary instance.
+    pub memtable_size: Option<u64>,
+    pub compaction_pending: Option<bool>,
+    pub pending_compaction_bytes: Option<u64>,
+}
+
+/// Latencies of an operation in microseconds since the database is opened.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    pub count: u64,
+    pub sum: u64,
+    pub average: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl From<HistogramData> for LatencyHistogram {
+    fn from(value: HistogramData) -> Self {
+        Self {
+            count: value.count(),
+            sum: value.sum(),
+            average: value.average(),
+            median: value.median(),
+            p95: value.p95(),
+            p99: value.p99(),
+            max: value.max(),
+        }
+    }
+}
+
+impl Statistics {
+    /// The ratio of the block reads served by the block cache.
+    pub fn block_cache_hit_rate(&self) -> Option<f64> {
+        let hits = self.block_cache_hits?;
+        let total = hits + self.block_cache_misses?;
+
+        (total > 0).then(|| hits as f64 / total as f64)
+    }
+
+    /// Format the statistics in the Prometheus text exposition format, with
+    /// the metric names prefixed with `kvstore_`.
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+
+        let gauges: [Gauge; 6] = [
+            ("estimated_keys", |column_family| {
+                Some(column_family.estimated_keys)
+            }),
+            ("live_sst_bytes", |column_family| {
+                Some(column_family.live_sst_size)
+            }),
+            ("total_sst_bytes", |column_family| {
+                Some(column_family.total_sst_size)
+            }),
+            ("memtable_bytes", |column_family| {
+                column_family.memtable_size
+            }),
+            ("compaction_pending", |column_family| {
+                column_family.compaction_pending.map(u64::from)
+            }),
+            ("pending_compaction_bytes", |column_family| {
+                column_family.pending_compaction_bytes
+            }),
+        ];
+        for (name, value) in gauges {
+            let values: Vec<_> = self
+                .column_families
+                .iter()
+                .filter_map(|column_family| Some((&column_family.name, value(column_family)?)))
+                .collect();
+            if values.is_empty() {
+                continue;
+            }
+
+            let _ = writeln!(text, "# TYPE kvstore_{name} gauge");
+            for (column_family, value) in values {
+                let _ = writeln!(
+                    text,
+                    "kvstore_{name}{{column_family=\"{}\"}} {value}",
+                    escape_label(column_family),
+                );
+            }
+        }
+
+        if let Some(usage) = self.block_cache_usage {
+            let _ = writeln!(text, "# TYPE kvstore_block_cache_usage_bytes gauge");
+            let _ = writeln!(text, "kvstore_block_cache_usage_bytes {usage}");
+        }
+        for (name, count) in [
+            ("block_cache_hits", self.block_cache_hits),
+            ("block_cache_misses", self.block_cache_misses),
+        ] {
+            if let Some(count) = count {
+                let _ = writeln!(text, "# TYPE kvstore_{name} counter");
+                let _ = writeln!(text, "kvstore_{name}_total {count}");
+            }
+        }
+
+        for (name, latency) in [
+            ("get", &self.get_latency),
+            ("write", &self.write_latency),
+            ("seek", &self.seek_latency),
+        ] {
+            if let Some(latency) = latency {
+                let metric = format!("kvstore_{name}_latency_microseconds");
+                let _ = writeln!(text, "# TYPE {metric} summary");
+                for (quantile, value) in [
+                    ("0.5", latency.median),
+                    ("0.95", latency.p95),
+                    ("0.99", latency.p99),
+                    ("1", latency.max),
+                ] {
+                    let _ = writeln!(text, "{metric}{{quantile=\"{quantile}\"}} {value}");
+                }
+                let _ = writeln!(text, "{metric}_sum {}", latency.sum);
+                let _ = writeln!(text, "{metric}_count {}", latency.count);
+            }
+        }
+
+        text
+    }
+}
+
+/// The name and the value of a metric of every column family, which is
+/// `None` if it is not read.
+type Gauge = (&'static str, fn(&ColumnFamilyStatistics) -> Option<u64>);
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The options shared by every column family, which are kept to create the
+/// column families and to read the statistics.
+#[derive(Clone, Default)]
+pub(super) struct SharedOptions {
+    // The options of the database sharing the statistics with it.
+    pub(super) statistics: Option<Options>,
+    pub(super) block_cache: Option<Cache>,
+    pub(super) bloom_filter_bits: Option<f64>,
+}
+
+impl SharedOptions {
+    /// Set the block cache and the bloom filter to the options of a column
+    /// family.
+    pub(super) fn apply(&self, options: &mut Options) {
+        if self.block_cache.is_none() && self.bloom_filter_bits.is_none() {
+            return;
+        }
+
+        let mut block_based_options = BlockBasedOptions::default();
+        if let Some(block_cache) = &self.block_cache {
+            block_based_options.set_block_cache(block_cache);
+        }
+        if let Some(bloom_filter_bits) = self.bloom_filter_bits {
+            block_based_options.set_bloom_filter(bloom_filter_bits, false);
+        }
+        options.set_block_based_table_factory(&block_based_options);
+    }
+}
+
+pub(super) fn collect(kvstore: &KvStore) -> Result<Statistics, KvStoreError> {
+    let column_families = match kvstore.database.as_ref() {
+        // TransactionDB does not expose the properties, so they are read from
+        // the secondary instance, which shares the files and replays the WAL.
+        Database::Pessimistic(database, _) => {
+            let names = list_column_families(database.path())?;
+            let mut cached = kvstore.secondary.lock().unwrap();
+            let secondary = match cached.take() {
+                // The secondary instance does not open the column families
+                // created since.
+                Some(secondary) if secondary.column_families == names => {
+                    secondary
+                        .database
+                        .try_catch_up_with_primary()
+                        .map_err(KvStoreError::CatchUpWithPrimary)?;
+                    secondary
+                }
+                _others => Secondary::open(kvstore)?,
+            };
+            let database = &cached.insert(secondary).database;
+
+            column_families_statistics(
+                &names,
+                false,
+                |name| database.cf_handle(name),
+                |handle, property_name| database.property_int_value_cf(*handle, property_name),
+            )?
+        }
+        Database::Optimistic(database) => column_families_statistics(
+            &list_column_families(database.path())?,
+            true,
+            |name| database.cf_handle(name),
+            |handle, property_name| database.property_int_value_cf(handle, property_name),
+        )?,
+        Database::ReadOnly(database) => column_families_statistics(
+            &list_column_families(database.path())?,
+            true,
+            |name| database.cf_handle(name),
+            |handle, property_name| database.property_int_value_cf(handle, property_name),
+        )?,
+        Database::Backend(_) => return Err(KvStoreError::Unsupported("statistics")),
+    };
+
+    let shared_options = &kvstore.shared_options;
+    let statistics = shared_options.statistics.as_ref();
+    let latency =
+        |histogram| statistics.map(|options| options.get_histogram_data(histogram).into());
+
+    Ok(Statistics {
+        column_families,
+        block_cache_usage: shared_options
+            .block_cache
+            .as_ref()
+            .map(|block_cache| block_cache.get_usage() as u64),
+        block_cache_hits: statistics.map(|options| options.get_ticker_count(Ticker::BlockCacheHit)),
+        block_cache_misses: statistics
+            .map(|options| options.get_ticker_count(Ticker::BlockCacheMiss)),
+        get_latency: latency(Histogram::DbGet),
+        write_latency: latency(Histogram::DbWrite),
+        seek_latency: latency(Histogram::DbSeek),
+    })
+}
+
+fn list_column_families(path: &Path) -> Result<Vec<String>, KvStoreError> {
+    DB::list_cf(&Options::default(), path).map_err(KvStoreError::ReadProperty)
+}
+
+/// Read the statistics of the column families `names` which have a handle,
+/// except the one of [`StoredFormat`].
+/// The memtables and the compactions are only read from the `primary`
+/// instance.
+fn column_families_statistics<H>(
+    names: &[String],
+    primary: bool,
+    cf_handle: impl Fn(&str) -> Option<H>,
+    property: impl Fn(&H, &PropName) -> Result<Option<u64>, rocksdb::Error>,
+) -> Result<Vec<ColumnFamilyStatistics>, KvStoreError> {
+    names
+        .iter()
+        .filter(|name| *name != StoredFormat::COLUMN_FAMILY)
+        .filter_map(|name| Some((name, cf_handle(name)?)))
+        .map(|(name, handle)| {
+            let property = |property_name| {
+                property(&handle, property_name)
+                    .map(Option::unwrap_or_default)
+                    .map_err(KvStoreError::ReadProperty)
+            };
+            let primary_property =
+                |property_name| primary.then(|| property(property_name)).transpose();
+
+            Ok(ColumnFamilyStatistics {
+                name: name.clone(),
+                estimated_keys: property(properties::ESTIMATE_NUM_KEYS)?,
+                live_sst_size: property(properties::LIVE_SST_FILES_SIZE)?,
+                total_sst_size: property(properties::TOTAL_SST_FILES_SIZE)?,
+                memtable_size: primary_property(properties::CUR_SIZE_ALL_MEM_TABLES)?,
+                compaction_pending: primary_property(properties::COMPACTION_PENDING)?
+                    .map(|pending| pending > 0),
+                pending_compaction_bytes: primary_property(
+                    properties::ESTIMATE_PENDING_COMPACTION_BYTES,
+                )?,
+            })
+        })
+        .collect()
+}
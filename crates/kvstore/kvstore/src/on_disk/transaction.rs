@@ -0,0 +1,222 @@
+use std::{cell::RefCell, fmt::Debug, time::Duration};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    database::RawTransaction, watch::PendingEvent, ColumnFamily, KvStore, KvStoreError,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+/// Transaction passed to the closure of
+/// [`KvStore::transaction()`](super::KvStore::transaction). Every operation
+/// is committed atomically when the closure returns `Ok`, and rolled back
+/// when it returns `Err`.
+pub struct Transaction<'db> {
+    pub(super) transaction: RawTransaction<'db>,
+    pub(super) kvstore: &'db KvStore,
+    // Sent to the watchers after the commit.
+    pub(super) events: RefCell<Vec<PendingEvent>>,
+    // The number of the events at each savepoint.
+    savepoints: RefCell<Vec<usize>>,
+}
+
+impl<'db> Transaction<'db> {
+    pub(super) fn new(kvstore: &'db KvStore) -> Result<Self, KvStoreError> {
+        Ok(Self {
+            transaction: kvstore.database.transaction()?,
+            kvstore,
+            events: RefCell::new(Vec::new()),
+            savepoints: RefCell::new(Vec::new()),
+        })
+    }
+
+    /// Get the column family to operate on within the transaction.
+    pub fn column_family(&self, name: &str) -> Result<TransactionColumnFamily, KvStoreError> {
+        Ok(TransactionColumnFamily {
+            transaction: &self.transaction,
+            column_family: self.kvstore.column_family(name)?,
+            events: &self.events,
+        })
+    }
+
+    pub fn put<K, V>(&self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put(key, value)
+    }
+
+    pub fn put_with_ttl<K, V>(&self, key: &K, value: &V, ttl: Duration) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put_with_ttl(key, value, ttl)
+    }
+
+    /// Get the value including the writes of the transaction.
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.get(key)
+    }
+
+    /// Get the value and lock the key until the transaction ends, so that
+    /// the value is not changed by others before the transaction commits.
+    pub fn get_for_update<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .get_for_update(key)
+    }
+
+    pub fn delete<K>(&self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.delete(key)
+    }
+
+    /// Set a savepoint to roll back to with
+    /// [`Transaction::rollback_to_savepoint()`]. Savepoints are nested.
+    pub fn set_savepoint(&self) {
+        self.transaction.set_savepoint();
+        self.savepoints
+            .borrow_mut()
+            .push(self.events.borrow().len());
+    }
+
+    /// Undo the writes since the last savepoint and remove the savepoint.
+    pub fn rollback_to_savepoint(&self) -> Result<(), KvStoreError> {
+        self.transaction
+            .rollback_to_savepoint()
+            .map_err(KvStoreError::RollbackTransaction)?;
+        if let Some(length) = self.savepoints.borrow_mut().pop() {
+            self.events.borrow_mut().truncate(length);
+        }
+
+        Ok(())
+    }
+
+    /// Undo every write of the transaction. The transaction can still be used
+    /// afterwards.
+    pub fn rollback(&self) -> Result<(), KvStoreError> {
+        self.transaction
+            .rollback()
+            .map_err(KvStoreError::RollbackTransaction)?;
+        self.events.borrow_mut().clear();
+        self.savepoints.borrow_mut().clear();
+
+        Ok(())
+    }
+}
+
+/// Column family of [`Transaction`] returned by
+/// [`Transaction::column_family()`].
+pub struct TransactionColumnFamily<'tx, 'db> {
+    transaction: &'tx RawTransaction<'db>,
+    column_family: ColumnFamily<'db>,
+    events: &'tx RefCell<Vec<PendingEvent>>,
+}
+
+impl TransactionColumnFamily<'_, '_> {
+    pub fn put<K, V>(&self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+        let value_vec = self
+            .column_family
+            .encode(self.column_family.serialize_value(value)?);
+
+        self.transaction
+            .put_cf(&self.column_family.handle, &key_vec, &value_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Put))?;
+        self.column_family
+            .pend(&mut self.events.borrow_mut(), &key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn put_with_ttl<K, V>(&self, key: &K, value: &V, ttl: Duration) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+        let value_vec = self
+            .column_family
+            .encode_with_ttl(self.column_family.serialize_value(value)?, ttl)?;
+
+        self.transaction
+            .put_cf(&self.column_family.handle, &key_vec, &value_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Put))?;
+        self.column_family
+            .pend(&mut self.events.borrow_mut(), &key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn get<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+
+        let value_slice = self
+            .transaction
+            .get_pinned_cf(&self.column_family.handle, key_vec)
+            .map_err(KvStoreError::Get)?;
+        let value_slice = value_slice
+            .as_deref()
+            .and_then(|value_slice| self.column_family.decode(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.column_family.deserialize_value(value_slice)?;
+
+        Ok(value)
+    }
+
+    pub fn get_for_update<K, V>(&self, key: &K) -> Result<V, KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+
+        let value_slice = self
+            .transaction
+            .get_pinned_for_update_cf(&self.column_family.handle, key_vec, true)
+            .map_err(KvStoreError::lock(KvStoreError::GetMut))?;
+        let value_slice = value_slice
+            .as_deref()
+            .and_then(|value_slice| self.column_family.decode(value_slice))
+            .ok_or(KvStoreError::NoneType)?;
+        let value: V = self.column_family.deserialize_value(value_slice)?;
+
+        Ok(value)
+    }
+
+    pub fn delete<K>(&self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+
+        self.transaction
+            .delete_cf(&self.column_family.handle, &key_vec)
+            .map_err(KvStoreError::lock(KvStoreError::Delete))?;
+        self.column_family
+            .pend(&mut self.events.borrow_mut(), &key_vec, None);
+
+        Ok(())
+    }
+}
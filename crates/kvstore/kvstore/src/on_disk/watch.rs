@@ -0,0 +1,282 @@
+use std::{
+    fmt::Debug,
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard, RwLock, Weak},
+};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+use tokio::sync::mpsc::{
+    error::TryRecvError, unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
+
+use super::{column_family::decode_key, schema::deserialize_value, KvStoreError};
+use crate::data_type::{Codec, KeyEncoding};
+
+/// Change of a watched key returned by [`Watch`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum WatchEvent<K, V> {
+    Put { key: K, value: V },
+    Delete { key: K },
+}
+
+/// Subscription to the changes of a key or the keys with a prefix, returned
+/// by [`KvStore::watch()`](super::KvStore::watch) and
+/// [`KvStore::watch_prefix()`](super::KvStore::watch_prefix). The events are
+/// sent after the changes are committed in the order of the commits, and the
+/// subscription ends when it is dropped.
+///
+/// The events are buffered without limit until they are received, so the
+/// events should be received as long as the subscription is alive.
+///
+/// # Examples
+///
+/// ```rust
+/// use kvstore::{KeyEncoding, KvStoreBuilder, WatchEvent};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let database = KvStoreBuilder::default()
+///     .set_key_encoding(KeyEncoding::Ordered)
+///     .build(std::env::temp_dir().join("kvstore_watch_example"))
+///     .unwrap();
+/// let mut watch = database
+///     .watch_prefix::<_, (String, u64), String>(&("Task",))
+///     .unwrap();
+///
+/// database
+///     .put(&("Task", 1_u64), &"pending".to_owned())
+///     .unwrap();
+/// database.delete(&("Task", 1_u64)).unwrap();
+///
+/// let key = ("Task".to_owned(), 1);
+/// assert_eq!(
+///     watch.recv().await.unwrap().unwrap(),
+///     WatchEvent::Put {
+///         key: key.clone(),
+///         value: "pending".to_owned()
+///     }
+/// );
+/// assert_eq!(
+///     watch.recv().await.unwrap().unwrap(),
+///     WatchEvent::Delete { key }
+/// );
+/// # }
+/// ```
+pub struct Watch<K, V> {
+    receiver: UnboundedReceiver<RawEvent>,
+    // Weak so that the events end when the database is dropped.
+    watchers: Weak<Watchers>,
+    codec: Arc<dyn Codec>,
+    key_encoding: KeyEncoding,
+    _type: PhantomData<fn() -> (K, V)>,
+}
+
+impl<K, V> Watch<K, V>
+where
+    K: Debug + DeserializeOwned + Serialize,
+    V: Debug + DeserializeOwned + Serialize,
+{
+    pub(super) fn new(
+        receiver: UnboundedReceiver<RawEvent>,
+        watchers: &Arc<Watchers>,
+        codec: Arc<dyn Codec>,
+        key_encoding: KeyEncoding,
+    ) -> Self {
+        Self {
+            receiver,
+            watchers: Arc::downgrade(watchers),
+            codec,
+            key_encoding,
+            _type: PhantomData,
+        }
+    }
+
+    /// Wait for the next event. Returns `None` if the database is dropped.
+    pub async fn recv(&mut self) -> Option<Result<WatchEvent<K, V>, KvStoreError>> {
+        let event = self.receiver.recv().await?;
+
+        Some(event.typed(self.codec.as_ref(), self.key_encoding))
+    }
+
+    /// Get the next event if there is any.
+    pub fn try_recv(&mut self) -> Option<Result<WatchEvent<K, V>, KvStoreError>> {
+        match self.receiver.try_recv() {
+            Ok(event) => Some(event.typed(self.codec.as_ref(), self.key_encoding)),
+            Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// Block the thread until the next event. Must not be called in an
+    /// async context.
+    pub fn blocking_recv(&mut self) -> Option<Result<WatchEvent<K, V>, KvStoreError>> {
+        let event = self.receiver.blocking_recv()?;
+
+        Some(event.typed(self.codec.as_ref(), self.key_encoding))
+    }
+}
+
+impl<K, V> Drop for Watch<K, V> {
+    fn drop(&mut self) {
+        self.receiver.close();
+        if let Some(watchers) = self.watchers.upgrade() {
+            watchers.remove_closed();
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct RawEvent {
+    key_vec: Vec<u8>,
+    value_vec: Option<Vec<u8>>,
+}
+
+impl RawEvent {
+    fn typed<K, V>(
+        self,
+        codec: &dyn Codec,
+        key_encoding: KeyEncoding,
+    ) -> Result<WatchEvent<K, V>, KvStoreError>
+    where
+        K: Debug + DeserializeOwned + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key: K = decode_key(key_encoding, codec, &self.key_vec)?;
+
+        Ok(match self.value_vec {
+            Some(value_vec) => WatchEvent::Put {
+                key,
+                value: deserialize_value(codec, &value_vec)?,
+            },
+            None => WatchEvent::Delete { key },
+        })
+    }
+}
+
+/// Change to send after the transaction or the batch which made it is
+/// committed.
+pub(super) struct PendingEvent {
+    column_family: String,
+    event: RawEvent,
+}
+
+pub(super) enum Target {
+    Key(Vec<u8>),
+    Prefix(Vec<u8>),
+}
+
+struct Watcher {
+    column_family: String,
+    target: Target,
+    sender: UnboundedSender<RawEvent>,
+}
+
+impl Watcher {
+    fn is_watching(&self, column_family: &str, key_vec: &[u8]) -> bool {
+        self.column_family == column_family
+            && match &self.target {
+                Target::Key(watched) => watched.as_slice() == key_vec,
+                Target::Prefix(prefix) => key_vec.starts_with(prefix),
+            }
+    }
+}
+
+/// Subscriptions of a [`KvStore`](super::KvStore).
+#[derive(Default)]
+pub(super) struct Watchers {
+    watchers: RwLock<Vec<Watcher>>,
+    // Held from before the commits of the watched keys until their events are
+    // sent, so that the events are sent in the order of the commits.
+    order: Mutex<()>,
+}
+
+impl Watchers {
+    pub(super) fn subscribe(
+        &self,
+        column_family: &str,
+        target: Target,
+    ) -> UnboundedReceiver<RawEvent> {
+        let (sender, receiver) = unbounded_channel();
+        self.watchers.write().unwrap().push(Watcher {
+            column_family: column_family.to_owned(),
+            target,
+            sender,
+        });
+
+        receiver
+    }
+
+    pub(super) fn is_watching(&self, column_family: &str, key_vec: &[u8]) -> bool {
+        self.watchers
+            .read()
+            .unwrap()
+            .iter()
+            .any(|watcher| watcher.is_watching(column_family, key_vec))
+    }
+
+    /// Keep the events of the key in the order of the commits until the
+    /// guard is dropped after [`Watchers::notify()`]. Returns `None` if the key
+    /// is not watched.
+    pub(super) fn order(&self, column_family: &str, key_vec: &[u8]) -> Option<MutexGuard<()>> {
+        self.is_watching(column_family, key_vec)
+            .then(|| self.order.lock().unwrap())
+    }
+
+    /// [`Watchers::order()`] for the events of a transaction or a batch.
+    pub(super) fn order_pending(&self, events: &[PendingEvent]) -> Option<MutexGuard<()>> {
+        (!events.is_empty()).then(|| self.order.lock().unwrap())
+    }
+
+    /// Send the change to the subscriptions of the key. `value_slice` is
+    /// `None` if the key is deleted.
+    pub(super) fn notify(&self, column_family: &str, key_vec: &[u8], value_slice: Option<&[u8]>) {
+        let watchers = self.watchers.read().unwrap();
+        for watcher in watchers
+            .iter()
+            .filter(|watcher| watcher.is_watching(column_family, key_vec))
+        {
+            let event = RawEvent {
+                key_vec: key_vec.to_vec(),
+                value_vec: value_slice.map(<[u8]>::to_vec),
+            };
+            let _ = watcher.sender.send(event);
+        }
+    }
+
+    /// Remove the subscriptions which have been dropped.
+    fn remove_closed(&self) {
+        self.watchers
+            .write()
+            .unwrap()
+            .retain(|watcher| !watcher.sender.is_closed());
+    }
+
+    /// Keep the change to send with [`Watchers::notify_pending()`] if the key
+    /// is watched.
+    pub(super) fn pend(
+        &self,
+        events: &mut Vec<PendingEvent>,
+        column_family: &str,
+        key_vec: &[u8],
+        value_slice: Option<&[u8]>,
+    ) {
+        if self.is_watching(column_family, key_vec) {
+            events.push(PendingEvent {
+                column_family: column_family.to_owned(),
+                event: RawEvent {
+                    key_vec: key_vec.to_vec(),
+                    value_vec: value_slice.map(<[u8]>::to_vec),
+                },
+            });
+        }
+    }
+
+    pub(super) fn notify_pending(&self, events: Vec<PendingEvent>) {
+        for PendingEvent {
+            column_family,
+            event,
+        } in events
+        {
+            self.notify(&column_family, &event.key_vec, event.value_vec.as_deref());
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fmt::Debug, time::Duration};
+
+use serde::{de::DeserializeOwned, ser::Serialize};
+
+use super::{
+    database::RawWriteBatch, watch::PendingEvent, ColumnFamily, KvStore, KvStoreError,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+/// Batch of writes passed to the closure of
+/// [`KvStore::write_batch()`](super::KvStore::write_batch). The writes are
+/// applied atomically when the closure returns `Ok`, and discarded when it
+/// returns `Err`.
+///
+/// Unlike [`Transaction`](super::Transaction), the keys are not locked and the
+/// writes are not visible until the batch is applied, which makes a batch
+/// much cheaper for writing many keys at once.
+pub struct WriteBatch<'db> {
+    pub(super) batch: RawWriteBatch,
+    pub(super) kvstore: &'db KvStore,
+    // Sent to the watchers after the batch is applied.
+    pub(super) events: Vec<PendingEvent>,
+}
+
+impl<'db> WriteBatch<'db> {
+    /// Get the column family to write to within the batch.
+    pub fn column_family(
+        &mut self,
+        name: &str,
+    ) -> Result<WriteBatchColumnFamily<'_, 'db>, KvStoreError> {
+        Ok(WriteBatchColumnFamily {
+            column_family: self.kvstore.column_family(name)?,
+            batch: &mut self.batch,
+            events: &mut self.events,
+        })
+    }
+
+    pub fn put<K, V>(&mut self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put(key, value)
+    }
+
+    pub fn put_with_ttl<K, V>(
+        &mut self,
+        key: &K,
+        value: &V,
+        ttl: Duration,
+    ) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?
+            .put_with_ttl(key, value, ttl)
+    }
+
+    pub fn delete<K>(&mut self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        self.column_family(DEFAULT_COLUMN_FAMILY_NAME)?.delete(key)
+    }
+
+    /// The number of the writes in the batch.
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+}
+
+/// Column family of [`WriteBatch`] returned by
+/// [`WriteBatch::column_family()`].
+pub struct WriteBatchColumnFamily<'b, 'db> {
+    batch: &'b mut RawWriteBatch,
+    column_family: ColumnFamily<'db>,
+    events: &'b mut Vec<PendingEvent>,
+}
+
+impl WriteBatchColumnFamily<'_, '_> {
+    pub fn put<K, V>(&mut self, key: &K, value: &V) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+        let value_vec = self
+            .column_family
+            .encode(self.column_family.serialize_value(value)?);
+
+        self.batch
+            .put_cf(&self.column_family.handle, &key_vec, &value_vec);
+        self.column_family
+            .pend(self.events, &key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn put_with_ttl<K, V>(
+        &mut self,
+        key: &K,
+        value: &V,
+        ttl: Duration,
+    ) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+        V: Debug + DeserializeOwned + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+        let value_vec = self
+            .column_family
+            .encode_with_ttl(self.column_family.serialize_value(value)?, ttl)?;
+
+        self.batch
+            .put_cf(&self.column_family.handle, &key_vec, &value_vec);
+        self.column_family
+            .pend(self.events, &key_vec, Some(&value_vec));
+
+        Ok(())
+    }
+
+    pub fn delete<K>(&mut self, key: &K) -> Result<(), KvStoreError>
+    where
+        K: Debug + Serialize,
+    {
+        let key_vec = self.column_family.serialize_key(key)?;
+
+        self.batch.delete_cf(&self.column_family.handle, &key_vec);
+        self.column_family.pend(self.events, &key_vec, None);
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-#[cfg(any(feature = "full", feature = "context"))]
+#[cfg(any(feature = "full", feature = "context", feature = "context-kvstore"))]
 pub use context;
 #[cfg(any(
     feature = "full",
@@ -11,7 +11,12 @@ pub mod json_rpc {
     #[cfg(any(feature = "full", feature = "json-rpc-server"))]
     pub use json_rpc_server as server;
 }
-#[cfg(any(feature = "full", feature = "kvstore-bytes", feature = "kvstore-json"))]
+#[cfg(any(
+    feature = "full",
+    feature = "kvstore-bincode",
+    feature = "kvstore-cbor",
+    feature = "kvstore-json"
+))]
 pub use kvstore;
 #[cfg(any(feature = "full", feature = "liveness-radius"))]
 pub mod liveness {